@@ -0,0 +1,145 @@
+//! OneBot 与 mirai-api-http 适配器共用的部分
+
+use std::{
+    collections::VecDeque,
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine;
+
+use crate::{
+    message::MessageTarget,
+    network::{download, message::MessageReceipt, NetworkResult},
+};
+
+/// 记录的消息数量，更早的消息无法再通过消息 id 撤回
+const MESSAGE_STORE_CAPACITY: usize = 4096;
+/// 发送的图片大小上限
+const MAX_IMAGE_SIZE: usize = 30 * 1024 * 1024;
+
+/// 记录的发送凭据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StoredReceipt {
+    Ready(MessageReceipt),
+    /// bot 发出的群消息，服务器分配的 seq 尚未随推送回来，此时无法撤回
+    Pending(MessageReceipt),
+}
+
+/// 将适配器分配的消息 id 与发送凭据对应起来
+#[derive(Debug, Default)]
+pub(crate) struct MessageStore {
+    next_id: i32,
+    receipts: VecDeque<(i32, StoredReceipt)>,
+}
+
+impl MessageStore {
+    /// 记录收到的消息
+    pub(crate) fn insert(&mut self, receipt: MessageReceipt) -> i32 {
+        self.push(StoredReceipt::Ready(receipt))
+    }
+
+    /// 记录 bot 发出的消息，群消息需要等待 [`MessageStore::confirm`]
+    pub(crate) fn insert_sent(&mut self, receipt: MessageReceipt) -> i32 {
+        match receipt.target {
+            MessageTarget::Group(_) => self.push(StoredReceipt::Pending(receipt)),
+            MessageTarget::Friend(_) => self.push(StoredReceipt::Ready(receipt)),
+        }
+    }
+
+    fn push(&mut self, receipt: StoredReceipt) -> i32 {
+        self.next_id = self.next_id.wrapping_add(1);
+        if self.receipts.len() >= MESSAGE_STORE_CAPACITY {
+            self.receipts.pop_front();
+        }
+        self.receipts.push_back((self.next_id, receipt));
+        self.next_id
+    }
+
+    /// 用推送回来的群消息填入服务器分配的 seq
+    pub(crate) fn confirm(&mut self, target: MessageTarget, random: u32, seq: u32) {
+        for (_, stored) in self.receipts.iter_mut().rev() {
+            if let StoredReceipt::Pending(r) = *stored {
+                if r.target == target && r.random == random {
+                    *stored = StoredReceipt::Ready(MessageReceipt { seq, ..r });
+                    return;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, id: i32) -> Option<StoredReceipt> {
+        self.receipts
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, r)| *r)
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 读取要发送的图片，`file` 可以是 `base64://`、`file://`、http 地址或本地路径
+pub(crate) async fn load_file(file: &str) -> NetworkResult<Vec<u8>> {
+    if let Some(data) = file.strip_prefix("base64://") {
+        return base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e).into());
+    }
+    if file.starts_with("http://") || file.starts_with("https://") {
+        return download(file, MAX_IMAGE_SIZE, None).await;
+    }
+    let path = file.strip_prefix("file://").unwrap_or(file);
+    Ok(tokio::fs::read(path).await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_message_store_capacity() {
+        let mut store = MessageStore::default();
+        let receipt = |seq| MessageReceipt {
+            target: MessageTarget::Group(10001),
+            seq,
+            random: 0,
+            time: 0,
+        };
+        let first = store.insert(receipt(1));
+        for seq in 2..=MESSAGE_STORE_CAPACITY as u32 + 1 {
+            store.insert(receipt(seq));
+        }
+        assert!(store.get(first).is_none());
+        assert_eq!(store.get(first + 1), Some(StoredReceipt::Ready(receipt(2))));
+    }
+
+    #[test]
+    fn test_confirm_group_seq() {
+        let mut store = MessageStore::default();
+        let sent = MessageReceipt {
+            target: MessageTarget::Group(10001),
+            seq: 7,
+            random: 42,
+            time: 0,
+        };
+        let id = store.insert_sent(sent);
+        assert_eq!(store.get(id), Some(StoredReceipt::Pending(sent)));
+        store.confirm(MessageTarget::Group(10002), 42, 100);
+        store.confirm(MessageTarget::Group(10001), 42, 100);
+        assert_eq!(
+            store.get(id),
+            Some(StoredReceipt::Ready(MessageReceipt { seq: 100, ..sent }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_base64() {
+        assert_eq!(load_file("base64://aGk=").await.unwrap(), b"hi");
+        assert!(load_file("base64://!").await.is_err());
+    }
+}
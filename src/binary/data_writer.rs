@@ -52,6 +52,10 @@ impl DataWriter {
         func(&mut s)?;
         Ok(s)
     }
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buff
+    }
+
     pub fn write_data<T: WriteTo>(&mut self, data: &T) -> io::Result<()> {
         data.write_to(self)
     }
@@ -0,0 +1,539 @@
+//! 无需 schema 的 JCE (Tars) 编解码，`OnlinePush` 等旧服务仍使用 JCE 而非 protobuf
+//! [参考](https://github.com/Mrs4s/MiraiGo/tree/master/binary/jce)
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Cursor, Read},
+};
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+const TYPE_BYTE: u8 = 0;
+const TYPE_SHORT: u8 = 1;
+const TYPE_INT: u8 = 2;
+const TYPE_LONG: u8 = 3;
+const TYPE_FLOAT: u8 = 4;
+const TYPE_DOUBLE: u8 = 5;
+const TYPE_STRING1: u8 = 6;
+const TYPE_STRING4: u8 = 7;
+const TYPE_MAP: u8 = 8;
+const TYPE_LIST: u8 = 9;
+const TYPE_STRUCT_BEGIN: u8 = 10;
+const TYPE_STRUCT_END: u8 = 11;
+const TYPE_ZERO: u8 = 12;
+const TYPE_SIMPLE_LIST: u8 = 13;
+/// 结构体、列表与字典的最大嵌套层数，避免恶意数据耗尽栈空间
+const MAX_DEPTH: usize = 64;
+
+/// JCE 字段的值，各种长度的整数统一为 `Int`，`SimpleList` 为 `Bytes`
+#[derive(Debug, Clone, PartialEq)]
+pub enum JceValue {
+    Int(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Map(Vec<(JceValue, JceValue)>),
+    List(Vec<JceValue>),
+    Struct(JceStruct),
+    Bytes(Vec<u8>),
+}
+
+impl JceValue {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JceValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JceValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            JceValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_struct(&self) -> Option<&JceStruct> {
+        match self {
+            JceValue::Struct(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[JceValue]> {
+        match self {
+            JceValue::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(JceValue, JceValue)]> {
+        match self {
+            JceValue::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for JceValue {
+            fn from(v: $t) -> Self {
+                JceValue::Int(v as i64)
+            }
+        })*
+    };
+}
+
+impl_from_int!(bool, u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl From<f32> for JceValue {
+    fn from(v: f32) -> Self {
+        JceValue::Float(v)
+    }
+}
+
+impl From<f64> for JceValue {
+    fn from(v: f64) -> Self {
+        JceValue::Double(v)
+    }
+}
+
+impl From<String> for JceValue {
+    fn from(v: String) -> Self {
+        JceValue::String(v)
+    }
+}
+
+impl From<&str> for JceValue {
+    fn from(v: &str) -> Self {
+        JceValue::String(v.to_string())
+    }
+}
+
+impl From<Vec<u8>> for JceValue {
+    fn from(v: Vec<u8>) -> Self {
+        JceValue::Bytes(v)
+    }
+}
+
+impl From<JceStruct> for JceValue {
+    fn from(v: JceStruct) -> Self {
+        JceValue::Struct(v)
+    }
+}
+
+impl From<Vec<JceStruct>> for JceValue {
+    fn from(v: Vec<JceStruct>) -> Self {
+        JceValue::List(v.into_iter().map(JceValue::Struct).collect())
+    }
+}
+
+impl From<Vec<JceValue>> for JceValue {
+    fn from(v: Vec<JceValue>) -> Self {
+        JceValue::List(v)
+    }
+}
+
+impl From<Vec<(JceValue, JceValue)>> for JceValue {
+    fn from(v: Vec<(JceValue, JceValue)>) -> Self {
+        JceValue::Map(v)
+    }
+}
+
+/// 动态构建或解码的 JCE 结构体，key 为 tag
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JceStruct(BTreeMap<u8, JceValue>);
+
+impl JceStruct {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn with(mut self, tag: u8, value: impl Into<JceValue>) -> Self {
+        self.set(tag, value);
+        self
+    }
+
+    pub fn set(&mut self, tag: u8, value: impl Into<JceValue>) {
+        self.0.insert(tag, value.into());
+    }
+
+    pub fn get(&self, tag: u8) -> Option<&JceValue> {
+        self.0.get(&tag)
+    }
+
+    pub fn get_i64(&self, tag: u8) -> Option<i64> {
+        self.get(tag)?.as_i64()
+    }
+
+    pub fn get_string(&self, tag: u8) -> Option<String> {
+        self.get(tag)?.as_str().map(str::to_string)
+    }
+
+    pub fn get_bytes(&self, tag: u8) -> Option<&[u8]> {
+        self.get(tag)?.as_bytes()
+    }
+
+    pub fn get_struct(&self, tag: u8) -> Option<&JceStruct> {
+        self.get(tag)?.as_struct()
+    }
+
+    pub fn get_list(&self, tag: u8) -> Option<&[JceValue]> {
+        self.get(tag)?.as_list()
+    }
+
+    /// 结构体列表，跳过类型不符的元素
+    pub fn get_struct_list(&self, tag: u8) -> Vec<&JceStruct> {
+        self.get_list(tag)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(JceValue::as_struct)
+            .collect()
+    }
+
+    pub fn get_map(&self, tag: u8) -> Option<&[(JceValue, JceValue)]> {
+        self.get(tag)?.as_map()
+    }
+
+    /// 编码为不带首尾标记的字段序列
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256);
+        for (tag, value) in &self.0 {
+            write_value(&mut buf, *tag, value);
+        }
+        buf
+    }
+
+    /// 解码字段序列，遇到结构体结束标记或数据结束时停止
+    pub fn decode(data: &[u8]) -> io::Result<Self> {
+        read_struct(&mut Cursor::new(data), 0)
+    }
+}
+
+fn write_head(buf: &mut Vec<u8>, tag: u8, ty: u8) {
+    if tag < 15 {
+        buf.push(tag << 4 | ty);
+    } else {
+        buf.push(0xf0 | ty);
+        buf.push(tag);
+    }
+}
+
+fn write_int(buf: &mut Vec<u8>, tag: u8, v: i64) {
+    if v == 0 {
+        write_head(buf, tag, TYPE_ZERO);
+    } else if v >= i8::MIN as i64 && v <= i8::MAX as i64 {
+        write_head(buf, tag, TYPE_BYTE);
+        buf.push(v as u8);
+    } else if v >= i16::MIN as i64 && v <= i16::MAX as i64 {
+        write_head(buf, tag, TYPE_SHORT);
+        buf.extend_from_slice(&(v as i16).to_be_bytes());
+    } else if v >= i32::MIN as i64 && v <= i32::MAX as i64 {
+        write_head(buf, tag, TYPE_INT);
+        buf.extend_from_slice(&(v as i32).to_be_bytes());
+    } else {
+        write_head(buf, tag, TYPE_LONG);
+        buf.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+fn write_value(buf: &mut Vec<u8>, tag: u8, value: &JceValue) {
+    match value {
+        JceValue::Int(v) => write_int(buf, tag, *v),
+        JceValue::Float(v) => {
+            write_head(buf, tag, TYPE_FLOAT);
+            buf.extend_from_slice(&v.to_be_bytes());
+        }
+        JceValue::Double(v) => {
+            write_head(buf, tag, TYPE_DOUBLE);
+            buf.extend_from_slice(&v.to_be_bytes());
+        }
+        JceValue::String(s) => {
+            if s.len() <= u8::MAX as usize {
+                write_head(buf, tag, TYPE_STRING1);
+                buf.push(s.len() as u8);
+            } else {
+                write_head(buf, tag, TYPE_STRING4);
+                buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+            }
+            buf.extend_from_slice(s.as_bytes());
+        }
+        JceValue::Map(m) => {
+            write_head(buf, tag, TYPE_MAP);
+            write_int(buf, 0, m.len() as i64);
+            for (k, v) in m {
+                write_value(buf, 0, k);
+                write_value(buf, 1, v);
+            }
+        }
+        JceValue::List(l) => {
+            write_head(buf, tag, TYPE_LIST);
+            write_int(buf, 0, l.len() as i64);
+            for v in l {
+                write_value(buf, 0, v);
+            }
+        }
+        JceValue::Struct(s) => {
+            write_head(buf, tag, TYPE_STRUCT_BEGIN);
+            buf.extend(s.encode());
+            write_head(buf, 0, TYPE_STRUCT_END);
+        }
+        JceValue::Bytes(b) => {
+            write_head(buf, tag, TYPE_SIMPLE_LIST);
+            write_head(buf, 0, TYPE_BYTE);
+            write_int(buf, 0, b.len() as i64);
+            buf.extend_from_slice(b);
+        }
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_head(reader: &mut Cursor<&[u8]>) -> io::Result<(u8, u8)> {
+    let b = reader.read_u8()?;
+    let ty = b & 0x0f;
+    let tag = match b >> 4 {
+        15 => reader.read_u8()?,
+        tag => tag,
+    };
+    Ok((tag, ty))
+}
+
+fn read_exact_vec(reader: &mut Cursor<&[u8]>, size: usize) -> io::Result<Vec<u8>> {
+    let remain = reader.get_ref().len() - reader.position() as usize;
+    if size > remain {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "jce field out of range",
+        ));
+    }
+    let mut buf = vec![0u8; size];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_length(reader: &mut Cursor<&[u8]>, depth: usize) -> io::Result<usize> {
+    let (_, ty) = read_head(reader)?;
+    match read_value(reader, ty, depth + 1)? {
+        JceValue::Int(n) if n >= 0 => Ok(n as usize),
+        _ => Err(invalid("invalid jce length")),
+    }
+}
+
+fn read_value(reader: &mut Cursor<&[u8]>, ty: u8, depth: usize) -> io::Result<JceValue> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("jce nesting too deep"));
+    }
+    Ok(match ty {
+        TYPE_BYTE => JceValue::Int(reader.read_i8()? as i64),
+        TYPE_SHORT => JceValue::Int(reader.read_i16::<BigEndian>()? as i64),
+        TYPE_INT => JceValue::Int(reader.read_i32::<BigEndian>()? as i64),
+        TYPE_LONG => JceValue::Int(reader.read_i64::<BigEndian>()?),
+        TYPE_FLOAT => JceValue::Float(reader.read_f32::<BigEndian>()?),
+        TYPE_DOUBLE => JceValue::Double(reader.read_f64::<BigEndian>()?),
+        TYPE_STRING1 | TYPE_STRING4 => {
+            let size = if ty == TYPE_STRING1 {
+                reader.read_u8()? as usize
+            } else {
+                reader.read_u32::<BigEndian>()? as usize
+            };
+            let data = read_exact_vec(reader, size)?;
+            JceValue::String(String::from_utf8_lossy(&data).to_string())
+        }
+        TYPE_MAP => {
+            let size = read_length(reader, depth)?;
+            let mut map = Vec::with_capacity(size.min(1024));
+            for _ in 0..size {
+                let (_, kt) = read_head(reader)?;
+                let k = read_value(reader, kt, depth + 1)?;
+                let (_, vt) = read_head(reader)?;
+                map.push((k, read_value(reader, vt, depth + 1)?));
+            }
+            JceValue::Map(map)
+        }
+        TYPE_LIST => {
+            let size = read_length(reader, depth)?;
+            let mut list = Vec::with_capacity(size.min(1024));
+            for _ in 0..size {
+                let (_, t) = read_head(reader)?;
+                list.push(read_value(reader, t, depth + 1)?);
+            }
+            JceValue::List(list)
+        }
+        TYPE_STRUCT_BEGIN => JceValue::Struct(read_struct(reader, depth + 1)?),
+        TYPE_ZERO => JceValue::Int(0),
+        TYPE_SIMPLE_LIST => {
+            read_head(reader)?;
+            let size = read_length(reader, depth)?;
+            JceValue::Bytes(read_exact_vec(reader, size)?)
+        }
+        t => return Err(invalid(format!("unsupported jce type {}", t))),
+    })
+}
+
+fn read_struct(reader: &mut Cursor<&[u8]>, depth: usize) -> io::Result<JceStruct> {
+    let mut fields = BTreeMap::new();
+    while (reader.position() as usize) < reader.get_ref().len() {
+        let (tag, ty) = read_head(reader)?;
+        if ty == TYPE_STRUCT_END {
+            break;
+        }
+        fields.insert(tag, read_value(reader, ty, depth)?);
+    }
+    Ok(JceStruct(fields))
+}
+
+/// JCE 服务的外层包 `RequestPacket`，`sBuffer` 为 `RequestDataVersion3` 的
+/// `map<string, bytes>`，其中每个值都是以结构体开始标记包裹的数据
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestPacket {
+    pub version: i16,
+    pub request_id: i32,
+    pub servant_name: String,
+    pub func_name: String,
+    pub buffer: Vec<u8>,
+}
+
+impl RequestPacket {
+    pub fn new(servant_name: &str, func_name: &str) -> Self {
+        Self {
+            version: 3,
+            servant_name: servant_name.to_string(),
+            func_name: func_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: i32) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    /// 以 `RequestDataVersion3` 写入单个结构体
+    pub fn with_data(mut self, key: &str, data: JceStruct) -> Self {
+        let mut value = Vec::new();
+        write_value(&mut value, 0, &JceValue::Struct(data));
+        let map = JceStruct::new().with(0, vec![(JceValue::from(key), JceValue::Bytes(value))]);
+        self.buffer = map.encode();
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        JceStruct::new()
+            .with(1, self.version)
+            .with(2, 0u8)
+            .with(3, 0i32)
+            .with(4, self.request_id)
+            .with(5, self.servant_name.as_str())
+            .with(6, self.func_name.as_str())
+            .with(7, self.buffer.clone())
+            .with(8, 0i32)
+            .with(9, Vec::<(JceValue, JceValue)>::new())
+            .with(10, Vec::<(JceValue, JceValue)>::new())
+            .encode()
+    }
+
+    pub fn decode(data: &[u8]) -> io::Result<Self> {
+        let pkt = JceStruct::decode(data)?;
+        Ok(Self {
+            version: pkt.get_i64(1).unwrap_or_default() as i16,
+            request_id: pkt.get_i64(4).unwrap_or_default() as i32,
+            servant_name: pkt.get_string(5).unwrap_or_default(),
+            func_name: pkt.get_string(6).unwrap_or_default(),
+            buffer: pkt.get_bytes(7).unwrap_or_default().to_vec(),
+        })
+    }
+
+    /// 取出 `sBuffer` 中的全部数据，兼容 `RequestDataVersion2` 的两层 map
+    pub fn data(&self) -> io::Result<HashMap<String, JceStruct>> {
+        let buffer = JceStruct::decode(&self.buffer)?;
+        let mut res = HashMap::new();
+        for (k, v) in buffer.get_map(0).unwrap_or_default() {
+            let Some(key) = k.as_str() else {
+                continue;
+            };
+            let value = match v {
+                JceValue::Map(inner) => match inner.first() {
+                    Some((_, JceValue::Bytes(b))) => b.as_slice(),
+                    _ => continue,
+                },
+                JceValue::Bytes(b) => b.as_slice(),
+                _ => continue,
+            };
+            // 数据包裹在 tag 0 的结构体中
+            if let Some(value) = JceStruct::decode(value)?.get_struct(0) {
+                res.insert(key.to_string(), value.clone());
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| {
+            let mut value = JceValue::Int(1);
+            for _ in 0..depth {
+                value = JceValue::Struct(JceStruct::new().with(0, value));
+            }
+            JceStruct::new().with(0, value).encode()
+        };
+        assert!(JceStruct::decode(&nested(MAX_DEPTH)).is_ok());
+        assert!(JceStruct::decode(&nested(MAX_DEPTH + 1)).is_err());
+        // 只有开始标记的结构体与列表不会耗尽栈空间
+        assert!(JceStruct::decode(&[0x0a; 100_000]).is_err());
+        assert!(JceStruct::decode(&[0x09, 0x00, 0x01].repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_struct_round_trip() {
+        let data = JceStruct::new()
+            .with(0, 10086i64)
+            .with(1, 300u16)
+            .with(2, 0x0012_3456_789a_i64)
+            .with(3, "MiraiGo")
+            .with(4, "x".repeat(300))
+            .with(5, vec![1u8, 2, 3])
+            .with(6, vec![JceStruct::new().with(0, -1i32)])
+            .with(7, vec![(JceValue::from("k"), JceValue::from(1u8))])
+            .with(8, JceStruct::new().with(1, "inner"))
+            .with(20, 0u8)
+            .encode();
+
+        let s = JceStruct::decode(&data).unwrap();
+        assert_eq!(s.get_i64(0), Some(10086));
+        assert_eq!(s.get_i64(1), Some(300));
+        assert_eq!(s.get_i64(2), Some(0x0012_3456_789a));
+        assert_eq!(s.get_string(3).unwrap(), "MiraiGo");
+        assert_eq!(s.get_string(4).unwrap().len(), 300);
+        assert_eq!(s.get_bytes(5), Some(&[1u8, 2, 3][..]));
+        assert_eq!(s.get_struct_list(6)[0].get_i64(0), Some(-1));
+        assert_eq!(s.get_map(7).unwrap()[0].1, JceValue::Int(1));
+        assert_eq!(s.get_struct(8).unwrap().get_string(1).unwrap(), "inner");
+        assert_eq!(s.get_i64(20), Some(0));
+    }
+
+    #[test]
+    fn test_request_packet() {
+        let pkt = RequestPacket::new("OnlinePush", "SvcRespPushMsg")
+            .with_request_id(7)
+            .with_data("resp", JceStruct::new().with(0, 12345i64));
+        let decoded = RequestPacket::decode(&pkt.encode()).unwrap();
+        assert_eq!(decoded, pkt);
+        assert_eq!(decoded.data().unwrap()["resp"].get_i64(0), Some(12345));
+    }
+}
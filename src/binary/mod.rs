@@ -5,8 +5,9 @@ pub mod data_reader;
 pub mod write_impls;
 pub mod read_impls;
 pub mod protobuf;
+pub mod jce;
 
-mod utils;
+pub mod utils;
 
 pub trait WriteTo {
     fn write_to<W: Write>(&self, write: &mut W) -> io::Result<()>;
@@ -2,7 +2,11 @@ use std::io::{self, Write};
 
 use self::proto_msg::DataEncoder;
 
+pub use self::proto_msg::DynamicProtoMessage;
+pub use self::proto_reader::ProtoReader;
+
 mod proto_msg;
+mod proto_reader;
 mod to_varint_impls;
 pub trait VarInt: Write {
     fn uvarint(&mut self, data: u64) -> io::Result<usize>;
@@ -20,6 +24,7 @@ impl<T: Write> VarInt for T {
             ld >>= 7;
             idx += 1;
         }
+        self.write_all(&[ld as u8])?;
 
         Ok(idx + 1)
     }
@@ -37,3 +42,37 @@ impl<T: Write> VarInt for T {
 pub trait WriteToVarInt {
     fn write_to_varint(&self, writer: &mut DataEncoder, key: u64) -> io::Result<()>;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uvarint() {
+        let mut buf = Vec::new();
+        assert_eq!(buf.uvarint(1).unwrap(), 1);
+        assert_eq!(buf.uvarint(300).unwrap(), 2);
+        assert_eq!(buf, vec![0x01, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let inner = DynamicProtoMessage::new().with(1, 10086u64);
+        let data = DynamicProtoMessage::new()
+            .with(1, 3u32)
+            .with(2, "MiraiGO Here".to_string())
+            .with(3, vec![0x01u8, 0x02, 0x03])
+            .with(4, inner)
+            .with(5, vec![7u64, 8])
+            .encode()
+            .unwrap();
+
+        let msg = ProtoReader::decode(&data).unwrap();
+        assert_eq!(msg.get_u64(1), Some(3));
+        assert_eq!(msg.get_string(2), Some("MiraiGO Here".to_string()));
+        assert_eq!(msg.get_bytes(3), Some(&[0x01u8, 0x02, 0x03][..]));
+        assert_eq!(msg.get_message(4).unwrap().unwrap().get_u64(1), Some(10086));
+        assert_eq!(msg.get_repeated_u64(5), vec![7, 8]);
+        assert_eq!(msg.get_u64(6), None);
+    }
+}
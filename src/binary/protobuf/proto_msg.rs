@@ -1,20 +1,35 @@
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     io::{self, Write},
 };
 
 use super::WriteToVarInt;
 
-pub struct DynamicProtoMessage(HashMap<u64, Box<dyn WriteToVarInt>>);
+/// 动态构建的 protobuf 消息，key 为字段编号
+#[derive(Default)]
+pub struct DynamicProtoMessage(BTreeMap<u64, Box<dyn WriteToVarInt + Send + Sync>>);
 
 impl DynamicProtoMessage {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn with<T: WriteToVarInt + Send + Sync + 'static>(mut self, field: u64, value: T) -> Self {
+        self.set(field, value);
+        self
+    }
+
+    pub fn set<T: WriteToVarInt + Send + Sync + 'static>(&mut self, field: u64, value: T) {
+        self.0.insert(field, Box::new(value));
+    }
+
     pub fn encode(&self) -> io::Result<Vec<u8>> {
         let mut encoder = DataEncoder {
             buff: Vec::with_capacity(1024),
         };
 
         for (key, v) in &self.0 {
-            v.write_to_varint(&mut encoder, *key)?;
+            v.write_to_varint(&mut encoder, *key << 3)?;
         }
 
         Ok(encoder.buff)
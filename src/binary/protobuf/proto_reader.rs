@@ -0,0 +1,151 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Cursor, Read},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+/// protobuf 字段的原始值，未知类型的字段按 wire type 保留
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtoValue {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(Vec<u8>),
+    Fixed32(u32),
+}
+
+/// 无需 schema 的 protobuf 解码结果，key 为字段编号
+#[derive(Debug, Default, Clone)]
+pub struct ProtoReader(BTreeMap<u64, Vec<ProtoValue>>);
+
+fn read_uvarint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut res = 0u64;
+    let mut shift = 0;
+    loop {
+        let b = reader.read_u8()?;
+        if shift >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "varint overflow",
+            ));
+        }
+        res |= ((b & 0x7f) as u64) << shift;
+        if b < 0x80 {
+            return Ok(res);
+        }
+        shift += 7;
+    }
+}
+
+impl ProtoReader {
+    pub fn decode(data: &[u8]) -> io::Result<Self> {
+        let mut map: BTreeMap<u64, Vec<ProtoValue>> = BTreeMap::new();
+        let mut reader = Cursor::new(data);
+
+        while (reader.position() as usize) < data.len() {
+            let key = read_uvarint(&mut reader)?;
+            let value = match key & 7 {
+                0 => ProtoValue::Varint(read_uvarint(&mut reader)?),
+                1 => ProtoValue::Fixed64(reader.read_u64::<LittleEndian>()?),
+                2 => {
+                    let size = read_uvarint(&mut reader)? as usize;
+                    let remain = data.len() - reader.position() as usize;
+                    if size > remain {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "length delimited field out of range",
+                        ));
+                    }
+                    let mut buf = vec![0u8; size];
+                    reader.read_exact(&mut buf)?;
+                    ProtoValue::Bytes(buf)
+                }
+                5 => ProtoValue::Fixed32(reader.read_u32::<LittleEndian>()?),
+                t => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unsupported wire type {}", t),
+                    ))
+                }
+            };
+            map.entry(key >> 3).or_default().push(value);
+        }
+
+        Ok(Self(map))
+    }
+
+    fn last(&self, field: u64) -> Option<&ProtoValue> {
+        self.0.get(&field).and_then(|v| v.last())
+    }
+
+    pub fn get_u64(&self, field: u64) -> Option<u64> {
+        match self.last(field)? {
+            ProtoValue::Varint(v) | ProtoValue::Fixed64(v) => Some(*v),
+            ProtoValue::Fixed32(v) => Some(*v as u64),
+            ProtoValue::Bytes(_) => None,
+        }
+    }
+
+    /// 普通（非 zigzag）编码的有符号整数
+    pub fn get_i64(&self, field: u64) -> Option<i64> {
+        self.get_u64(field).map(|v| v as i64)
+    }
+
+    pub fn get_bytes(&self, field: u64) -> Option<&[u8]> {
+        match self.last(field)? {
+            ProtoValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn get_string(&self, field: u64) -> Option<String> {
+        self.get_bytes(field)
+            .map(|b| String::from_utf8_lossy(b).to_string())
+    }
+
+    pub fn get_message(&self, field: u64) -> io::Result<Option<ProtoReader>> {
+        self.get_bytes(field).map(Self::decode).transpose()
+    }
+
+    pub fn get_repeated_bytes(&self, field: u64) -> Vec<&[u8]> {
+        self.0
+            .get(&field)
+            .map(|vs| {
+                vs.iter()
+                    .filter_map(|v| match v {
+                        ProtoValue::Bytes(b) => Some(b.as_slice()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_repeated_message(&self, field: u64) -> io::Result<Vec<ProtoReader>> {
+        self.get_repeated_bytes(field)
+            .into_iter()
+            .map(Self::decode)
+            .collect()
+    }
+
+    /// 同时兼容 packed 与非 packed 的 repeated 整数字段
+    pub fn get_repeated_u64(&self, field: u64) -> Vec<u64> {
+        let mut res = Vec::new();
+        for v in self.0.get(&field).into_iter().flatten() {
+            match v {
+                ProtoValue::Varint(v) | ProtoValue::Fixed64(v) => res.push(*v),
+                ProtoValue::Fixed32(v) => res.push(*v as u64),
+                ProtoValue::Bytes(b) => {
+                    let mut rd = Cursor::new(b.as_slice());
+                    while (rd.position() as usize) < b.len() {
+                        match read_uvarint(&mut rd) {
+                            Ok(v) => res.push(v),
+                            Err(_) => break,
+                        }
+                    }
+                }
+            }
+        }
+        res
+    }
+}
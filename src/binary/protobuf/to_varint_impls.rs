@@ -90,11 +90,9 @@ impl WriteToVarInt for Vec<u64> {
 
 impl WriteToVarInt for Vec<u8> {
     fn write_to_varint(&self, writer: &mut DataEncoder, key: u64) -> std::io::Result<()> {
-        for i in self {
-            writer.uvarint(key | 2)?;
-            writer.uvarint((*i) as u64)?;
-        }
-        Ok(())
+        writer.uvarint(key | 2)?;
+        writer.uvarint(self.len() as u64)?;
+        self.write_to(writer)
     }
 }
 
@@ -106,3 +104,12 @@ impl WriteToVarInt for DynamicProtoMessage {
         writer.write_all(&b)
     }
 }
+
+impl WriteToVarInt for Vec<DynamicProtoMessage> {
+    fn write_to_varint(&self, writer: &mut DataEncoder, key: u64) -> std::io::Result<()> {
+        for msg in self {
+            msg.write_to_varint(writer, key)?;
+        }
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use crate::utils::crypto::tea::CryptoResult;
 use super::{WriteTo, data_writer::DataWriter};
 
 pub fn zlib_uncompress(src: Vec<u8>) -> io::Result<Vec<u8>> {
-    let mut data = flate2::write::ZlibDecoder::new(Cursor::new(src));
+    let mut data = flate2::read::ZlibDecoder::new(Cursor::new(src));
     let mut res = Vec::with_capacity(1024);
     data.read_to_end(&mut res)?;
     Ok(res)
@@ -13,20 +13,20 @@ pub fn zlib_uncompress(src: Vec<u8>) -> io::Result<Vec<u8>> {
 
 pub fn zlib_compress(data: Vec<u8>) -> io::Result<Vec<u8>> {
     let mut dst =
-        flate2::write::ZlibEncoder::new(Cursor::new(data), flate2::Compression::default());
+        flate2::read::ZlibEncoder::new(Cursor::new(data), flate2::Compression::default());
     let mut res = Vec::with_capacity(1024);
     dst.read_to_end(&mut res)?;
     Ok(res)
 }
 
 pub fn gzip_uncompress(src: Vec<u8>) -> io::Result<Vec<u8>> {
-    let mut data = flate2::write::GzDecoder::new(Cursor::new(src));
+    let mut data = flate2::read::GzDecoder::new(Cursor::new(src));
     let mut res = Vec::with_capacity(1024);
     data.read_to_end(&mut res)?;
     Ok(res)
 }
 pub fn gzip_compress(data: Vec<u8>) -> io::Result<Vec<u8>> {
-    let mut dst = flate2::write::GzEncoder::new(Cursor::new(data), flate2::Compression::default());
+    let mut dst = flate2::read::GzEncoder::new(Cursor::new(data), flate2::Compression::default());
     let mut res = Vec::with_capacity(1024);
     dst.read_to_end(&mut res)?;
     Ok(res)
@@ -35,3 +35,20 @@ pub fn gzip_compress(data: Vec<u8>) -> io::Result<Vec<u8>> {
 pub fn to_bytes<T: WriteTo>(data: &T) -> CryptoResult<Vec<u8>> {
     DataWriter::new_filled(|w| Ok(w.write_data(data)?))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let data = b"hello hello hello hello".to_vec();
+        let gz = gzip_compress(data.clone()).unwrap();
+        assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+        assert_eq!(gzip_uncompress(gz).unwrap(), data);
+
+        let zlib = zlib_compress(data.clone()).unwrap();
+        assert_eq!(zlib[0], 0x78);
+        assert_eq!(zlib_uncompress(zlib).unwrap(), data);
+    }
+}
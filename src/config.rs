@@ -0,0 +1,197 @@
+//! bot 守护进程的配置文件
+//!
+//! ```toml
+//! log_level = "info"
+//!
+//! [[accounts]]
+//! uin = 10086
+//! protocol = "android_pad"
+//! device = "device-10086.json"
+//!
+//! [accounts.onebot]
+//! listen = "127.0.0.1:5700"
+//! access_token = "token"
+//!
+//! [accounts.mirai_api_http]
+//! listen = "127.0.0.1:8080"
+//! verify_key = "key"
+//! ```
+
+use std::{fmt::Display, io, net::SocketAddr, path::PathBuf};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        };
+        f.write_str(s)
+    }
+}
+
+/// 登录使用的协议
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    AndroidPhone,
+    #[default]
+    AndroidPad,
+    AndroidWatch,
+    MacOs,
+    Ipad,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OneBotAdapterConfig {
+    pub listen: SocketAddr,
+    pub access_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MiraiApiHttpAdapterConfig {
+    pub listen: SocketAddr,
+    pub verify_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountConfig {
+    pub uin: u64,
+    /// 未设置时使用扫码登录
+    pub password: Option<String>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// 设备信息文件，未设置时为 `device-<uin>.json`
+    pub device: Option<PathBuf>,
+    /// 保存登录凭据的文件，未设置时为 `session-<uin>.token`
+    pub session: Option<PathBuf>,
+    pub onebot: Option<OneBotAdapterConfig>,
+    pub mirai_api_http: Option<MiraiApiHttpAdapterConfig>,
+}
+
+impl AccountConfig {
+    pub fn device_path(&self) -> PathBuf {
+        self.device
+            .clone()
+            .unwrap_or_else(|| format!("device-{}.json", self.uin).into())
+    }
+
+    pub fn session_path(&self) -> PathBuf {
+        self.session
+            .clone()
+            .unwrap_or_else(|| format!("session-{}.token", self.uin).into())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub log_level: LogLevel,
+    pub accounts: Vec<AccountConfig>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let config: Config =
+            toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// 账号不能重复，启用的适配器必须在编译时打开对应的 feature，
+    /// mirai-api-http 必须设置 `verify_key`
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        for (i, account) in self.accounts.iter().enumerate() {
+            if self.accounts[..i].iter().any(|a| a.uin == account.uin) {
+                return invalid(format!("duplicate account {}", account.uin));
+            }
+            if account.onebot.is_some() && !cfg!(feature = "onebot") {
+                return invalid(format!(
+                    "account {}: built without the onebot feature",
+                    account.uin
+                ));
+            }
+            if let Some(mirai) = &account.mirai_api_http {
+                if !cfg!(feature = "mirai-api-http") {
+                    return invalid(format!(
+                        "account {}: built without the mirai-api-http feature",
+                        account.uin
+                    ));
+                }
+                if mirai.verify_key.is_none() {
+                    return invalid(format!(
+                        "account {}: mirai-api-http requires verify_key",
+                        account.uin
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            log_level = "debug"
+
+            [[accounts]]
+            uin = 10086
+            protocol = "android_watch"
+
+            [[accounts]]
+            uin = 10010
+            password = "pwd"
+            device = "a.json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.accounts[0].protocol, Protocol::AndroidWatch);
+        assert_eq!(
+            config.accounts[0].device_path(),
+            PathBuf::from("device-10086.json")
+        );
+        assert_eq!(config.accounts[1].device_path(), PathBuf::from("a.json"));
+        assert_eq!(config.accounts[1].protocol, Protocol::AndroidPad);
+    }
+
+    #[test]
+    fn test_duplicate_account() {
+        let text = "[[accounts]]\nuin = 1\n[[accounts]]\nuin = 1\n";
+        assert!(Config::parse(text).is_err());
+    }
+
+    #[cfg(feature = "mirai-api-http")]
+    #[test]
+    fn test_mirai_requires_verify_key() {
+        let text =
+            "[[accounts]]\nuin = 1\n[accounts.mirai_api_http]\nlisten = \"127.0.0.1:8080\"\n";
+        assert!(Config::parse(text).is_err());
+        let text = format!("{}verify_key = \"key\"\n", text);
+        assert!(Config::parse(&text).is_ok());
+    }
+}
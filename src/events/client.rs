@@ -0,0 +1,10 @@
+//! 同一账号其他客户端的上下线事件
+
+use crate::network::other_client::OtherClient;
+
+/// 其他客户端（如 PC、平板）登录或下线
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtherClientStatusEvent {
+    pub client: OtherClient,
+    pub online: bool,
+}
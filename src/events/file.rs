@@ -0,0 +1,19 @@
+//! 文件相关事件
+
+use crate::network::{
+    offline_file::{query_offline_file_url, OfflineFile},
+    NetworkResult, SsoSender,
+};
+
+/// 收到好友发送的离线文件
+#[derive(Debug, Clone)]
+pub struct OfflineFileEvent {
+    pub sender_uin: u64,
+    pub file: OfflineFile,
+}
+
+impl OfflineFileEvent {
+    pub async fn download_url<S: SsoSender>(&self, sender: &S, uin: u64) -> NetworkResult<String> {
+        query_offline_file_url(sender, uin, &self.file).await
+    }
+}
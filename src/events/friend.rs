@@ -0,0 +1,29 @@
+//! 好友关系与好友资料变动事件
+
+/// 新增好友，包括申请通过以及群成员、陌生人转为好友
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FriendAddedEvent {
+    pub uin: u64,
+    /// 部分推送中不带昵称，此时为空
+    pub nick: String,
+}
+
+/// 好友被删除，包括 bot 主动删除与被对方删除
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FriendDeletedEvent {
+    pub uin: u64,
+}
+
+/// 好友昵称变更
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FriendNickChangeEvent {
+    pub uin: u64,
+    pub new_nick: String,
+}
+
+/// 好友正在输入状态变化
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FriendInputStatusEvent {
+    pub uin: u64,
+    pub typing: bool,
+}
@@ -0,0 +1,76 @@
+//! 群成员与群资料变动事件，成员为 bot 自身时同样会收到
+
+/// 新成员入群
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberJoinEvent {
+    pub group_code: u64,
+    pub member_uin: u64,
+    pub member_nick: String,
+}
+
+/// 成员退群或被踢出
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberLeaveEvent {
+    pub group_code: u64,
+    pub member_uin: u64,
+    /// 被踢出时为操作者
+    pub operator_uin: Option<u64>,
+}
+
+/// 成员被设置或取消管理员
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberPermissionChangeEvent {
+    pub group_code: u64,
+    pub member_uin: u64,
+    pub is_admin: bool,
+}
+
+/// 成员被禁言或解除禁言
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberMuteEvent {
+    pub group_code: u64,
+    pub operator_uin: u64,
+    pub member_uin: u64,
+    /// 禁言时长，单位为秒，为 0 时表示解除禁言
+    pub duration: u32,
+}
+
+/// 全员禁言开启或关闭
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMuteAllEvent {
+    pub group_code: u64,
+    pub operator_uin: u64,
+    pub enabled: bool,
+}
+
+/// 群名片变更，服务器只推送新值，旧值需要自行记录
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberCardChangeEvent {
+    pub group_code: u64,
+    pub member_uin: u64,
+    pub new_card: String,
+}
+
+/// 群主授予或收回专属头衔
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberSpecialTitleChangeEvent {
+    pub group_code: u64,
+    pub member_uin: u64,
+    pub new_title: String,
+}
+
+/// 群名称变更
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupNameChangeEvent {
+    pub group_code: u64,
+    pub operator_uin: u64,
+    pub new_name: String,
+}
+
+/// 群主转让
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupOwnerTransferEvent {
+    pub group_code: u64,
+    pub old_owner_uin: u64,
+    pub new_owner_uin: u64,
+}
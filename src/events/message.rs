@@ -0,0 +1,45 @@
+//! 消息事件
+
+use crate::message::{MessageChain, MessageTarget};
+
+/// 群成员发送的群消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMessageEvent {
+    pub group_code: u64,
+    pub sender_uin: u64,
+    /// 群名片，未设置时为昵称
+    pub sender_name: String,
+    pub seq: u32,
+    /// 与 `seq` 一起用于撤回
+    pub random: u32,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+    pub chain: MessageChain,
+}
+
+/// bot 账号在其他设备上发送的消息，服务器同步回来的副本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfMessageEvent {
+    /// 消息的接收方，即发到的群或好友
+    pub target: MessageTarget,
+    pub seq: u64,
+    /// 发送时填写的随机数，用于将推送回来的群消息与发送凭据对应
+    pub random: u32,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+    pub chain: MessageChain,
+}
+
+/// 频道中收到的消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildMessageEvent {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub seq: u64,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+    /// 频道中以 tiny id 而不是 uin 区分用户
+    pub sender_tiny_id: u64,
+    pub sender_nick: String,
+    pub chain: MessageChain,
+}
@@ -0,0 +1,49 @@
+pub mod client;
+pub mod file;
+pub mod friend;
+pub mod group;
+pub mod message;
+pub mod notice;
+pub mod request;
+
+use self::client::OtherClientStatusEvent;
+use self::file::OfflineFileEvent;
+use self::friend::{
+    FriendAddedEvent, FriendDeletedEvent, FriendInputStatusEvent, FriendNickChangeEvent,
+};
+use self::group::{
+    GroupMuteAllEvent, GroupNameChangeEvent, GroupOwnerTransferEvent, MemberCardChangeEvent,
+    MemberJoinEvent, MemberLeaveEvent, MemberMuteEvent, MemberPermissionChangeEvent,
+    MemberSpecialTitleChangeEvent,
+};
+use self::message::{GroupMessageEvent, GuildMessageEvent, SelfMessageEvent};
+use self::notice::NudgeEvent;
+use self::request::{
+    BotInvitedJoinGroupRequestEvent, MemberJoinRequestEvent, NewFriendRequestEvent,
+};
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    NewFriendRequest(NewFriendRequestEvent),
+    MemberJoinRequest(MemberJoinRequestEvent),
+    BotInvitedJoinGroupRequest(BotInvitedJoinGroupRequestEvent),
+    OfflineFile(OfflineFileEvent),
+    Nudge(NudgeEvent),
+    MemberJoin(MemberJoinEvent),
+    MemberLeave(MemberLeaveEvent),
+    MemberPermissionChange(MemberPermissionChangeEvent),
+    MemberMute(MemberMuteEvent),
+    GroupMuteAll(GroupMuteAllEvent),
+    MemberCardChange(MemberCardChangeEvent),
+    MemberSpecialTitleChange(MemberSpecialTitleChangeEvent),
+    GroupNameChange(GroupNameChangeEvent),
+    GroupOwnerTransfer(GroupOwnerTransferEvent),
+    FriendAdded(FriendAddedEvent),
+    FriendDeleted(FriendDeletedEvent),
+    FriendNickChange(FriendNickChangeEvent),
+    FriendInputStatus(FriendInputStatusEvent),
+    OtherClientStatus(OtherClientStatusEvent),
+    GroupMessage(GroupMessageEvent),
+    SelfMessage(SelfMessageEvent),
+    GuildMessage(GuildMessageEvent),
+}
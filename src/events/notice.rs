@@ -0,0 +1,12 @@
+//! 群与好友的通知事件
+
+/// 戳一戳，例如 "10010 戳了戳 10086 的脸"
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NudgeEvent {
+    /// 好友间的戳一戳为 `None`
+    pub group_code: Option<u64>,
+    pub sender_uin: u64,
+    pub receiver_uin: u64,
+    pub action: String,
+    pub suffix: String,
+}
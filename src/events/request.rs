@@ -0,0 +1,140 @@
+//! 需要 bot 处理的申请事件
+
+use crate::network::{
+    system_msg::{solve_friend_request, solve_group_request, GroupSystemMsgAction, RequestAction},
+    NetworkResult, SsoSender,
+};
+
+/// 收到好友申请
+#[derive(Debug, Clone)]
+pub struct NewFriendRequestEvent {
+    pub request_id: u64,
+    pub message: String,
+    pub requester_uin: u64,
+    pub requester_nick: String,
+}
+
+/// 有人申请加入 bot 管理的群
+#[derive(Debug, Clone)]
+pub struct MemberJoinRequestEvent {
+    pub request_id: u64,
+    pub message: String,
+    pub requester_uin: u64,
+    pub requester_nick: String,
+    pub group_code: u64,
+    pub group_name: String,
+    /// 由群成员邀请时为邀请人
+    pub invitor_uin: Option<u64>,
+    /// 服务器标记的可疑账号
+    pub suspicious: bool,
+}
+
+/// bot 被邀请加入群
+#[derive(Debug, Clone)]
+pub struct BotInvitedJoinGroupRequestEvent {
+    pub request_id: u64,
+    pub invitor_uin: u64,
+    pub invitor_nick: String,
+    pub group_code: u64,
+    pub group_name: String,
+}
+
+/// 三种申请的同意、拒绝与忽略，具体请求由各自的 `solve` 构造
+macro_rules! impl_request_actions {
+    ($($t:ty),*) => {
+        $(impl $t {
+            pub async fn accept<S: SsoSender>(&self, sender: &S) -> NetworkResult<()> {
+                self.solve(sender, RequestAction::Accept, false, "").await
+            }
+
+            pub async fn reject<S: SsoSender>(
+                &self,
+                sender: &S,
+                blacklist: bool,
+                reason: &str,
+            ) -> NetworkResult<()> {
+                self.solve(sender, RequestAction::Reject, blacklist, reason)
+                    .await
+            }
+
+            pub async fn ignore<S: SsoSender>(
+                &self,
+                sender: &S,
+                blacklist: bool,
+            ) -> NetworkResult<()> {
+                self.solve(sender, RequestAction::Ignore, blacklist, "")
+                    .await
+            }
+        })*
+    };
+}
+
+impl_request_actions!(
+    NewFriendRequestEvent,
+    MemberJoinRequestEvent,
+    BotInvitedJoinGroupRequestEvent
+);
+
+impl NewFriendRequestEvent {
+    async fn solve<S: SsoSender>(
+        &self,
+        sender: &S,
+        action: RequestAction,
+        blacklist: bool,
+        reason: &str,
+    ) -> NetworkResult<()> {
+        solve_friend_request(
+            sender,
+            self.request_id,
+            self.requester_uin,
+            action,
+            blacklist,
+            reason,
+        )
+        .await
+    }
+}
+
+impl MemberJoinRequestEvent {
+    async fn solve<S: SsoSender>(
+        &self,
+        sender: &S,
+        action: RequestAction,
+        blacklist: bool,
+        reason: &str,
+    ) -> NetworkResult<()> {
+        let req = GroupSystemMsgAction {
+            request_id: self.request_id,
+            requester_uin: self.requester_uin,
+            group_code: self.group_code,
+            is_invite: false,
+            suspicious: self.suspicious,
+            action,
+            blacklist,
+            reason,
+        };
+        solve_group_request(sender, &req).await
+    }
+}
+
+impl BotInvitedJoinGroupRequestEvent {
+    async fn solve<S: SsoSender>(
+        &self,
+        sender: &S,
+        action: RequestAction,
+        blacklist: bool,
+        reason: &str,
+    ) -> NetworkResult<()> {
+        let req = GroupSystemMsgAction {
+            request_id: self.request_id,
+            requester_uin: self.invitor_uin,
+            group_code: self.group_code,
+            is_invite: true,
+            suspicious: false,
+            action,
+            blacklist,
+            reason,
+        };
+        solve_group_request(sender, &req).await
+    }
+}
@@ -7,5 +7,16 @@ mod tests {
     }
 }
 
+#[cfg(any(feature = "onebot", feature = "mirai-api-http"))]
+mod adapter;
 mod binary;
-mod utils;
\ No newline at end of file
+mod utils;
+
+pub mod events;
+pub mod manager;
+pub mod message;
+#[cfg(feature = "mirai-api-http")]
+pub mod mirai_api_http;
+pub mod network;
+#[cfg(feature = "onebot")]
+pub mod onebot;
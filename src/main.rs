@@ -1 +1,154 @@
-fn main(){}
\ No newline at end of file
+//! bot 守护进程，用法：`mirai_rust [config.toml]`
+//!
+//! 每个账号由 [`BotManager`] 在各自的任务中运行，收到 SIGINT 或 SIGTERM 后
+//! 通知所有账号下线，等待 [`SHUTDOWN_TIMEOUT`] 后退出
+//!
+//! 登录流程（wtlogin 与 SSO 长连接）尚未实现，账号启动后会以 "login is not supported"
+//! 失败，适配器依赖登录后的连接，因此也不会启动
+
+mod config;
+
+use std::{fmt::Display, io, process::ExitCode, sync::OnceLock, time::Duration};
+
+use mirai_rust::{
+    manager::{BotManager, BotRunner, BotState, EventSink, Health},
+    network::{NetworkError, NetworkResult},
+};
+
+use config::{AccountConfig, Config, LogLevel};
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// 收到退出信号后等待账号下线的时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+static LOG_LEVEL: OnceLock<LogLevel> = OnceLock::new();
+
+fn log(level: LogLevel, msg: impl Display) {
+    if level <= LOG_LEVEL.get().copied().unwrap_or_default() {
+        eprintln!("[{}] {}", level, msg);
+    }
+}
+
+struct Account(AccountConfig);
+
+impl BotRunner for Account {
+    /// 检查配置后返回登录不受支持的错误，
+    /// 扫码、滑块与短信验证的终端交互以及登录凭据的保存都依赖于登录流程
+    async fn run(&self, _events: EventSink) -> NetworkResult<()> {
+        let account = &self.0;
+        log(
+            LogLevel::Debug,
+            format_args!(
+                "account {}: protocol {:?}, device {}, session {}, {} login",
+                account.uin,
+                account.protocol,
+                account.device_path().display(),
+                account.session_path().display(),
+                if account.password.is_some() {
+                    "password"
+                } else {
+                    "qrcode"
+                },
+            ),
+        );
+        if let Some(onebot) = &account.onebot {
+            let auth = if onebot.access_token.is_some() {
+                " with access token"
+            } else {
+                ""
+            };
+            log(
+                LogLevel::Debug,
+                format_args!(
+                    "account {}: onebot on {}{}",
+                    account.uin, onebot.listen, auth
+                ),
+            );
+        }
+        if let Some(mirai) = &account.mirai_api_http {
+            log(
+                LogLevel::Debug,
+                format_args!(
+                    "account {}: mirai-api-http on {}",
+                    account.uin, mirai.listen
+                ),
+            );
+        }
+        Err(NetworkError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "login is not supported: wtlogin and the sso connection are not implemented",
+        )))
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// 等待所有账号下线
+async fn all_exited(manager: &BotManager<Account>) {
+    for uin in manager.uins() {
+        if let Some(mut status) = manager.watch_status(uin) {
+            let _ = status.wait_for(|s| s.state != BotState::Running).await;
+        }
+    }
+}
+
+fn report(health: &Health) {
+    for bot in &health.bots {
+        match &bot.state {
+            BotState::Running => {}
+            BotState::Stopped => log(LogLevel::Info, format_args!("account {}: stopped", bot.uin)),
+            BotState::Failed(e) => log(LogLevel::Error, format_args!("account {}: {}", bot.uin, e)),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+    let config = match Config::load(&path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("failed to load {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let _ = LOG_LEVEL.set(config.log_level);
+
+    let manager = BotManager::new();
+    for account in config.accounts {
+        manager.add(account.uin, Account(account));
+    }
+    let health = tokio::select! {
+        _ = shutdown_signal() => {
+            log(LogLevel::Info, "shutting down");
+            manager.shutdown(SHUTDOWN_TIMEOUT).await
+        }
+        _ = all_exited(&manager) => manager.health(),
+    };
+    report(&health);
+    if health.failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
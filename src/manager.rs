@@ -0,0 +1,419 @@
+//! 在同一个 tokio 运行时中管理多个 bot
+//!
+//! 每个 bot 由一个 [`BotRunner`] 驱动，运行在独立的任务中，
+//! 产生的事件带上 bot 的 uin 后汇总到同一个广播通道。
+//! [`BotManager::shutdown`] 先通知所有 bot 下线，超时后才强制结束任务
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{events::Event, network::NetworkResult};
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 驱动一个 bot，返回即视为 bot 下线
+///
+/// 收到 [`EventSink::stopped`] 的通知后应当尽快下线并返回
+pub trait BotRunner: Send + Sync + 'static {
+    fn run(&self, events: EventSink) -> impl Future<Output = NetworkResult<()>> + Send;
+}
+
+#[derive(Debug, Clone)]
+pub struct BotEvent {
+    pub uin: u64,
+    pub event: Event,
+}
+
+/// bot 用来上报事件以及接收停止通知的句柄
+#[derive(Debug, Clone)]
+pub struct EventSink {
+    uin: u64,
+    events: broadcast::Sender<BotEvent>,
+    stop: watch::Receiver<bool>,
+}
+
+impl EventSink {
+    pub fn uin(&self) -> u64 {
+        self.uin
+    }
+
+    pub fn push(&self, event: Event) {
+        // 没有订阅者时直接丢弃
+        let _ = self.events.send(BotEvent {
+            uin: self.uin,
+            event,
+        });
+    }
+
+    /// manager 要求 bot 停止时返回，manager 被释放时同样视为要求停止
+    pub async fn stopped(&self) {
+        let mut stop = self.stop.clone();
+        let _ = stop.wait_for(|stop| *stop).await;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotState {
+    Running,
+    /// `run` 正常返回
+    Stopped,
+    /// `run` 返回错误或 panic
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotStatus {
+    pub uin: u64,
+    pub state: BotState,
+    /// 通过 [`BotManager::restart`] 重启的次数
+    pub restarts: u32,
+}
+
+/// 所有 bot 的状态汇总
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Health {
+    pub running: usize,
+    pub stopped: usize,
+    pub failed: usize,
+    /// 按 uin 排序
+    pub bots: Vec<BotStatus>,
+}
+
+impl Health {
+    /// 所有 bot 都在运行
+    pub fn is_healthy(&self) -> bool {
+        self.stopped == 0 && self.failed == 0
+    }
+}
+
+struct BotEntry<R> {
+    runner: Arc<R>,
+    status: Arc<watch::Sender<BotStatus>>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+pub struct BotManager<R> {
+    bots: Mutex<HashMap<u64, BotEntry<R>>>,
+    events: broadcast::Sender<BotEvent>,
+}
+
+impl<R: BotRunner> Default for BotManager<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: BotRunner> BotManager<R> {
+    pub fn new() -> Self {
+        Self {
+            bots: Mutex::new(HashMap::new()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// 订阅所有 bot 的事件
+    pub fn subscribe(&self) -> broadcast::Receiver<BotEvent> {
+        self.events.subscribe()
+    }
+
+    /// 添加并启动 bot，`uin` 已存在时返回 `false`
+    ///
+    /// 需要在 tokio 运行时中调用
+    pub fn add(&self, uin: u64, runner: R) -> bool {
+        let mut bots = self.bots.lock().unwrap();
+        if bots.contains_key(&uin) {
+            return false;
+        }
+        let runner = Arc::new(runner);
+        let status = Arc::new(watch::Sender::new(BotStatus {
+            uin,
+            state: BotState::Running,
+            restarts: 0,
+        }));
+        let (stop, task) = self.spawn(uin, runner.clone(), status.clone());
+        bots.insert(
+            uin,
+            BotEntry {
+                runner,
+                status,
+                stop,
+                task,
+            },
+        );
+        true
+    }
+
+    /// 立即结束 bot 的任务并将其移除，返回其 runner
+    pub fn remove(&self, uin: u64) -> Option<Arc<R>> {
+        let entry = self.bots.lock().unwrap().remove(&uin)?;
+        entry.task.abort();
+        Some(entry.runner)
+    }
+
+    /// 立即结束 bot 的任务后使用同一个 runner 重新启动，`uin` 不存在时返回 `false`
+    pub fn restart(&self, uin: u64) -> bool {
+        let mut bots = self.bots.lock().unwrap();
+        let Some(entry) = bots.get_mut(&uin) else {
+            return false;
+        };
+        entry.task.abort();
+        // 旧任务可能仍持有状态，换成新的状态以免被其覆盖
+        let restarts = entry.status.borrow().restarts + 1;
+        entry.status = Arc::new(watch::Sender::new(BotStatus {
+            uin,
+            state: BotState::Running,
+            restarts,
+        }));
+        (entry.stop, entry.task) = self.spawn(uin, entry.runner.clone(), entry.status.clone());
+        true
+    }
+
+    /// 通知所有 bot 停止并等待其退出，超过 `timeout` 仍未退出的 bot 将被强制结束并记为失败
+    pub async fn shutdown(&self, timeout: Duration) -> Health {
+        let deadline = Instant::now() + timeout;
+        let waiting = self
+            .bots
+            .lock()
+            .unwrap()
+            .values()
+            .map(|entry| {
+                entry.stop.send_replace(true);
+                entry.status.subscribe()
+            })
+            .collect::<Vec<_>>();
+        for mut status in waiting {
+            let stopped = status.wait_for(|s| s.state != BotState::Running);
+            let _ = tokio::time::timeout_at(deadline, stopped).await;
+        }
+        for entry in self.bots.lock().unwrap().values() {
+            // 已经结束的任务不受影响
+            entry.task.abort();
+            entry.status.send_if_modified(|s| {
+                let running = s.state == BotState::Running;
+                if running {
+                    s.state = BotState::Failed("did not stop in time".to_string());
+                }
+                running
+            });
+        }
+        self.health()
+    }
+
+    pub fn uins(&self) -> Vec<u64> {
+        let mut uins = self
+            .bots
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        uins.sort_unstable();
+        uins
+    }
+
+    pub fn status(&self, uin: u64) -> Option<BotStatus> {
+        let bots = self.bots.lock().unwrap();
+        bots.get(&uin).map(|e| e.status.borrow().clone())
+    }
+
+    /// 订阅 bot 的状态变化，重启后需要重新订阅
+    pub fn watch_status(&self, uin: u64) -> Option<watch::Receiver<BotStatus>> {
+        let bots = self.bots.lock().unwrap();
+        bots.get(&uin).map(|e| e.status.subscribe())
+    }
+
+    pub fn health(&self) -> Health {
+        let mut health = Health::default();
+        for entry in self.bots.lock().unwrap().values() {
+            let status = entry.status.borrow().clone();
+            match status.state {
+                BotState::Running => health.running += 1,
+                BotState::Stopped => health.stopped += 1,
+                BotState::Failed(_) => health.failed += 1,
+            }
+            health.bots.push(status);
+        }
+        health.bots.sort_unstable_by_key(|s| s.uin);
+        health
+    }
+
+    /// 在独立任务中运行 `run`，panic 时由外层任务记录为失败
+    fn spawn(
+        &self,
+        uin: u64,
+        runner: Arc<R>,
+        status: Arc<watch::Sender<BotStatus>>,
+    ) -> (watch::Sender<bool>, JoinHandle<()>) {
+        let (stop, stop_rx) = watch::channel(false);
+        let sink = EventSink {
+            uin,
+            events: self.events.clone(),
+            stop: stop_rx,
+        };
+        let inner = tokio::spawn(async move { runner.run(sink).await });
+        let task = tokio::spawn(async move {
+            let mut inner = AbortOnDrop(inner);
+            let state = match (&mut inner.0).await {
+                Ok(Ok(())) => BotState::Stopped,
+                Ok(Err(e)) => BotState::Failed(e.to_string()),
+                Err(e) => BotState::Failed(e.to_string()),
+            };
+            status.send_modify(|s| s.state = state);
+        });
+        (stop, task)
+    }
+}
+
+/// 未调用 [`BotManager::shutdown`] 时直接结束所有任务
+impl<R> Drop for BotManager<R> {
+    fn drop(&mut self) {
+        for entry in self.bots.get_mut().unwrap().values() {
+            entry.task.abort();
+        }
+    }
+}
+
+/// 外层任务被取消时一并取消 `run` 所在的任务
+struct AbortOnDrop(JoinHandle<NetworkResult<()>>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::friend::FriendAddedEvent;
+    use std::{
+        io,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    #[derive(Clone, Copy)]
+    enum Exit {
+        Fail,
+        /// 收到停止通知后返回
+        OnStop,
+        /// 忽略停止通知
+        Never,
+    }
+
+    /// 上报一个事件后按 `exit` 结束或继续运行
+    struct TestRunner {
+        exit: Exit,
+        runs: AtomicU32,
+    }
+
+    impl TestRunner {
+        fn new(exit: Exit) -> Self {
+            Self {
+                exit,
+                runs: AtomicU32::new(0),
+            }
+        }
+    }
+
+    impl BotRunner for TestRunner {
+        async fn run(&self, events: EventSink) -> NetworkResult<()> {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+            events.push(Event::FriendAdded(FriendAddedEvent {
+                uin: 10010,
+                nick: String::new(),
+            }));
+            match self.exit {
+                Exit::Fail => Err(io::Error::other("offline").into()),
+                Exit::OnStop => {
+                    events.stopped().await;
+                    Ok(())
+                }
+                Exit::Never => std::future::pending().await,
+            }
+        }
+    }
+
+    /// 等待 bot 结束运行
+    async fn wait_exit<R: BotRunner>(manager: &BotManager<R>, uin: u64) -> BotStatus {
+        let mut status = manager.watch_status(uin).unwrap();
+        let status = status
+            .wait_for(|s| s.state != BotState::Running)
+            .await
+            .unwrap();
+        status.clone()
+    }
+
+    #[tokio::test]
+    async fn test_events_and_health() {
+        let manager = BotManager::new();
+        let mut events = manager.subscribe();
+        assert!(manager.add(10086, TestRunner::new(Exit::OnStop)));
+        assert!(manager.add(10087, TestRunner::new(Exit::Fail)));
+        assert!(!manager.add(10086, TestRunner::new(Exit::OnStop)));
+
+        let mut uins = vec![
+            events.recv().await.unwrap().uin,
+            events.recv().await.unwrap().uin,
+        ];
+        uins.sort_unstable();
+        assert_eq!(uins, [10086, 10087]);
+
+        wait_exit(&manager, 10087).await;
+        let health = manager.health();
+        assert_eq!((health.running, health.failed), (1, 1));
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.bots[1].state,
+            BotState::Failed("offline".to_string())
+        );
+
+        let runner = manager.remove(10087).unwrap();
+        assert_eq!(runner.runs.load(Ordering::Relaxed), 1);
+        assert!(manager.health().is_healthy());
+        assert_eq!(manager.uins(), [10086]);
+    }
+
+    #[tokio::test]
+    async fn test_restart() {
+        let manager = BotManager::new();
+        manager.add(10086, TestRunner::new(Exit::Fail));
+        wait_exit(&manager, 10086).await;
+        assert!(manager.restart(10086));
+        assert!(!manager.restart(10010));
+        let status = wait_exit(&manager, 10086).await;
+        assert_eq!(status.restarts, 1);
+        assert!(matches!(status.state, BotState::Failed(_)));
+        let runner = manager.remove(10086).unwrap();
+        assert_eq!(runner.runs.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let manager = BotManager::new();
+        let mut events = manager.subscribe();
+        manager.add(10086, TestRunner::new(Exit::OnStop));
+        manager.add(10087, TestRunner::new(Exit::Never));
+        // 确认两个 bot 都已经开始运行
+        events.recv().await.unwrap();
+        events.recv().await.unwrap();
+
+        let health = manager.shutdown(Duration::from_millis(50)).await;
+        assert_eq!((health.running, health.stopped, health.failed), (0, 1, 1));
+        assert_eq!(health.bots[0].state, BotState::Stopped);
+        assert_eq!(
+            health.bots[1].state,
+            BotState::Failed("did not stop in time".to_string())
+        );
+    }
+}
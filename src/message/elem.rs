@@ -0,0 +1,366 @@
+//! `im_msg_body.Elem` 的编解码
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/pb/msg/objmsg.proto)
+
+use std::io;
+
+use crate::binary::{
+    protobuf::{DynamicProtoMessage, ProtoReader},
+    utils::{zlib_compress, zlib_uncompress},
+};
+
+use super::{
+    forward::{ForwardMessage, LongMessage},
+    image::{md5_from_image_id, Image, ImageKind, ImageType},
+    rich::{LightApp, ServiceMessage},
+    video::ShortVideo,
+    voice::{Voice, VoiceFormat},
+    MessageElement, MessageTarget,
+};
+
+const ELEM_TEXT: u64 = 1;
+const ELEM_NOT_ONLINE_IMAGE: u64 = 4;
+const ELEM_CUSTOM_FACE: u64 = 8;
+const ELEM_RICH_MSG: u64 = 12;
+const ELEM_VIDEO_FILE: u64 = 19;
+const ELEM_LIGHT_APP: u64 = 51;
+
+/// 编码为 `Elem`，语音等不属于 elems 的元素返回 `None`
+pub fn encode_elem(elem: &MessageElement, target: MessageTarget) -> Option<DynamicProtoMessage> {
+    let elem = match elem {
+        MessageElement::Text(text) => DynamicProtoMessage::new()
+            .with(ELEM_TEXT, DynamicProtoMessage::new().with(1, text.clone())),
+        MessageElement::Image(image) => match target {
+            MessageTarget::Group(_) => {
+                DynamicProtoMessage::new().with(ELEM_CUSTOM_FACE, encode_custom_face(image))
+            }
+            MessageTarget::Friend(_) => DynamicProtoMessage::new()
+                .with(ELEM_NOT_ONLINE_IMAGE, encode_not_online_image(image)),
+        },
+        MessageElement::Voice(_) => return None,
+        MessageElement::ShortVideo(video) => {
+            DynamicProtoMessage::new().with(ELEM_VIDEO_FILE, encode_video_file(video))
+        }
+        MessageElement::Forward(forward) => {
+            DynamicProtoMessage::new().with(ELEM_RICH_MSG, encode_rich_msg(&forward.to_xml(), 35)?)
+        }
+        MessageElement::LongMessage(long) => {
+            DynamicProtoMessage::new().with(ELEM_RICH_MSG, encode_rich_msg(&long.to_xml(), 35)?)
+        }
+        MessageElement::Service(service) => DynamicProtoMessage::new().with(
+            ELEM_RICH_MSG,
+            encode_rich_msg(&service.content, service.service_id)?,
+        ),
+        MessageElement::LightApp(app) => DynamicProtoMessage::new().with(
+            ELEM_LIGHT_APP,
+            DynamicProtoMessage::new().with(1, compress_card(&app.content)?),
+        ),
+    };
+    Some(elem)
+}
+
+/// 编码为 `RichText.ptt`
+pub fn encode_ptt(voice: &Voice) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(1, 4u32)
+        .with(3, voice.file_uuid.clone())
+        .with(4, voice.md5.to_vec())
+        .with(5, voice.name.clone())
+        .with(6, voice.size)
+        .with(8, voice.file_id)
+        .with(11, true)
+        .with(18, voice.file_key.clone())
+        .with(19, voice.duration)
+        .with(29, voice.format.code())
+        .with(30, vec![8u8, 0, 40, 0, 56, 0])
+}
+
+pub fn decode_ptt(ptt: &ProtoReader) -> Option<Voice> {
+    let md5 = ptt.get_bytes(4)?.try_into().ok()?;
+    Some(Voice {
+        md5,
+        size: ptt.get_u64(6).unwrap_or_default() as u32,
+        name: ptt.get_string(5).unwrap_or_default(),
+        format: VoiceFormat::from_code(ptt.get_u64(29).unwrap_or_default() as u32),
+        duration: ptt.get_u64(19).unwrap_or_default() as u32,
+        file_id: ptt.get_u64(8).unwrap_or_default(),
+        file_key: ptt.get_string(18).unwrap_or_default(),
+        file_uuid: ptt.get_bytes(3).unwrap_or_default().to_vec(),
+        url: ptt.get_string(20).unwrap_or_default(),
+    })
+}
+
+/// 卡片内容以一个标志字节开头，为 1 时其后为 zlib 压缩的内容，为 0 时未压缩
+fn compress_card(content: &str) -> Option<Vec<u8>> {
+    let mut data = vec![1u8];
+    data.extend(zlib_compress(content.as_bytes().to_vec()).ok()?);
+    Some(data)
+}
+
+fn uncompress_card(data: &[u8]) -> io::Result<Option<String>> {
+    let content = match data.first() {
+        Some(0) => data[1..].to_vec(),
+        Some(1) => zlib_uncompress(data[1..].to_vec())?,
+        _ => return Ok(None),
+    };
+    Ok(Some(String::from_utf8_lossy(&content).to_string()))
+}
+
+fn encode_rich_msg(content: &str, service_id: u32) -> Option<DynamicProtoMessage> {
+    Some(
+        DynamicProtoMessage::new()
+            .with(1, compress_card(content)?)
+            .with(2, service_id),
+    )
+}
+
+/// 合并转发与长消息也是 `RichMsg`，其余的作为 [`ServiceMessage`]
+fn decode_rich_msg(rich: &ProtoReader) -> io::Result<Option<MessageElement>> {
+    let Some(xml) = uncompress_card(rich.get_bytes(1).unwrap_or_default())? else {
+        return Ok(None);
+    };
+    if let Some(long) = LongMessage::from_xml(&xml) {
+        return Ok(Some(MessageElement::LongMessage(long)));
+    }
+    if let Some(forward) = ForwardMessage::from_xml(&xml) {
+        return Ok(Some(MessageElement::Forward(forward)));
+    }
+    Ok(Some(MessageElement::Service(ServiceMessage {
+        service_id: rich.get_u64(2).unwrap_or_default() as u32,
+        content: xml,
+    })))
+}
+
+fn encode_video_file(video: &ShortVideo) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(1, video.uuid.clone())
+        .with(2, video.md5.to_vec())
+        .with(3, video.name.clone())
+        .with(4, 3u32)
+        .with(5, video.duration)
+        .with(6, video.size)
+        .with(7, video.thumb_width)
+        .with(8, video.thumb_height)
+        .with(9, video.thumb_md5.to_vec())
+        .with(11, video.thumb_size)
+        .with(12, 0u32)
+        .with(13, 1u32)
+        .with(14, 1u32)
+        .with(15, true)
+}
+
+fn decode_video_file(video: &ProtoReader) -> Option<ShortVideo> {
+    Some(ShortVideo {
+        uuid: video.get_bytes(1).unwrap_or_default().to_vec(),
+        md5: video.get_bytes(2)?.try_into().ok()?,
+        name: video.get_string(3).unwrap_or_default(),
+        duration: video.get_u64(5).unwrap_or_default() as u32,
+        size: video.get_u64(6).unwrap_or_default() as u32,
+        thumb_width: video.get_u64(7).unwrap_or_default() as u32,
+        thumb_height: video.get_u64(8).unwrap_or_default() as u32,
+        thumb_md5: video
+            .get_bytes(9)
+            .and_then(|m| m.try_into().ok())
+            .unwrap_or_default(),
+        thumb_size: video.get_u64(11).unwrap_or_default() as u32,
+    })
+}
+
+fn encode_custom_face(image: &Image) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(2, image.image_id.clone())
+        .with(5, vec![0u8; 4])
+        .with(7, image.file_id)
+        .with(10, 66u32)
+        .with(12, 1u32)
+        .with(13, image.md5.to_vec())
+        .with(17, 5u32)
+        .with(20, image.image_type.code())
+        .with(22, image.width)
+        .with(23, image.height)
+        .with(25, image.size)
+        .with(26, 1u32)
+}
+
+fn encode_not_online_image(image: &Image) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(1, image.image_id.clone())
+        .with(2, image.size)
+        .with(3, image.res_id.clone())
+        .with(5, image.image_type.code())
+        .with(7, image.md5.to_vec())
+        .with(8, image.height)
+        .with(9, image.width)
+        .with(10, image.res_id.clone())
+        .with(13, 1u32)
+        .with(29, vec![0x78u8, 0x02])
+}
+
+pub fn decode_elem(elem: &ProtoReader) -> io::Result<Option<MessageElement>> {
+    if let Some(text) = elem.get_message(ELEM_TEXT)? {
+        return Ok(Some(MessageElement::Text(
+            text.get_string(1).unwrap_or_default(),
+        )));
+    }
+    if let Some(face) = elem.get_message(ELEM_CUSTOM_FACE)? {
+        return Ok(decode_custom_face(&face).map(MessageElement::Image));
+    }
+    if let Some(img) = elem.get_message(ELEM_NOT_ONLINE_IMAGE)? {
+        return Ok(decode_not_online_image(&img).map(MessageElement::Image));
+    }
+    if let Some(rich) = elem.get_message(ELEM_RICH_MSG)? {
+        return decode_rich_msg(&rich);
+    }
+    if let Some(app) = elem.get_message(ELEM_LIGHT_APP)? {
+        let content = uncompress_card(app.get_bytes(1).unwrap_or_default())?;
+        return Ok(content.map(|content| MessageElement::LightApp(LightApp { content })));
+    }
+    if let Some(video) = elem.get_message(ELEM_VIDEO_FILE)? {
+        return Ok(decode_video_file(&video).map(MessageElement::ShortVideo));
+    }
+    Ok(None)
+}
+
+fn md5_of(field: Option<&[u8]>, image_id: &str) -> Option<[u8; 16]> {
+    field
+        .and_then(|m| m.try_into().ok())
+        .or_else(|| md5_from_image_id(image_id))
+}
+
+fn decode_custom_face(face: &ProtoReader) -> Option<Image> {
+    let image_id = face.get_string(2).unwrap_or_default();
+    Some(Image {
+        md5: md5_of(face.get_bytes(13), &image_id)?,
+        size: face.get_u64(25).unwrap_or_default() as u32,
+        width: face.get_u64(22).unwrap_or_default() as u32,
+        height: face.get_u64(23).unwrap_or_default() as u32,
+        image_type: ImageType::from_code(face.get_u64(20).unwrap_or_default() as u32),
+        kind: ImageKind::Group,
+        file_id: face.get_u64(7).unwrap_or_default(),
+        res_id: String::new(),
+        url: face.get_string(16).unwrap_or_default(),
+        image_id,
+    })
+}
+
+fn decode_not_online_image(img: &ProtoReader) -> Option<Image> {
+    let image_id = img.get_string(1).unwrap_or_default();
+    Some(Image {
+        md5: md5_of(img.get_bytes(7), &image_id)?,
+        size: img.get_u64(2).unwrap_or_default() as u32,
+        width: img.get_u64(9).unwrap_or_default() as u32,
+        height: img.get_u64(8).unwrap_or_default() as u32,
+        image_type: ImageType::from_code(img.get_u64(5).unwrap_or_default() as u32),
+        kind: ImageKind::Friend,
+        file_id: 0,
+        res_id: img
+            .get_string(10)
+            .or_else(|| img.get_string(3))
+            .unwrap_or_default(),
+        url: img.get_string(15).unwrap_or_default(),
+        image_id,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{image::ImageInfo, MessageChain};
+
+    #[test]
+    fn test_chain_round_trip() {
+        let info = ImageInfo {
+            image_type: ImageType::Png,
+            width: 64,
+            height: 32,
+        };
+        for (target, kind) in [
+            (MessageTarget::Group(1), ImageKind::Group),
+            (MessageTarget::Friend(2), ImageKind::Friend),
+        ] {
+            let mut image = Image::new([7u8; 16], 1024, info, kind);
+            image.file_id = 4396;
+            let chain = MessageChain::new().with("hello ").with(image);
+
+            let rich = chain.to_rich_text(target).encode().unwrap();
+            let decoded =
+                MessageChain::from_rich_text(&ProtoReader::decode(&rich).unwrap()).unwrap();
+            assert_eq!(decoded.0[0], MessageElement::Text("hello ".to_string()));
+            let MessageElement::Image(img) = &decoded.0[1] else {
+                panic!("except image")
+            };
+            assert_eq!(img.image_id, "{07070707-0707-0707-0707-070707070707}.png");
+            assert_eq!((img.width, img.height, img.size), (64, 32, 1024));
+            assert_eq!(img.kind, kind);
+        }
+    }
+
+    #[test]
+    fn test_card_round_trip() {
+        let chain = MessageChain::new()
+            .with(LightApp::location(1.0, 2.0, "here", "there"))
+            .with(ServiceMessage::link(
+                "https://a.com",
+                "title",
+                "summary",
+                "",
+            ));
+        let rich = chain
+            .to_rich_text(MessageTarget::Friend(1))
+            .encode()
+            .unwrap();
+        let rich = ProtoReader::decode(&rich).unwrap();
+        let elems = rich.get_repeated_message(2).unwrap();
+        let data = elems[0].get_message(ELEM_LIGHT_APP).unwrap().unwrap();
+        assert_eq!(data.get_bytes(1).unwrap()[0], 1);
+        assert_eq!(MessageChain::from_rich_text(&rich).unwrap(), chain);
+
+        let mut raw = vec![0u8];
+        raw.extend_from_slice(br#"<msg serviceID="33"/>"#);
+        let elem = DynamicProtoMessage::new()
+            .with(
+                ELEM_RICH_MSG,
+                DynamicProtoMessage::new().with(1, raw).with(2, 33u32),
+            )
+            .encode()
+            .unwrap();
+        let decoded = decode_elem(&ProtoReader::decode(&elem).unwrap()).unwrap();
+        assert_eq!(
+            decoded,
+            Some(MessageElement::Service(ServiceMessage::new(
+                33,
+                r#"<msg serviceID="33"/>"#
+            )))
+        );
+    }
+
+    #[test]
+    fn test_short_video_round_trip() {
+        let mut video = ShortVideo::new([3u8; 16], 40960, [4u8; 16], 1024);
+        video.uuid = b"video-uuid".to_vec();
+        (video.thumb_width, video.thumb_height) = (320, 240);
+        let chain = MessageChain::new().with(video.clone());
+
+        let rich = chain
+            .to_rich_text(MessageTarget::Group(1))
+            .encode()
+            .unwrap();
+        let decoded = MessageChain::from_rich_text(&ProtoReader::decode(&rich).unwrap()).unwrap();
+        assert_eq!(decoded.0, vec![MessageElement::ShortVideo(video)]);
+    }
+
+    #[test]
+    fn test_voice_in_rich_text() {
+        let mut voice = Voice::new([1u8; 16], 2048, VoiceFormat::Silk, 3);
+        voice.file_key = "key".to_string();
+        let chain = MessageChain::new().with(voice.clone());
+
+        let rich = chain
+            .to_rich_text(MessageTarget::Group(1))
+            .encode()
+            .unwrap();
+        let rich = ProtoReader::decode(&rich).unwrap();
+        assert!(rich.get_repeated_bytes(2).is_empty());
+        let decoded = MessageChain::from_rich_text(&rich).unwrap();
+        assert_eq!(decoded.0, vec![MessageElement::Voice(voice)]);
+    }
+}
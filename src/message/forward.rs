@@ -0,0 +1,188 @@
+//! 合并转发消息，以及使用同一种卡片的长消息
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/message/forward.go)
+
+use super::MessageChain;
+
+/// 合并转发消息中预览显示的条数
+const PREVIEW_COUNT: usize = 4;
+
+/// 合并转发中的一条消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardNode {
+    pub sender_uin: u64,
+    pub sender_name: String,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+    pub chain: MessageChain,
+}
+
+/// 合并转发消息，可以嵌套在 [`ForwardNode`] 的消息链中
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardMessage {
+    /// 上传后服务器返回的 resid，收到的合并转发需要通过它下载内容
+    pub res_id: String,
+    /// 嵌套时在外层 `PbMultiMsgTransmit` 中的文件名
+    pub file_name: String,
+    /// 节点数量，收到而尚未下载时 `nodes` 为空，数量取自卡片
+    pub count: usize,
+    pub nodes: Vec<ForwardNode>,
+}
+
+impl ForwardMessage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_node(
+        mut self,
+        sender_uin: u64,
+        sender_name: impl Into<String>,
+        time: u32,
+        chain: MessageChain,
+    ) -> Self {
+        self.push(ForwardNode {
+            sender_uin,
+            sender_name: sender_name.into(),
+            time,
+            chain,
+        });
+        self
+    }
+
+    pub fn push(&mut self, node: ForwardNode) {
+        self.nodes.push(node);
+        self.count = self.nodes.len();
+    }
+
+    /// 生成 serviceID 为 35 的 XML 卡片
+    pub fn to_xml(&self) -> String {
+        let previews = self
+            .nodes
+            .iter()
+            .take(PREVIEW_COUNT)
+            .map(|node| {
+                format!(
+                    r##"<title size="26" color="#777777" maxLines="2" lineSpace="12">{}: {}</title>"##,
+                    xml_escape(&node.sender_name),
+                    xml_escape(&node.chain.summary())
+                )
+            })
+            .collect::<String>();
+        format!(
+            r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="[聊天记录]" m_resid="{}" m_fileName="{}" tSum="{}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="0"><item layout="1" advertiser_id="0" aid="0"><title size="34" maxLines="2" lineSpace="12">群聊的聊天记录</title>{}<hr hidden="false" style="0" /><summary size="26" color="#777777">查看{}条转发消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##,
+            xml_escape(&self.res_id),
+            xml_escape(&self.file_name),
+            self.count,
+            previews,
+            self.count,
+        )
+    }
+
+    /// 从收到的 XML 卡片中取出 resid 与文件名，不是合并转发时返回 `None`
+    pub fn from_xml(xml: &str) -> Option<Self> {
+        if !is_multi_msg_card(xml) || is_long_msg_card(xml) {
+            return None;
+        }
+        Some(Self {
+            res_id: xml_attr(xml, "m_resid").unwrap_or_default(),
+            file_name: xml_attr(xml, "m_fileName").unwrap_or_default(),
+            count: xml_attr(xml, "tSum")
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_default(),
+            nodes: Vec::new(),
+        })
+    }
+}
+
+/// 长消息的占位卡片，完整内容需要通过 resid 下载
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LongMessage {
+    pub res_id: String,
+    /// 卡片中显示的消息开头
+    pub brief: String,
+}
+
+impl LongMessage {
+    pub fn to_xml(&self) -> String {
+        format!(
+            r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="{}" m_resid="{}" m_fileName="{}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="1"><item layout="1"><title>{}</title><hr hidden="false" style="0" /><summary>点击查看完整消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##,
+            xml_escape(&self.brief),
+            xml_escape(&self.res_id),
+            rand::random::<u32>(),
+            xml_escape(&self.brief),
+        )
+    }
+
+    pub fn from_xml(xml: &str) -> Option<Self> {
+        if !is_multi_msg_card(xml) || !is_long_msg_card(xml) {
+            return None;
+        }
+        Some(Self {
+            res_id: xml_attr(xml, "m_resid")?,
+            brief: xml_attr(xml, "brief").unwrap_or_default(),
+        })
+    }
+}
+
+fn is_multi_msg_card(xml: &str) -> bool {
+    xml_attr(xml, "serviceID").as_deref() == Some("35")
+        && xml_attr(xml, "action").as_deref() == Some("viewMultiMsg")
+}
+
+fn is_long_msg_card(xml: &str) -> bool {
+    xml_attr(xml, "multiMsgFlag").as_deref() == Some("1")
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 取出第一个同名属性的值
+pub(crate) fn xml_attr(xml: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {}=\"", name);
+    let start = xml.find(&pattern)? + pattern.len();
+    let len = xml[start..].find('"')?;
+    Some(xml_unescape(&xml[start..start + len]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_xml_round_trip() {
+        let mut forward = ForwardMessage::new()
+            .with_node(10086, "<bot>", 1700000000, MessageChain::new().with("hi"))
+            .with_node(10010, "user", 1700000001, MessageChain::new().with("yo"));
+        forward.res_id = "res/id".to_string();
+
+        let xml = forward.to_xml();
+        assert!(xml.contains("&lt;bot&gt;: hi"));
+        let parsed = ForwardMessage::from_xml(&xml).unwrap();
+        assert_eq!(parsed.res_id, "res/id");
+        assert_eq!(parsed.count, 2);
+        assert!(parsed.nodes.is_empty());
+
+        assert!(ForwardMessage::from_xml(r#"<msg serviceID="1" action="web">"#).is_none());
+
+        let long = LongMessage {
+            res_id: "long".to_string(),
+            brief: "a very long".to_string(),
+        };
+        let xml = long.to_xml();
+        assert!(ForwardMessage::from_xml(&xml).is_none());
+        assert_eq!(LongMessage::from_xml(&xml), Some(long));
+    }
+}
@@ -0,0 +1,336 @@
+//! 图片元素与图片格式识别
+
+use std::io::Cursor;
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageType {
+    Jpg,
+    Png,
+    Gif,
+    Bmp,
+    Webp,
+    Unknown,
+}
+
+impl ImageType {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageType::Jpg | ImageType::Unknown => "jpg",
+            ImageType::Png => "png",
+            ImageType::Gif => "gif",
+            ImageType::Bmp => "bmp",
+            ImageType::Webp => "webp",
+        }
+    }
+
+    /// 协议中使用的图片类型编号
+    pub fn code(&self) -> u32 {
+        match self {
+            ImageType::Jpg | ImageType::Unknown => 1000,
+            ImageType::Png => 1001,
+            ImageType::Webp => 1002,
+            ImageType::Bmp => 1005,
+            ImageType::Gif => 2000,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            1000 => ImageType::Jpg,
+            1001 => ImageType::Png,
+            1002 => ImageType::Webp,
+            1005 => ImageType::Bmp,
+            2000 | 3 => ImageType::Gif,
+            _ => ImageType::Unknown,
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Self {
+        match ext.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => ImageType::Jpg,
+            "png" => ImageType::Png,
+            "gif" => ImageType::Gif,
+            "bmp" => ImageType::Bmp,
+            "webp" => ImageType::Webp,
+            _ => ImageType::Unknown,
+        }
+    }
+}
+
+/// 从文件头得到的图片信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub image_type: ImageType,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageInfo {
+    /// 识别 jpg/png/gif/bmp/webp 及其宽高，无法识别时返回 `None`
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        let (image_type, (width, height)) = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            (ImageType::Png, png_size(data)?)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            (ImageType::Gif, gif_size(data)?)
+        } else if data.starts_with(b"BM") {
+            (ImageType::Bmp, bmp_size(data)?)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            (ImageType::Webp, webp_size(data)?)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            (ImageType::Jpg, jpg_size(data)?)
+        } else {
+            return None;
+        };
+        Some(Self {
+            image_type,
+            width,
+            height,
+        })
+    }
+}
+
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut rd = Cursor::new(data.get(16..24)?);
+    Some((
+        rd.read_u32::<BigEndian>().ok()?,
+        rd.read_u32::<BigEndian>().ok()?,
+    ))
+}
+
+fn gif_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut rd = Cursor::new(data.get(6..10)?);
+    Some((
+        rd.read_u16::<LittleEndian>().ok()? as u32,
+        rd.read_u16::<LittleEndian>().ok()? as u32,
+    ))
+}
+
+fn bmp_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut rd = Cursor::new(data.get(18..26)?);
+    Some((
+        rd.read_i32::<LittleEndian>().ok()?.unsigned_abs(),
+        rd.read_i32::<LittleEndian>().ok()?.unsigned_abs(),
+    ))
+}
+
+fn webp_size(data: &[u8]) -> Option<(u32, u32)> {
+    let u24 = |b: &[u8]| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16;
+    match data.get(12..16)? {
+        b"VP8 " => {
+            let mut rd = Cursor::new(data.get(26..30)?);
+            Some((
+                (rd.read_u16::<LittleEndian>().ok()? & 0x3fff) as u32,
+                (rd.read_u16::<LittleEndian>().ok()? & 0x3fff) as u32,
+            ))
+        }
+        b"VP8L" => {
+            let b = data.get(21..25)?;
+            let bits = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        b"VP8X" => {
+            let b = data.get(24..30)?;
+            Some((u24(&b[0..3]) + 1, u24(&b[3..6]) + 1))
+        }
+        _ => None,
+    }
+}
+
+/// 查找 SOF 段读取宽高
+fn jpg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut idx = 2;
+    while idx + 9 < data.len() {
+        if data[idx] != 0xff {
+            idx += 1;
+            continue;
+        }
+        let marker = data[idx + 1];
+        let seg_len = u16::from_be_bytes([data[idx + 2], data[idx + 3]]) as usize;
+        if (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            let height = u16::from_be_bytes([data[idx + 5], data[idx + 6]]) as u32;
+            let width = u16::from_be_bytes([data[idx + 7], data[idx + 8]]) as u32;
+            return Some((width, height));
+        }
+        idx += 2 + seg_len;
+    }
+    None
+}
+
+/// 以 `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}.ext` 的形式表示 md5
+pub fn image_id(md5: &[u8; 16], image_type: ImageType) -> String {
+    let hex = hex::encode_upper(md5);
+    format!(
+        "{{{}-{}-{}-{}-{}}}.{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32],
+        image_type.extension()
+    )
+}
+
+/// 从 image id 中取回 md5
+pub fn md5_from_image_id(image_id: &str) -> Option<[u8; 16]> {
+    let hex = image_id
+        .split('.')
+        .next()?
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>();
+    hex::decode(hex).ok()?.try_into().ok()
+}
+
+/// 图片所在的会话类型，决定了下载地址的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Group,
+    Friend,
+}
+
+const GROUP_IMAGE_DOMAIN: &str = "https://gchat.qpic.cn";
+const FRIEND_IMAGE_DOMAIN: &str = "https://c2cpicdw.qpic.cn";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// `{MD5}.ext` 形式的 id
+    pub image_id: String,
+    pub md5: [u8; 16],
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub image_type: ImageType,
+    pub kind: ImageKind,
+    /// 群图片的 file id
+    pub file_id: u64,
+    /// 好友图片的 resource id
+    pub res_id: String,
+    /// 收到的图片中携带的原图下载路径，可能只有 path 部分
+    pub url: String,
+}
+
+impl Image {
+    pub fn new(md5: [u8; 16], size: u32, info: ImageInfo, kind: ImageKind) -> Self {
+        Self {
+            image_id: image_id(&md5, info.image_type),
+            md5,
+            size,
+            width: info.width,
+            height: info.height,
+            image_type: info.image_type,
+            kind,
+            file_id: 0,
+            res_id: String::new(),
+            url: String::new(),
+        }
+    }
+
+    /// 原图下载地址，优先使用消息中携带的路径
+    pub fn original_url(&self) -> String {
+        self.sized_url(0)
+    }
+
+    /// 缩略图下载地址
+    pub fn thumbnail_url(&self) -> String {
+        self.sized_url(198)
+    }
+
+    fn sized_url(&self, size: u32) -> String {
+        let domain = match self.kind {
+            ImageKind::Group => GROUP_IMAGE_DOMAIN,
+            ImageKind::Friend => FRIEND_IMAGE_DOMAIN,
+        };
+        if size == 0 && self.url.starts_with("http") {
+            return self.url.clone();
+        }
+        if size == 0 && self.url.starts_with('/') {
+            return format!("{}{}", domain, self.url);
+        }
+        match self.kind {
+            ImageKind::Friend if !self.res_id.is_empty() => {
+                format!("{}/offpic_new/0/{}/{}?term=2", domain, self.res_id, size)
+            }
+            _ => format!(
+                "{}/gchatpic_new/0/0-0-{}/{}?term=2",
+                GROUP_IMAGE_DOMAIN,
+                hex::encode_upper(self.md5),
+                size
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 1, 0, 0, 0, 0, 200]);
+        let info = ImageInfo::detect(&png).unwrap();
+        assert_eq!(info.image_type, ImageType::Png);
+        assert_eq!((info.width, info.height), (256, 200));
+
+        let gif = b"GIF89a\x40\x01\xf0\x00".to_vec();
+        let info = ImageInfo::detect(&gif).unwrap();
+        assert_eq!((info.width, info.height), (320, 240));
+
+        let jpg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x01,
+            0xe0, 0x02, 0x80, 0x03,
+        ];
+        let info = ImageInfo::detect(&jpg).unwrap();
+        assert_eq!(info.image_type, ImageType::Jpg);
+        assert_eq!((info.width, info.height), (640, 480));
+
+        assert!(ImageInfo::detect(b"not an image").is_none());
+    }
+
+    #[test]
+    fn test_image_id() {
+        let md5 = [
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+            0xcd, 0xef,
+        ];
+        let id = image_id(&md5, ImageType::Png);
+        assert_eq!(id, "{01234567-89AB-CDEF-0123-456789ABCDEF}.png");
+        assert_eq!(md5_from_image_id(&id), Some(md5));
+    }
+
+    #[test]
+    fn test_download_url() {
+        let info = ImageInfo {
+            image_type: ImageType::Jpg,
+            width: 1,
+            height: 1,
+        };
+        let mut image = Image::new([0xab; 16], 1, info, ImageKind::Group);
+        let md5 = "AB".repeat(16);
+        assert_eq!(
+            image.original_url(),
+            format!("https://gchat.qpic.cn/gchatpic_new/0/0-0-{}/0?term=2", md5)
+        );
+        assert_eq!(
+            image.thumbnail_url(),
+            format!(
+                "https://gchat.qpic.cn/gchatpic_new/0/0-0-{}/198?term=2",
+                md5
+            )
+        );
+
+        image.kind = ImageKind::Friend;
+        image.res_id = "/12345-abcdef".to_string();
+        assert_eq!(
+            image.thumbnail_url(),
+            "https://c2cpicdw.qpic.cn/offpic_new/0//12345-abcdef/198?term=2"
+        );
+        image.url = "/offpic_new/12345//12345-abcdef/0?term=2".to_string();
+        assert_eq!(
+            image.original_url(),
+            "https://c2cpicdw.qpic.cn/offpic_new/12345//12345-abcdef/0?term=2"
+        );
+    }
+}
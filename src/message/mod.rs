@@ -0,0 +1,173 @@
+//! 消息链
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/message/message.go)
+
+use std::io;
+
+use crate::binary::protobuf::{DynamicProtoMessage, ProtoReader};
+
+use self::{
+    forward::{ForwardMessage, LongMessage},
+    image::Image,
+    rich::{LightApp, ServiceMessage},
+    video::ShortVideo,
+    voice::Voice,
+};
+
+pub mod elem;
+pub mod forward;
+pub mod image;
+pub mod rich;
+#[cfg(feature = "silk-cli")]
+pub mod silk_cli;
+pub mod video;
+pub mod voice;
+
+/// 消息的发送目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageTarget {
+    Group(u64),
+    Friend(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageElement {
+    Text(String),
+    Image(Image),
+    Voice(Voice),
+    ShortVideo(ShortVideo),
+    Forward(ForwardMessage),
+    /// 收到的长消息占位，[`receive_group_message`](crate::network::message::receive_group_message) 会自动展开
+    LongMessage(LongMessage),
+    LightApp(LightApp),
+    Service(ServiceMessage),
+}
+
+impl From<&str> for MessageElement {
+    fn from(text: &str) -> Self {
+        MessageElement::Text(text.to_string())
+    }
+}
+
+impl From<String> for MessageElement {
+    fn from(text: String) -> Self {
+        MessageElement::Text(text)
+    }
+}
+
+impl From<Image> for MessageElement {
+    fn from(image: Image) -> Self {
+        MessageElement::Image(image)
+    }
+}
+
+impl From<Voice> for MessageElement {
+    fn from(voice: Voice) -> Self {
+        MessageElement::Voice(voice)
+    }
+}
+
+impl From<ShortVideo> for MessageElement {
+    fn from(video: ShortVideo) -> Self {
+        MessageElement::ShortVideo(video)
+    }
+}
+
+impl From<LightApp> for MessageElement {
+    fn from(app: LightApp) -> Self {
+        MessageElement::LightApp(app)
+    }
+}
+
+impl From<ServiceMessage> for MessageElement {
+    fn from(service: ServiceMessage) -> Self {
+        MessageElement::Service(service)
+    }
+}
+
+impl From<ForwardMessage> for MessageElement {
+    fn from(forward: ForwardMessage) -> Self {
+        MessageElement::Forward(forward)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageChain(pub Vec<MessageElement>);
+
+impl MessageChain {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn with(mut self, elem: impl Into<MessageElement>) -> Self {
+        self.push(elem);
+        self
+    }
+
+    pub fn push(&mut self, elem: impl Into<MessageElement>) {
+        self.0.push(elem.into());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MessageElement> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 纯文本摘要，非文本元素显示为 `[图片]` 等，用于预览与日志
+    pub fn summary(&self) -> String {
+        self.0
+            .iter()
+            .map(|e| match e {
+                MessageElement::Text(text) => text.as_str(),
+                MessageElement::Image(_) => "[图片]",
+                MessageElement::Voice(_) => "[语音]",
+                MessageElement::ShortVideo(_) => "[视频]",
+                MessageElement::Forward(_) => "[聊天记录]",
+                MessageElement::LongMessage(long) => long.brief.as_str(),
+                MessageElement::LightApp(_) | MessageElement::Service(_) => "[卡片]",
+            })
+            .collect()
+    }
+
+    /// 编码为 `RichText.elems`
+    pub fn to_elems(&self, target: MessageTarget) -> Vec<DynamicProtoMessage> {
+        self.0
+            .iter()
+            .filter_map(|e| elem::encode_elem(e, target))
+            .collect()
+    }
+
+    /// 编码为 `RichText`，语音放在 `ptt` 字段中
+    pub fn to_rich_text(&self, target: MessageTarget) -> DynamicProtoMessage {
+        let mut rich = DynamicProtoMessage::new().with(2, self.to_elems(target));
+        let voice = self.0.iter().find_map(|e| match e {
+            MessageElement::Voice(v) => Some(v),
+            _ => None,
+        });
+        if let Some(voice) = voice {
+            rich.set(4, elem::encode_ptt(voice));
+        }
+        rich
+    }
+
+    /// 从 `RichText.elems` 解码，无法识别的元素将被忽略
+    pub fn from_elems(elems: &[ProtoReader]) -> io::Result<Self> {
+        let mut chain = Self::new();
+        for e in elems {
+            if let Some(elem) = elem::decode_elem(e)? {
+                chain.0.push(elem);
+            }
+        }
+        Ok(chain)
+    }
+
+    pub fn from_rich_text(rich: &ProtoReader) -> io::Result<Self> {
+        let mut chain = Self::from_elems(&rich.get_repeated_message(2)?)?;
+        if let Some(voice) = rich.get_message(4)?.as_ref().and_then(elem::decode_ptt) {
+            chain.0.push(MessageElement::Voice(voice));
+        }
+        Ok(chain)
+    }
+}
@@ -0,0 +1,143 @@
+//! 卡片消息，`LightApp` 为 JSON，`RichMsg` 为 XML 服务消息
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/message/message.go)
+
+use serde_json::json;
+
+use super::forward::xml_escape;
+
+/// JSON 卡片（小程序）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightApp {
+    pub content: String,
+}
+
+impl LightApp {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+        }
+    }
+
+    /// 位置分享
+    pub fn location(lat: f64, lng: f64, title: &str, address: &str) -> Self {
+        let content = json!({
+            "app": "com.tencent.map",
+            "desc": "地图",
+            "view": "LocationShare",
+            "ver": "0.0.0.1",
+            "prompt": format!("[位置]{}", title),
+            "meta": {
+                "Location.Search": {
+                    "from": "plusPanel",
+                    "id": "",
+                    "lat": lat.to_string(),
+                    "lng": lng.to_string(),
+                    "name": title,
+                    "address": address,
+                }
+            },
+            "config": { "forward": true, "autosize": 1, "type": "card" },
+        });
+        Self::new(content.to_string())
+    }
+
+    /// 卡片的 `prompt`，即会话列表中显示的文字
+    pub fn prompt(&self) -> Option<String> {
+        let value: serde_json::Value = serde_json::from_str(&self.content).ok()?;
+        value.get("prompt")?.as_str().map(str::to_string)
+    }
+}
+
+/// XML 服务消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceMessage {
+    pub service_id: u32,
+    pub content: String,
+}
+
+impl ServiceMessage {
+    pub fn new(service_id: u32, content: impl Into<String>) -> Self {
+        Self {
+            service_id,
+            content: content.into(),
+        }
+    }
+
+    /// 链接分享卡片
+    pub fn link(url: &str, title: &str, summary: &str, picture_url: &str) -> Self {
+        let content = format!(
+            r#"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="1" templateID="1" action="web" brief="[分享] {}" sourceMsgId="0" url="{}" flag="0" adverSign="0" multiMsgFlag="0"><item layout="2" advertiser_id="0" aid="0"><picture cover="{}" w="0" h="0" /><title>{}</title><summary>{}</summary></item><source name="" icon="" action="" appid="-1" /></msg>"#,
+            xml_escape(title),
+            xml_escape(url),
+            xml_escape(picture_url),
+            xml_escape(title),
+            xml_escape(summary),
+        );
+        Self::new(1, content)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MusicKind {
+    QQ,
+    NetEase,
+    Kugou,
+}
+
+/// 音乐平台在 `OidbSvc.0xb77_9` 中使用的应用信息
+pub(crate) struct MusicAppInfo {
+    pub app_id: u64,
+    pub package_name: &'static str,
+    pub signature: &'static str,
+}
+
+impl MusicKind {
+    pub(crate) fn app_info(&self) -> MusicAppInfo {
+        match self {
+            MusicKind::QQ => MusicAppInfo {
+                app_id: 100497308,
+                package_name: "com.tencent.qqmusic",
+                signature: "cbd27cd7c861227d013a25b2d10f0799",
+            },
+            MusicKind::NetEase => MusicAppInfo {
+                app_id: 100495085,
+                package_name: "com.netease.cloudmusic",
+                signature: "da6b069da1e2982db3e386233f68d76d",
+            },
+            MusicKind::Kugou => MusicAppInfo {
+                app_id: 205141,
+                package_name: "com.kugou.android",
+                signature: "fe4a24d80fcf253a00676a808f62c2c6",
+            },
+        }
+    }
+}
+
+/// 音乐分享，通过 [`send_music_share`](crate::network::share::send_music_share) 发送，
+/// 由服务器生成卡片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MusicShare {
+    pub kind: MusicKind,
+    pub title: String,
+    pub summary: String,
+    /// 点击卡片跳转的地址
+    pub jump_url: String,
+    pub picture_url: String,
+    pub music_url: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_card_builders() {
+        let location = LightApp::location(39.9, 116.3, "天安门", "北京市东城区");
+        assert_eq!(location.prompt().as_deref(), Some("[位置]天安门"));
+
+        let link = ServiceMessage::link("https://a.com/?a=1&b=2", "<t>", "s", "");
+        assert_eq!(link.service_id, 1);
+        assert!(link.content.contains(r#"url="https://a.com/?a=1&amp;b=2""#));
+        assert!(link.content.contains("<title>&lt;t&gt;</title>"));
+    }
+}
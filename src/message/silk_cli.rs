@@ -0,0 +1,123 @@
+//! 通过外部命令行程序实现的 SILK 编解码器，需要启用 `silk-cli` feature
+//!
+//! 本库不内置 SILK 编解码，程序需要自行从
+//! [silk-v3-decoder](https://github.com/kn007/silk-v3-decoder) 编译并安装
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use super::voice::SilkCodec;
+
+/// 调用 [silk-v3-decoder](https://github.com/kn007/silk-v3-decoder) 中的
+/// `encoder` 与 `decoder` 命令行程序完成编解码，PCM 通过临时文件传递
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SilkCli {
+    pub encoder: PathBuf,
+    pub decoder: PathBuf,
+}
+
+impl Default for SilkCli {
+    /// 从 `PATH` 中查找 silk-v3-decoder 编译出的 `encoder` 与 `decoder`
+    fn default() -> Self {
+        Self {
+            encoder: "encoder".into(),
+            decoder: "decoder".into(),
+        }
+    }
+}
+
+/// 释放时删除的临时文件
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(ext: &str) -> Self {
+        let name = format!("silk-{}.{}", hex::encode(rand::random::<[u8; 8]>()), ext);
+        Self(std::env::temp_dir().join(name))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl SilkCli {
+    /// 以 `<程序> <输入> <输出> -Fs_API <采样率> [参数]` 的形式运行并读取输出文件
+    fn run(program: &Path, input: &[u8], sample_rate: u32, extra: &[&str]) -> io::Result<Vec<u8>> {
+        let (src, dst) = (TempFile::new("in"), TempFile::new("out"));
+        fs::write(&src.0, input)?;
+        let status = Command::new(program)
+            .arg(&src.0)
+            .arg(&dst.0)
+            .arg("-Fs_API")
+            .arg(sample_rate.to_string())
+            .args(extra)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                program.display(),
+                status
+            )));
+        }
+        fs::read(&dst.0)
+    }
+}
+
+impl SilkCodec for SilkCli {
+    fn encode(&self, pcm: &[i16], sample_rate: u32) -> io::Result<Vec<u8>> {
+        let raw = pcm.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<_>>();
+        // `-tencent` 输出 QQ 使用的带 `0x02` 前缀的文件头
+        Self::run(&self.encoder, &raw, sample_rate, &["-tencent", "-quiet"])
+    }
+
+    fn decode(&self, silk: &[u8], sample_rate: u32) -> io::Result<Vec<i16>> {
+        let raw = Self::run(&self.decoder, silk, sample_rate, &["-quiet"])?;
+        Ok(raw
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 用 `cp` 代替编解码器，检查 PCM 与参数的传递
+    #[cfg(unix)]
+    #[test]
+    fn test_silk_cli() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = TempFile::new("sh");
+        fs::write(
+            &script.0,
+            "#!/bin/sh\n[ \"$4\" = 24000 ] && cp \"$1\" \"$2\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script.0, fs::Permissions::from_mode(0o755)).unwrap();
+        let codec = SilkCli {
+            encoder: script.0.clone(),
+            decoder: script.0.clone(),
+        };
+        let samples = [1i16, -2, 300];
+        let encoded = codec.encode(&samples, 24000).unwrap();
+        assert_eq!(encoded, [1, 0, 0xfe, 0xff, 0x2c, 0x01]);
+        assert_eq!(codec.decode(&encoded, 24000).unwrap(), samples);
+        assert!(codec.encode(&samples, 16000).is_err());
+
+        let missing = SilkCli {
+            encoder: "/nonexistent/encoder".into(),
+            ..SilkCli::default()
+        };
+        assert!(missing.encode(&samples, 24000).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! 短视频元素
+
+/// 短视频，上传时视频与缩略图一同提交
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortVideo {
+    /// 服务器返回的 file id
+    pub uuid: Vec<u8>,
+    pub name: String,
+    pub md5: [u8; 16],
+    pub size: u32,
+    pub thumb_md5: [u8; 16],
+    pub thumb_size: u32,
+    pub thumb_width: u32,
+    pub thumb_height: u32,
+    /// 时长，单位为秒
+    pub duration: u32,
+}
+
+impl ShortVideo {
+    pub fn new(md5: [u8; 16], size: u32, thumb_md5: [u8; 16], thumb_size: u32) -> Self {
+        Self {
+            uuid: Vec::new(),
+            name: format!("{}.mp4", hex::encode(md5)),
+            md5,
+            size,
+            thumb_md5,
+            thumb_size,
+            thumb_width: 0,
+            thumb_height: 0,
+            duration: 0,
+        }
+    }
+
+    /// uuid 的字符串形式，下载时使用
+    pub fn file_id(&self) -> String {
+        String::from_utf8_lossy(&self.uuid).to_string()
+    }
+}
@@ -0,0 +1,341 @@
+//! 语音元素，QQ 语音使用 SILK v3 或 AMR 编码
+
+use std::io::{self, Cursor, Read};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+const SILK_HEADER: &[u8] = b"#!SILK_V3";
+const AMR_HEADER: &[u8] = b"#!AMR\n";
+/// SILK 与 AMR 每帧均为 20ms
+const FRAME_MILLIS: u32 = 20;
+/// AMR-NB 各模式每帧的数据长度（不含帧头）
+const AMR_FRAME_SIZE: [usize; 16] = [12, 13, 15, 17, 19, 20, 26, 31, 5, 0, 0, 0, 0, 0, 0, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceFormat {
+    Amr,
+    Silk,
+}
+
+impl VoiceFormat {
+    /// 识别 SILK（兼容腾讯在文件头前加的 `0x02`）与 AMR 格式
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(SILK_HEADER)
+            || data.get(1..).is_some_and(|d| d.starts_with(SILK_HEADER))
+        {
+            Some(VoiceFormat::Silk)
+        } else if data.starts_with(AMR_HEADER) {
+            Some(VoiceFormat::Amr)
+        } else {
+            None
+        }
+    }
+
+    /// `Ptt.format` 中的编号
+    pub fn code(&self) -> u32 {
+        match self {
+            VoiceFormat::Amr => 0,
+            VoiceFormat::Silk => 1,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            1 => VoiceFormat::Silk,
+            _ => VoiceFormat::Amr,
+        }
+    }
+}
+
+/// 通过帧数估算语音时长，单位为秒，至少为 1
+pub fn voice_duration(data: &[u8], format: VoiceFormat) -> u32 {
+    let frames = match format {
+        VoiceFormat::Silk => {
+            let start = if data.first() == Some(&0x02) { 1 } else { 0 } + SILK_HEADER.len();
+            let mut rd = Cursor::new(data.get(start..).unwrap_or_default());
+            let mut frames = 0;
+            while let Ok(len) = rd.read_i16::<LittleEndian>() {
+                if len < 0 {
+                    break;
+                }
+                rd.set_position(rd.position() + len as u64);
+                if rd.position() as usize > rd.get_ref().len() {
+                    break;
+                }
+                frames += 1;
+            }
+            frames
+        }
+        VoiceFormat::Amr => {
+            let mut idx = AMR_HEADER.len();
+            let mut frames = 0;
+            while idx < data.len() {
+                idx += 1 + AMR_FRAME_SIZE[((data[idx] >> 3) & 0x0f) as usize];
+                frames += 1;
+            }
+            frames
+        }
+    };
+    (frames * FRAME_MILLIS / 1000).max(1)
+}
+
+/// SILK 编解码器，本库不内置实现。启用 `silk-cli` feature 后可以使用
+/// `silk_cli::SilkCli` 调用外部程序，也可以自行封装其他实现
+pub trait SilkCodec {
+    /// 将单声道 16bit PCM 编码为 SILK v3
+    fn encode(&self, pcm: &[i16], sample_rate: u32) -> io::Result<Vec<u8>>;
+    /// 将 SILK v3 解码为单声道 16bit PCM
+    fn decode(&self, silk: &[u8], sample_rate: u32) -> io::Result<Vec<i16>>;
+}
+
+/// 待发送的音频
+pub enum AudioInput {
+    /// 已经编码好的 SILK 或 AMR
+    Encoded(Vec<u8>),
+    /// 16bit PCM WAV 文件
+    Wav(Vec<u8>),
+    /// 单声道 16bit PCM
+    Pcm { samples: Vec<i16>, sample_rate: u32 },
+}
+
+/// 单声道 PCM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcm {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 解析 16bit PCM WAV，多声道时取平均值混为单声道
+pub fn wav_to_pcm(data: &[u8]) -> io::Result<Pcm> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a wav file"));
+    }
+    let mut rd = Cursor::new(&data[12..]);
+    let (mut channels, mut sample_rate, mut bits) = (0u16, 0u32, 0u16);
+    loop {
+        let mut id = [0u8; 4];
+        rd.read_exact(&mut id)?;
+        let size = rd.read_u32::<LittleEndian>()? as usize;
+        let remaining = rd.get_ref().len() - rd.position() as usize;
+        match &id {
+            b"fmt " => {
+                if size > remaining {
+                    return Err(invalid("truncated fmt chunk"));
+                }
+                let mut fmt = vec![0u8; size];
+                rd.read_exact(&mut fmt)?;
+                let mut f = Cursor::new(fmt);
+                if f.read_u16::<LittleEndian>()? != 1 {
+                    return Err(invalid("only pcm wav is supported"));
+                }
+                channels = f.read_u16::<LittleEndian>()?;
+                sample_rate = f.read_u32::<LittleEndian>()?;
+                f.set_position(14);
+                bits = f.read_u16::<LittleEndian>()?;
+            }
+            b"data" => {
+                if bits != 16 || channels == 0 {
+                    return Err(invalid("only 16bit pcm wav is supported"));
+                }
+                // 流式写出的文件中 data 的长度可能为 0xffffffff，只读取实际存在的部分
+                let start = rd.position() as usize;
+                let raw = &rd.get_ref()[start..start + size.min(remaining)];
+                let samples = raw
+                    .chunks_exact(2 * channels as usize)
+                    .map(|frame| {
+                        let sum: i32 = frame
+                            .chunks_exact(2)
+                            .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                            .sum();
+                        (sum / channels as i32) as i16
+                    })
+                    .collect();
+                return Ok(Pcm {
+                    samples,
+                    sample_rate,
+                });
+            }
+            _ => rd.set_position(rd.position() + size as u64 + (size % 2) as u64),
+        }
+    }
+}
+
+/// 将音频转为可以上传的 SILK 或 AMR 数据
+///
+/// 已编码的数据直接返回，WAV 与 PCM 需要提供 `codec`
+pub fn encode_voice(
+    input: AudioInput,
+    codec: Option<&dyn SilkCodec>,
+) -> io::Result<(Vec<u8>, VoiceFormat)> {
+    let pcm = match input {
+        AudioInput::Encoded(data) => {
+            let format =
+                VoiceFormat::detect(&data).ok_or_else(|| invalid("unknown voice format"))?;
+            return Ok((data, format));
+        }
+        AudioInput::Wav(data) => wav_to_pcm(&data)?,
+        AudioInput::Pcm {
+            samples,
+            sample_rate,
+        } => Pcm {
+            samples,
+            sample_rate,
+        },
+    };
+    let codec = codec.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "silk codec is required to encode pcm",
+        )
+    })?;
+    Ok((
+        codec.encode(&pcm.samples, pcm.sample_rate)?,
+        VoiceFormat::Silk,
+    ))
+}
+
+/// 将收到的语音解码为 PCM，用于语音识别等场景，AMR 暂不支持解码
+pub fn decode_voice(data: &[u8], codec: &dyn SilkCodec, sample_rate: u32) -> io::Result<Pcm> {
+    match VoiceFormat::detect(data) {
+        Some(VoiceFormat::Silk) => Ok(Pcm {
+            samples: codec.decode(data, sample_rate)?,
+            sample_rate,
+        }),
+        Some(VoiceFormat::Amr) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "amr decoding is not supported",
+        )),
+        None => Err(invalid("unknown voice format")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Voice {
+    pub md5: [u8; 16],
+    pub size: u32,
+    pub name: String,
+    pub format: VoiceFormat,
+    /// 时长，单位为秒
+    pub duration: u32,
+    /// 群语音的 file id
+    pub file_id: u64,
+    /// 群语音的 file key
+    pub file_key: String,
+    /// 好友语音的 uuid
+    pub file_uuid: Vec<u8>,
+    /// 收到的语音中携带的下载参数
+    pub url: String,
+}
+
+impl Voice {
+    pub fn new(md5: [u8; 16], size: u32, format: VoiceFormat, duration: u32) -> Self {
+        Self {
+            name: format!("{}.amr", hex::encode_upper(md5)),
+            md5,
+            size,
+            format,
+            duration,
+            file_id: 0,
+            file_key: String::new(),
+            file_uuid: Vec::new(),
+            url: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn silk(frames: usize) -> Vec<u8> {
+        let mut data = vec![0x02];
+        data.extend_from_slice(SILK_HEADER);
+        for _ in 0..frames {
+            data.extend_from_slice(&[3, 0, 1, 2, 3]);
+        }
+        data.extend_from_slice(&[0xff, 0xff]);
+        data
+    }
+
+    #[test]
+    fn test_detect_and_duration() {
+        let data = silk(150);
+        assert_eq!(VoiceFormat::detect(&data), Some(VoiceFormat::Silk));
+        assert_eq!(voice_duration(&data, VoiceFormat::Silk), 3);
+
+        let mut amr = AMR_HEADER.to_vec();
+        for _ in 0..100 {
+            amr.push(7 << 3);
+            amr.extend_from_slice(&[0u8; 31]);
+        }
+        assert_eq!(VoiceFormat::detect(&amr), Some(VoiceFormat::Amr));
+        assert_eq!(voice_duration(&amr, VoiceFormat::Amr), 2);
+        assert_eq!(VoiceFormat::detect(b"RIFF"), None);
+    }
+
+    #[test]
+    fn test_wav_to_pcm() {
+        let samples: [i16; 4] = [100, 300, -100, -300];
+        let mut wav = b"RIFF\x00\x00\x00\x00WAVEfmt ".to_vec();
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&24000u32.to_le_bytes());
+        wav.extend_from_slice(&(24000u32 * 4).to_le_bytes());
+        wav.extend_from_slice(&4u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&8u32.to_le_bytes());
+        samples
+            .iter()
+            .for_each(|s| wav.extend_from_slice(&s.to_le_bytes()));
+
+        let pcm = wav_to_pcm(&wav).unwrap();
+        assert_eq!(pcm.sample_rate, 24000);
+        assert_eq!(pcm.samples, vec![200, -200]);
+
+        // 长度超出文件时不按头部的长度分配内存
+        wav[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(wav_to_pcm(&wav).unwrap().samples, vec![200, -200]);
+        wav[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(wav_to_pcm(&wav).is_err());
+    }
+
+    struct FakeCodec;
+
+    impl SilkCodec for FakeCodec {
+        fn encode(&self, pcm: &[i16], _sample_rate: u32) -> io::Result<Vec<u8>> {
+            Ok(silk(pcm.len()))
+        }
+
+        fn decode(&self, _silk: &[u8], _sample_rate: u32) -> io::Result<Vec<i16>> {
+            Ok(vec![0; 480])
+        }
+    }
+
+    #[test]
+    fn test_encode_voice() {
+        let (data, format) = encode_voice(AudioInput::Encoded(silk(1)), None).unwrap();
+        assert_eq!((data, format), (silk(1), VoiceFormat::Silk));
+
+        let pcm = AudioInput::Pcm {
+            samples: vec![0; 60],
+            sample_rate: 24000,
+        };
+        assert!(encode_voice(pcm, None).is_err());
+
+        let pcm = AudioInput::Pcm {
+            samples: vec![0; 60],
+            sample_rate: 24000,
+        };
+        let (data, _) = encode_voice(pcm, Some(&FakeCodec)).unwrap();
+        assert_eq!(voice_duration(&data, VoiceFormat::Silk), 1);
+
+        let pcm = decode_voice(&data, &FakeCodec, 24000).unwrap();
+        assert_eq!(pcm.samples.len(), 480);
+    }
+}
@@ -0,0 +1,457 @@
+//! mirai-api-http 的命令与状态码，以及各命令到客户端接口的映射
+//! [参考](https://github.com/project-mirai/mirai-api-http/blob/master/docs/api/API.md)
+
+use std::{
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    adapter::{load_file, StoredReceipt},
+    events::Event,
+    message::{
+        rich::{LightApp, ServiceMessage},
+        MessageChain, MessageTarget,
+    },
+    network::{
+        group_admin::{get_group_member_list, mute_all, mute_member},
+        image::upload_image,
+        message::{recall_message, send_message},
+        multi_msg::pack_long_message,
+        profile::{get_group_info, MemberPermission},
+        NetworkError, SsoSender,
+    },
+};
+
+use super::{event::permission_str, MiraiApiHttp};
+
+/// xml 消息默认的 service id
+const DEFAULT_XML_SERVICE_ID: u32 = 60;
+
+#[derive(Debug)]
+pub enum ApiError {
+    WrongVerifyKey,
+    /// 指定的 bot 不是本适配器的 bot
+    BotNotExist,
+    InvalidSession,
+    /// session 未绑定 bot
+    NotVerified,
+    TargetNotExist(String),
+    /// 缺少参数、参数类型错误或不支持的命令
+    BadRequest(String),
+    Failed(String),
+}
+
+impl ApiError {
+    pub fn code(&self) -> i32 {
+        match self {
+            ApiError::WrongVerifyKey => 1,
+            ApiError::BotNotExist => 2,
+            ApiError::InvalidSession => 3,
+            ApiError::NotVerified => 4,
+            ApiError::TargetNotExist(_) => 5,
+            ApiError::BadRequest(_) => 400,
+            ApiError::Failed(_) => 500,
+        }
+    }
+
+    /// `{"code": .., "msg": ..}`
+    pub fn to_json(&self) -> Value {
+        json!({ "code": self.code(), "msg": self.to_string() })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::WrongVerifyKey => write!(f, "wrong verify key"),
+            ApiError::BotNotExist => write!(f, "bot not exist"),
+            ApiError::InvalidSession => write!(f, "invalid session key"),
+            ApiError::NotVerified => write!(f, "session not verified"),
+            ApiError::TargetNotExist(target) => write!(f, "target not exist: {}", target),
+            ApiError::BadRequest(msg) => write!(f, "bad request: {}", msg),
+            ApiError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<NetworkError> for ApiError {
+    fn from(err: NetworkError) -> Self {
+        ApiError::Failed(err.to_string())
+    }
+}
+
+type ApiResult = Result<Value, ApiError>;
+
+fn success() -> Value {
+    json!({ "code": 0, "msg": "success" })
+}
+
+/// 数字参数也可能以字符串形式传递，GET 请求的参数都是字符串
+pub(crate) fn u64_param(content: &Value, key: &str) -> Result<u64, ApiError> {
+    match content.get(key) {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| ApiError::BadRequest(key.to_string()))
+}
+
+pub(crate) fn str_param<'a>(content: &'a Value, key: &str) -> Result<&'a str, ApiError> {
+    content
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| ApiError::BadRequest(key.to_string()))
+}
+
+impl<S: SsoSender + Sync> MiraiApiHttp<S> {
+    /// 执行已通过 session 校验的命令，HTTP 接口的路径去掉 `/` 并以 `_` 连接即为命令名
+    pub async fn handle_command(&self, command: &str, content: &Value) -> Value {
+        match self.dispatch(command, content).await {
+            Ok(data) => data,
+            Err(err) => err.to_json(),
+        }
+    }
+
+    async fn dispatch(&self, command: &str, content: &Value) -> ApiResult {
+        match command {
+            "about" => Ok(json!({
+                "code": 0,
+                "msg": "",
+                "data": { "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "sendGroupMessage" => {
+                let target = MessageTarget::Group(self.target(content, "group")?);
+                self.send(target, content).await
+            }
+            "sendFriendMessage" => {
+                let target = MessageTarget::Friend(self.target(content, "qq")?);
+                self.send(target, content).await
+            }
+            "recall" => {
+                // 2.6 之前 target 即为 messageId
+                let id = match content.get("messageId") {
+                    Some(_) => u64_param(content, "messageId")?,
+                    None => u64_param(content, "target")?,
+                } as i32;
+                let receipt = match self.receipt(id) {
+                    Some(StoredReceipt::Ready(receipt)) => receipt,
+                    Some(StoredReceipt::Pending(_)) => {
+                        return Err(ApiError::Failed(format!(
+                            "message {} is not confirmed by the server yet",
+                            id
+                        )))
+                    }
+                    None => return Err(ApiError::TargetNotExist(format!("message {}", id))),
+                };
+                recall_message(&self.sender, self.uin, &receipt).await?;
+                Ok(success())
+            }
+            "memberList" => {
+                let code = u64_param(content, "target")?;
+                let info = get_group_info(&self.sender, self.app_id, code).await?;
+                let members =
+                    get_group_member_list(&self.sender, self.uin, code, info.owner_uin).await?;
+                let permission = members
+                    .iter()
+                    .find(|m| m.uin == self.uin)
+                    .map_or(MemberPermission::Member, |m| m.permission);
+                let group = json!({
+                    "id": code,
+                    "name": info.name,
+                    "permission": permission_str(permission),
+                });
+                let data = members
+                    .iter()
+                    .filter(|m| m.uin != self.uin)
+                    .map(|m| {
+                        let name = if m.card.is_empty() { &m.nick } else { &m.card };
+                        json!({
+                            "id": m.uin,
+                            "memberName": name,
+                            "specialTitle": m.special_title,
+                            "permission": permission_str(m.permission),
+                            "joinTimestamp": m.join_time,
+                            "lastSpeakTimestamp": m.last_speak_time,
+                            "muteTimeRemaining": 0,
+                            "group": group,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "code": 0, "msg": "", "data": data }))
+            }
+            "mute" => {
+                let code = u64_param(content, "target")?;
+                let uin = u64_param(content, "memberId")?;
+                mute_member(&self.sender, code, uin, u64_param(content, "time")? as u32).await?;
+                Ok(success())
+            }
+            "unmute" => {
+                let code = u64_param(content, "target")?;
+                mute_member(&self.sender, code, u64_param(content, "memberId")?, 0).await?;
+                Ok(success())
+            }
+            "muteAll" | "unmuteAll" => {
+                let code = u64_param(content, "target")?;
+                mute_all(&self.sender, code, command == "muteAll").await?;
+                Ok(success())
+            }
+            "resp_newFriendRequestEvent" => {
+                let (id, Event::NewFriendRequest(req)) = self.request(content)? else {
+                    return Err(ApiError::BadRequest("eventId".to_string()));
+                };
+                let message = content.get("message").and_then(Value::as_str);
+                match u64_param(content, "operate")? {
+                    0 => req.accept(&self.sender).await?,
+                    operate => {
+                        req.reject(&self.sender, operate == 2, message.unwrap_or_default())
+                            .await?
+                    }
+                }
+                self.remove_request(id);
+                Ok(success())
+            }
+            "resp_memberJoinRequestEvent" => {
+                let (id, Event::MemberJoinRequest(req)) = self.request(content)? else {
+                    return Err(ApiError::BadRequest("eventId".to_string()));
+                };
+                let message = content.get("message").and_then(Value::as_str);
+                match u64_param(content, "operate")? {
+                    0 => req.accept(&self.sender).await?,
+                    2 => req.ignore(&self.sender, false).await?,
+                    4 => req.ignore(&self.sender, true).await?,
+                    operate => {
+                        req.reject(&self.sender, operate == 3, message.unwrap_or_default())
+                            .await?
+                    }
+                }
+                self.remove_request(id);
+                Ok(success())
+            }
+            "resp_botInvitedJoinGroupRequestEvent" => {
+                let (id, Event::BotInvitedJoinGroupRequest(req)) = self.request(content)? else {
+                    return Err(ApiError::BadRequest("eventId".to_string()));
+                };
+                let message = content.get("message").and_then(Value::as_str);
+                match u64_param(content, "operate")? {
+                    0 => req.accept(&self.sender).await?,
+                    _ => {
+                        req.reject(&self.sender, false, message.unwrap_or_default())
+                            .await?
+                    }
+                }
+                self.remove_request(id);
+                Ok(success())
+            }
+            _ => Err(ApiError::BadRequest(format!(
+                "unsupported command: {}",
+                command
+            ))),
+        }
+    }
+
+    /// 目标一般为 `target`，旧版本中也可以是 `group` 或 `qq`
+    fn target(&self, content: &Value, alias: &str) -> Result<u64, ApiError> {
+        u64_param(content, "target").or_else(|_| u64_param(content, alias))
+    }
+
+    /// 查找 `eventId` 对应的申请，处理成功后才需要通过 `remove_request` 移除
+    fn request(&self, content: &Value) -> Result<(u64, Event), ApiError> {
+        let id = u64_param(content, "eventId")?;
+        let request = self
+            .find_request(id)
+            .ok_or_else(|| ApiError::TargetNotExist(format!("event {}", id)))?;
+        Ok((id, request))
+    }
+
+    async fn send(&self, target: MessageTarget, content: &Value) -> ApiResult {
+        let elements = content
+            .get("messageChain")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ApiError::BadRequest("messageChain".to_string()))?;
+        let mut chain = self.json_to_chain(target, elements).await?;
+        if chain.is_empty() {
+            return Err(ApiError::BadRequest("messageChain is empty".to_string()));
+        }
+        if let Some(highway) = &self.highway {
+            chain = pack_long_message(&self.sender, highway, target, chain).await?;
+        }
+        let receipt = send_message(&self.sender, target, self.next_seq(), &chain).await?;
+        Ok(json!({
+            "code": 0,
+            "msg": "success",
+            "messageId": self.store_sent(receipt),
+        }))
+    }
+
+    /// 暂不支持的消息类型将被忽略
+    async fn json_to_chain(
+        &self,
+        target: MessageTarget,
+        elements: &[Value],
+    ) -> Result<MessageChain, ApiError> {
+        let mut chain = MessageChain::new();
+        for element in elements {
+            match element.get("type").and_then(Value::as_str) {
+                Some("Plain") => chain.push(str_param(element, "text")?),
+                Some("Image") => {
+                    let highway = self.highway.as_ref().ok_or_else(|| {
+                        ApiError::Failed("image upload is not available".to_string())
+                    })?;
+                    let image = if let Ok(url) = str_param(element, "url") {
+                        if !url.starts_with("http://") && !url.starts_with("https://") {
+                            return Err(ApiError::BadRequest(format!("image url: {}", url)));
+                        }
+                        load_file(url).await?
+                    } else if let Ok(path) = str_param(element, "path") {
+                        let path = resolve_image_path(self.config.image_dir.as_deref(), path)?;
+                        tokio::fs::read(path).await.map_err(NetworkError::from)?
+                    } else {
+                        load_file(&format!("base64://{}", str_param(element, "base64")?)).await?
+                    };
+                    chain.push(upload_image(&self.sender, highway, target, image).await?);
+                }
+                Some("App") => chain.push(LightApp {
+                    content: str_param(element, "content")?.to_string(),
+                }),
+                Some("Xml") => chain.push(ServiceMessage {
+                    service_id: DEFAULT_XML_SERVICE_ID,
+                    content: str_param(element, "xml")?.to_string(),
+                }),
+                _ => {}
+            }
+        }
+        Ok(chain)
+    }
+}
+
+/// `Image.path` 只能是 `image_dir` 下的相对路径，不能包含 `..`
+fn resolve_image_path(image_dir: Option<&Path>, path: &str) -> Result<PathBuf, ApiError> {
+    let dir =
+        image_dir.ok_or_else(|| ApiError::BadRequest("image path is disabled".to_string()))?;
+    let relative = Path::new(path);
+    let confined = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !confined {
+        return Err(ApiError::BadRequest(format!("image path: {}", path)));
+    }
+    Ok(dir.join(relative))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::protobuf::{DynamicProtoMessage, ProtoReader},
+        events::message::SelfMessageEvent,
+        mirai_api_http::MiraiApiHttpConfig,
+        network::{
+            message::{CMD_MSG_WITHDRAW, CMD_SEND_MSG},
+            test::MockSender,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_send_and_recall() {
+        let send = DynamicProtoMessage::new().with(1, 0u32).encode().unwrap();
+        let withdraw = DynamicProtoMessage::new()
+            .with(2, vec![DynamicProtoMessage::new().with(1, 0u32)])
+            .encode()
+            .unwrap();
+        let bot = MiraiApiHttp::new(
+            MockSender::new(vec![send, withdraw]),
+            10086,
+            16,
+            MiraiApiHttpConfig::default(),
+        );
+        let content = json!({
+            "target": 10001,
+            "messageChain": [
+                { "type": "Plain", "text": "hi " },
+                { "type": "Xml", "xml": "<msg/>" },
+                { "type": "Face", "faceId": 1 },
+            ],
+        });
+        let rsp = bot.handle_command("sendGroupMessage", &content).await;
+        assert_eq!(rsp["code"], 0);
+
+        let (command, body) = bot.sender.sent_body(0);
+        assert_eq!(command, CMD_SEND_MSG);
+        let rich = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(3)
+            .unwrap()
+            .unwrap()
+            .get_message(1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            MessageChain::from_rich_text(&rich).unwrap().summary(),
+            "hi [卡片]"
+        );
+
+        // 服务器分配的 seq 推送回来之前无法撤回
+        let content = json!({ "target": 10001, "messageId": rsp["messageId"] });
+        let rsp = bot.handle_command("recall", &content).await;
+        assert_ne!(rsp["code"], 0);
+
+        let random = ProtoReader::decode(&body).unwrap().get_u64(5).unwrap() as u32;
+        bot.push_event(&Event::SelfMessage(SelfMessageEvent {
+            target: MessageTarget::Group(10001),
+            seq: 233,
+            random,
+            time: 0,
+            chain: MessageChain::default(),
+        }));
+        let rsp = bot.handle_command("recall", &content).await;
+        assert_eq!(rsp["code"], 0);
+        let (command, body) = bot.sender.sent_body(1);
+        assert_eq!(command, CMD_MSG_WITHDRAW);
+        let info = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(2)
+            .unwrap()
+            .unwrap()
+            .get_message(4)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.get_u64(1), Some(233));
+    }
+
+    #[tokio::test]
+    async fn test_command_errors() {
+        let bot = MiraiApiHttp::new(
+            MockSender::new(vec![]),
+            10086,
+            16,
+            MiraiApiHttpConfig::default(),
+        );
+        let rsp = bot
+            .handle_command("recall", &json!({ "target": 10001, "messageId": 1 }))
+            .await;
+        assert_eq!(rsp["code"], 5);
+        let rsp = bot.handle_command("sendFriendMessage", &json!({})).await;
+        assert_eq!(rsp["code"], 400);
+        let rsp = bot.handle_command("no_such_command", &json!({})).await;
+        assert_eq!(rsp["code"], 400);
+    }
+
+    #[test]
+    fn test_resolve_image_path() {
+        let dir = Path::new("/srv/images");
+        assert_eq!(
+            resolve_image_path(Some(dir), "a/b.png").unwrap(),
+            dir.join("a/b.png")
+        );
+        for path in ["../secret.png", "a/../../b.png", "/etc/passwd"] {
+            assert!(matches!(
+                resolve_image_path(Some(dir), path),
+                Err(ApiError::BadRequest(_))
+            ));
+        }
+        assert!(resolve_image_path(None, "a.png").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! 将 [`Event`] 与 [`MessageChain`] 转换为 mirai-api-http 的 JSON
+//! [参考](https://github.com/project-mirai/mirai-api-http/blob/master/docs/api/EventType.md)
+
+use serde_json::{json, Value};
+
+use crate::{
+    adapter::unix_now,
+    events::Event,
+    message::{MessageChain, MessageElement, MessageTarget},
+    network::{message::MessageReceipt, profile::MemberPermission},
+};
+
+pub fn permission_str(permission: MemberPermission) -> &'static str {
+    match permission {
+        MemberPermission::Owner => "OWNER",
+        MemberPermission::Administrator => "ADMINISTRATOR",
+        MemberPermission::Member => "MEMBER",
+    }
+}
+
+/// 事件中只有群号，群名称与 bot 的权限无法得知
+fn group_json(group_code: u64) -> Value {
+    json!({ "id": group_code, "name": "", "permission": "MEMBER" })
+}
+
+fn member_json(group_code: u64, uin: u64, name: &str) -> Value {
+    json!({
+        "id": uin,
+        "memberName": name,
+        "specialTitle": "",
+        "permission": "MEMBER",
+        "joinTimestamp": 0,
+        "lastSpeakTimestamp": 0,
+        "muteTimeRemaining": 0,
+        "group": group_json(group_code),
+    })
+}
+
+fn friend_json(uin: u64, nick: &str) -> Value {
+    json!({ "id": uin, "nickname": nick, "remark": "" })
+}
+
+/// 转换消息链，`source` 为 `(messageId, time)` 时在开头加上 `Source`
+///
+/// 无法表示的元素以 `Plain` 摘要代替
+pub fn chain_to_json(chain: &MessageChain, source: Option<(i32, u32)>) -> Vec<Value> {
+    let source = source.map(|(id, time)| json!({ "type": "Source", "id": id, "time": time }));
+    source
+        .into_iter()
+        .chain(chain.iter().map(|e| match e {
+            MessageElement::Text(text) => json!({ "type": "Plain", "text": text }),
+            MessageElement::Image(image) => json!({
+                "type": "Image",
+                "imageId": image.image_id,
+                "url": image.url,
+                "width": image.width,
+                "height": image.height,
+                "size": image.size,
+            }),
+            MessageElement::Voice(voice) => json!({
+                "type": "Voice",
+                "voiceId": voice.name,
+                "url": voice.url,
+                "length": voice.duration,
+            }),
+            MessageElement::ShortVideo(video) => json!({
+                "type": "ShortVideo",
+                "videoId": video.name,
+                "filename": video.name,
+                "fileSize": video.size,
+            }),
+            MessageElement::LightApp(app) => json!({ "type": "App", "content": app.content }),
+            MessageElement::Service(service) => json!({ "type": "Xml", "xml": service.content }),
+            MessageElement::LongMessage(long) => json!({ "type": "Plain", "text": long.brief }),
+            MessageElement::Forward(_) => json!({ "type": "Plain", "text": "[聊天记录]" }),
+        }))
+        .collect()
+}
+
+/// 可以撤回的消息，分配 `messageId` 用
+pub(crate) fn receipt_of(event: &Event) -> Option<MessageReceipt> {
+    match event {
+        Event::GroupMessage(e) => Some(MessageReceipt {
+            target: MessageTarget::Group(e.group_code),
+            seq: e.seq,
+            random: e.random,
+            time: e.time,
+        }),
+        _ => None,
+    }
+}
+
+/// 申请事件的 `eventId`，处理申请时用于找回原事件
+pub(crate) fn request_id(event: &Event) -> Option<u64> {
+    match event {
+        Event::NewFriendRequest(e) => Some(e.request_id),
+        Event::MemberJoinRequest(e) => Some(e.request_id),
+        Event::BotInvitedJoinGroupRequest(e) => Some(e.request_id),
+        _ => None,
+    }
+}
+
+/// 转换为 mirai-api-http 事件，没有对应事件类型时返回 `None`
+///
+/// 与 bot 自身有关的群事件转换为对应的 `Bot*` 事件
+pub fn to_json(self_uin: u64, event: &Event, message_id: Option<i32>) -> Option<Value> {
+    let json = match event {
+        Event::GroupMessage(e) => json!({
+            "type": "GroupMessage",
+            "messageChain": chain_to_json(&e.chain, message_id.map(|id| (id, e.time))),
+            "sender": member_json(e.group_code, e.sender_uin, &e.sender_name),
+        }),
+        Event::SelfMessage(e) => {
+            let chain = chain_to_json(&e.chain, None);
+            match e.target {
+                MessageTarget::Group(code) => json!({
+                    "type": "GroupSyncMessage",
+                    "messageChain": chain,
+                    "subject": group_json(code),
+                }),
+                MessageTarget::Friend(uin) => json!({
+                    "type": "FriendSyncMessage",
+                    "messageChain": chain,
+                    "subject": friend_json(uin, ""),
+                }),
+            }
+        }
+        Event::NewFriendRequest(e) => json!({
+            "type": "NewFriendRequestEvent",
+            "eventId": e.request_id,
+            "fromId": e.requester_uin,
+            "groupId": 0,
+            "nick": e.requester_nick,
+            "message": e.message,
+        }),
+        Event::MemberJoinRequest(e) => json!({
+            "type": "MemberJoinRequestEvent",
+            "eventId": e.request_id,
+            "fromId": e.requester_uin,
+            "groupId": e.group_code,
+            "groupName": e.group_name,
+            "nick": e.requester_nick,
+            "message": e.message,
+            "invitorId": e.invitor_uin,
+        }),
+        Event::BotInvitedJoinGroupRequest(e) => json!({
+            "type": "BotInvitedJoinGroupRequestEvent",
+            "eventId": e.request_id,
+            "fromId": e.invitor_uin,
+            "groupId": e.group_code,
+            "groupName": e.group_name,
+            "nick": e.invitor_nick,
+            "message": "",
+        }),
+        Event::MemberJoin(e) if e.member_uin == self_uin => json!({
+            "type": "BotJoinGroupEvent",
+            "group": group_json(e.group_code),
+            "invitor": null,
+        }),
+        Event::MemberJoin(e) => json!({
+            "type": "MemberJoinEvent",
+            "member": member_json(e.group_code, e.member_uin, &e.member_nick),
+            "invitor": null,
+        }),
+        Event::MemberLeave(e) => {
+            let group = group_json(e.group_code);
+            let member = member_json(e.group_code, e.member_uin, "");
+            let operator = e.operator_uin.map(|op| member_json(e.group_code, op, ""));
+            match (e.member_uin == self_uin, operator) {
+                (true, None) => json!({ "type": "BotLeaveEventActive", "group": group }),
+                (true, Some(operator)) => {
+                    json!({ "type": "BotLeaveEventKick", "group": group, "operator": operator })
+                }
+                (false, None) => json!({ "type": "MemberLeaveEventQuit", "member": member }),
+                (false, Some(operator)) => json!({
+                    "type": "MemberLeaveEventKick",
+                    "member": member,
+                    "operator": operator,
+                }),
+            }
+        }
+        Event::MemberPermissionChange(e) => {
+            let (origin, current) = if e.is_admin {
+                ("MEMBER", "ADMINISTRATOR")
+            } else {
+                ("ADMINISTRATOR", "MEMBER")
+            };
+            if e.member_uin == self_uin {
+                json!({
+                    "type": "BotGroupPermissionChangeEvent",
+                    "origin": origin,
+                    "current": current,
+                    "group": group_json(e.group_code),
+                })
+            } else {
+                json!({
+                    "type": "MemberPermissionChangeEvent",
+                    "origin": origin,
+                    "current": current,
+                    "member": member_json(e.group_code, e.member_uin, ""),
+                })
+            }
+        }
+        Event::MemberMute(e) => {
+            let operator = member_json(e.group_code, e.operator_uin, "");
+            match (e.member_uin == self_uin, e.duration > 0) {
+                (true, true) => json!({
+                    "type": "BotMuteEvent",
+                    "durationSeconds": e.duration,
+                    "operator": operator,
+                }),
+                (true, false) => json!({ "type": "BotUnmuteEvent", "operator": operator }),
+                (false, true) => json!({
+                    "type": "MemberMuteEvent",
+                    "durationSeconds": e.duration,
+                    "member": member_json(e.group_code, e.member_uin, ""),
+                    "operator": operator,
+                }),
+                (false, false) => json!({
+                    "type": "MemberUnmuteEvent",
+                    "member": member_json(e.group_code, e.member_uin, ""),
+                    "operator": operator,
+                }),
+            }
+        }
+        Event::GroupMuteAll(e) => json!({
+            "type": "GroupMuteAllEvent",
+            "origin": !e.enabled,
+            "current": e.enabled,
+            "group": group_json(e.group_code),
+            "operator": member_json(e.group_code, e.operator_uin, ""),
+        }),
+        Event::MemberCardChange(e) => json!({
+            "type": "MemberCardChangeEvent",
+            "origin": "",
+            "current": e.new_card,
+            "member": member_json(e.group_code, e.member_uin, &e.new_card),
+        }),
+        Event::MemberSpecialTitleChange(e) => json!({
+            "type": "MemberSpecialTitleChangeEvent",
+            "origin": "",
+            "current": e.new_title,
+            "member": member_json(e.group_code, e.member_uin, ""),
+        }),
+        Event::GroupNameChange(e) => json!({
+            "type": "GroupNameChangeEvent",
+            "origin": "",
+            "current": e.new_name,
+            "group": group_json(e.group_code),
+            "operator": member_json(e.group_code, e.operator_uin, ""),
+        }),
+        Event::Nudge(e) => {
+            let subject = match e.group_code {
+                Some(code) => json!({ "id": code, "kind": "Group" }),
+                None => json!({ "id": e.sender_uin, "kind": "Friend" }),
+            };
+            json!({
+                "type": "NudgeEvent",
+                "fromId": e.sender_uin,
+                "subject": subject,
+                "action": e.action,
+                "suffix": e.suffix,
+                "target": e.receiver_uin,
+            })
+        }
+        Event::FriendAdded(e) => json!({
+            "type": "FriendAddEvent",
+            "friend": friend_json(e.uin, &e.nick),
+            "stranger": false,
+        }),
+        Event::FriendNickChange(e) => json!({
+            "type": "FriendNickChangedEvent",
+            "friend": friend_json(e.uin, &e.new_nick),
+            "from": "",
+            "to": e.new_nick,
+        }),
+        Event::FriendInputStatus(e) => json!({
+            "type": "FriendInputStatusChangedEvent",
+            "friend": friend_json(e.uin, ""),
+            "inputting": e.typing,
+        }),
+        _ => return None,
+    };
+    Some(json)
+}
+
+/// WebSocket 推送的事件，`syncId` 固定为 `-1`
+pub fn ws_event(data: Value) -> Value {
+    json!({ "syncId": "-1", "data": data })
+}
+
+/// 连接建立时发送的消息，包含本连接的 session key
+pub fn ws_connected(session: &str) -> Value {
+    json!({ "syncId": "", "data": { "code": 0, "session": session, "time": unix_now() } })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        group::MemberMuteEvent, message::GroupMessageEvent, request::MemberJoinRequestEvent,
+    };
+
+    #[test]
+    fn test_group_message_to_json() {
+        let event = Event::GroupMessage(GroupMessageEvent {
+            group_code: 10001,
+            sender_uin: 10010,
+            sender_name: "card".to_string(),
+            seq: 1,
+            random: 2,
+            time: 1700000000,
+            chain: MessageChain::new().with("hi"),
+        });
+        let json = to_json(10086, &event, Some(3)).unwrap();
+        assert_eq!(json["type"], "GroupMessage");
+        assert_eq!(
+            json["messageChain"],
+            json!([
+                { "type": "Source", "id": 3, "time": 1700000000 },
+                { "type": "Plain", "text": "hi" },
+            ])
+        );
+        assert_eq!(json["sender"]["group"]["id"], 10001);
+        assert_eq!(json["sender"]["memberName"], "card");
+    }
+
+    #[test]
+    fn test_bot_events() {
+        let mute = |member_uin, duration| {
+            Event::MemberMute(MemberMuteEvent {
+                group_code: 10001,
+                operator_uin: 10000,
+                member_uin,
+                duration,
+            })
+        };
+        let json = to_json(10086, &mute(10086, 60), None).unwrap();
+        assert_eq!(json["type"], "BotMuteEvent");
+        assert_eq!(json["durationSeconds"], 60);
+        let json = to_json(10086, &mute(10010, 0), None).unwrap();
+        assert_eq!(json["type"], "MemberUnmuteEvent");
+
+        let request = Event::MemberJoinRequest(MemberJoinRequestEvent {
+            request_id: 7,
+            message: "hi".to_string(),
+            requester_uin: 10010,
+            requester_nick: String::new(),
+            group_code: 10001,
+            group_name: String::new(),
+            invitor_uin: None,
+            suspicious: false,
+        });
+        let json = to_json(10086, &request, None).unwrap();
+        assert_eq!(
+            (json["eventId"].clone(), json["invitorId"].clone()),
+            (json!(7), Value::Null)
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! mirai-api-http v2 兼容适配器，提供 HTTP 接口与 WebSocket 事件推送
+//! [参考](https://github.com/project-mirai/mirai-api-http/tree/master/docs)
+//!
+//! 与 OneBot 适配器相同，收到的 [`Event`] 需要通过 [`MiraiApiHttp::push_event`] 交给适配器
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::{
+    adapter::{MessageStore, StoredReceipt},
+    events::Event,
+    network::{highway::HighwaySession, message::MessageReceipt, SsoSender},
+};
+
+pub mod api;
+pub mod event;
+pub mod server;
+
+use api::ApiError;
+
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+/// 未绑定的 session 超过这个时间后失效
+const UNBOUND_SESSION_TTL: Duration = Duration::from_secs(5 * 60);
+/// 未绑定的 session 数量上限，超出时移除最早创建的
+const MAX_UNBOUND_SESSIONS: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+pub struct MiraiApiHttpConfig {
+    /// 对应 `verifyKey`，未设置时 `/verify` 接受任意密钥，[`server::serve`] 拒绝启动
+    pub verify_key: Option<String>,
+    /// `Image.path` 所在的目录，`path` 只能是其中的相对路径，未设置时不接受 `path`
+    pub image_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    bound: bool,
+    created: Instant,
+}
+
+pub struct MiraiApiHttp<S> {
+    sender: S,
+    uin: u64,
+    app_id: u32,
+    /// 未设置时无法发送图片
+    highway: Option<HighwaySession>,
+    config: MiraiApiHttpConfig,
+    seq: AtomicU32,
+    sessions: Mutex<HashMap<String, Session>>,
+    messages: Mutex<MessageStore>,
+    /// 尚未处理的申请，以 `eventId` 为键
+    requests: Mutex<HashMap<u64, Event>>,
+    events: broadcast::Sender<Value>,
+}
+
+impl<S: SsoSender> MiraiApiHttp<S> {
+    pub fn new(sender: S, uin: u64, app_id: u32, config: MiraiApiHttpConfig) -> Self {
+        Self {
+            sender,
+            uin,
+            app_id,
+            highway: None,
+            config,
+            seq: AtomicU32::new(rand::random::<u16>() as u32),
+            sessions: Mutex::new(HashMap::new()),
+            messages: Mutex::new(MessageStore::default()),
+            requests: Mutex::new(HashMap::new()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn with_highway(mut self, highway: HighwaySession) -> Self {
+        self.highway = Some(highway);
+        self
+    }
+
+    pub fn uin(&self) -> u64 {
+        self.uin
+    }
+
+    pub fn config(&self) -> &MiraiApiHttpConfig {
+        &self.config
+    }
+
+    /// 订阅转换后的事件，每个连接各自订阅
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    /// 转换并广播事件，无法用 mirai-api-http 表示的事件将被忽略
+    pub fn push_event(&self, event: &Event) {
+        let message_id = event::receipt_of(event).map(|r| self.store_receipt(r));
+        if let Event::SelfMessage(e) = event {
+            let seq = e.seq as u32;
+            self.messages
+                .lock()
+                .unwrap()
+                .confirm(e.target, e.random, seq);
+        }
+        if let Some(id) = event::request_id(event) {
+            self.requests.lock().unwrap().insert(id, event.clone());
+        }
+        if let Some(json) = event::to_json(self.uin, event, message_id) {
+            // 没有任何连接时发送会失败，直接丢弃即可
+            let _ = self.events.send(json);
+        }
+    }
+
+    /// 校验 `verifyKey` 并创建一个未绑定的 session
+    pub fn verify(&self, verify_key: &str) -> Result<String, ApiError> {
+        if let Some(key) = &self.config.verify_key {
+            if key != verify_key {
+                return Err(ApiError::WrongVerifyKey);
+            }
+        }
+        let session = hex::encode(rand::random::<[u8; 16]>());
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        evict_unbound(&mut sessions, now);
+        sessions.insert(
+            session.clone(),
+            Session {
+                bound: false,
+                created: now,
+            },
+        );
+        Ok(session)
+    }
+
+    /// 将 session 绑定到 bot，`qq` 必须是本 bot 的 uin
+    pub fn bind(&self, session: &str, qq: u64) -> Result<(), ApiError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let entry = sessions.get_mut(session).ok_or(ApiError::InvalidSession)?;
+        if !entry.bound && entry.created.elapsed() >= UNBOUND_SESSION_TTL {
+            sessions.remove(session);
+            return Err(ApiError::InvalidSession);
+        }
+        if qq != self.uin {
+            return Err(ApiError::BotNotExist);
+        }
+        entry.bound = true;
+        Ok(())
+    }
+
+    pub fn release(&self, session: &str, qq: u64) -> Result<(), ApiError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if !sessions.contains_key(session) {
+            return Err(ApiError::InvalidSession);
+        }
+        if qq != self.uin {
+            return Err(ApiError::BotNotExist);
+        }
+        sessions.remove(session);
+        Ok(())
+    }
+
+    /// 检查 session 是否存在且已绑定
+    pub fn check_session(&self, session: &str) -> Result<(), ApiError> {
+        match self.sessions.lock().unwrap().get(session) {
+            None => Err(ApiError::InvalidSession),
+            Some(s) if s.bound => Ok(()),
+            Some(_) => Err(ApiError::NotVerified),
+        }
+    }
+
+    fn next_seq(&self) -> u32 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn store_receipt(&self, receipt: MessageReceipt) -> i32 {
+        self.messages.lock().unwrap().insert(receipt)
+    }
+
+    fn store_sent(&self, receipt: MessageReceipt) -> i32 {
+        self.messages.lock().unwrap().insert_sent(receipt)
+    }
+
+    fn receipt(&self, message_id: i32) -> Option<StoredReceipt> {
+        self.messages.lock().unwrap().get(message_id)
+    }
+
+    fn find_request(&self, event_id: u64) -> Option<Event> {
+        self.requests.lock().unwrap().get(&event_id).cloned()
+    }
+
+    fn remove_request(&self, event_id: u64) {
+        self.requests.lock().unwrap().remove(&event_id);
+    }
+}
+
+/// 移除过期的未绑定 session，并为新的 session 留出位置，已绑定的 session 只能通过 `release` 释放
+fn evict_unbound(sessions: &mut HashMap<String, Session>, now: Instant) {
+    sessions.retain(|_, s| s.bound || now.duration_since(s.created) < UNBOUND_SESSION_TTL);
+    let mut unbound = sessions
+        .iter()
+        .filter(|(_, s)| !s.bound)
+        .map(|(k, s)| (s.created, k.clone()))
+        .collect::<Vec<_>>();
+    if unbound.len() < MAX_UNBOUND_SESSIONS {
+        return;
+    }
+    unbound.sort_unstable();
+    for (_, key) in &unbound[..=unbound.len() - MAX_UNBOUND_SESSIONS] {
+        sessions.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    #[test]
+    fn test_session_flow() {
+        let config = MiraiApiHttpConfig {
+            verify_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        let bot = MiraiApiHttp::new(MockSender::new(vec![]), 10086, 16, config);
+        assert!(matches!(bot.verify("bad"), Err(ApiError::WrongVerifyKey)));
+        let session = bot.verify("key").unwrap();
+        assert!(matches!(
+            bot.check_session(&session),
+            Err(ApiError::NotVerified)
+        ));
+        assert!(matches!(
+            bot.bind(&session, 10010),
+            Err(ApiError::BotNotExist)
+        ));
+        bot.bind(&session, 10086).unwrap();
+        bot.check_session(&session).unwrap();
+        bot.release(&session, 10086).unwrap();
+        assert!(matches!(
+            bot.check_session(&session),
+            Err(ApiError::InvalidSession)
+        ));
+    }
+    #[test]
+    fn test_evict_unbound_sessions() {
+        let bot = MiraiApiHttp::new(
+            MockSender::new(vec![]),
+            10086,
+            16,
+            MiraiApiHttpConfig::default(),
+        );
+        let bound = bot.verify("").unwrap();
+        bot.bind(&bound, 10086).unwrap();
+        for _ in 0..MAX_UNBOUND_SESSIONS + 10 {
+            bot.verify("").unwrap();
+        }
+        let mut sessions = bot.sessions.lock().unwrap();
+        assert_eq!(sessions.len(), MAX_UNBOUND_SESSIONS + 1);
+
+        evict_unbound(&mut sessions, Instant::now() + UNBOUND_SESSION_TTL);
+        assert_eq!(sessions.keys().collect::<Vec<_>>(), [&bound]);
+    }
+}
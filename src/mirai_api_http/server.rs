@@ -0,0 +1,431 @@
+//! mirai-api-http 的 HTTP 与 WebSocket adapter
+//! [参考](https://github.com/project-mirai/mirai-api-http/tree/master/docs/adapter)
+
+use std::{collections::HashMap, io, net::TcpListener, sync::Arc};
+
+use axum::{
+    body::Bytes,
+    extract::{ws, Path, Query, State, WebSocketUpgrade},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::network::SsoSender;
+
+use super::{
+    api::{str_param, u64_param, ApiError},
+    event::{ws_connected, ws_event},
+    MiraiApiHttp,
+};
+
+/// WebSocket 连接接收的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    All,
+    Message,
+    Event,
+}
+
+impl Channel {
+    /// 消息类事件的类型都以 `Message` 结尾
+    fn accepts(self, event: &Value) -> bool {
+        let is_message = event["type"]
+            .as_str()
+            .is_some_and(|t| t.ends_with("Message"));
+        match self {
+            Channel::All => true,
+            Channel::Message => is_message,
+            Channel::Event => !is_message,
+        }
+    }
+}
+
+fn parse_body(body: &Bytes) -> Result<Value, ApiError> {
+    serde_json::from_slice::<Value>(body).map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+fn respond(result: Result<Value, ApiError>) -> Json<Value> {
+    Json(result.unwrap_or_else(|err| err.to_json()))
+}
+
+async fn verify<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    body: Bytes,
+) -> Json<Value> {
+    respond(parse_body(&body).and_then(|content| {
+        let session = bot.verify(str_param(&content, "verifyKey")?)?;
+        Ok(json!({ "code": 0, "session": session }))
+    }))
+}
+
+async fn bind<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    body: Bytes,
+) -> Json<Value> {
+    respond(parse_body(&body).and_then(|content| {
+        bot.bind(
+            str_param(&content, "sessionKey")?,
+            u64_param(&content, "qq")?,
+        )?;
+        Ok(json!({ "code": 0, "msg": "success" }))
+    }))
+}
+
+async fn release<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    body: Bytes,
+) -> Json<Value> {
+    respond(parse_body(&body).and_then(|content| {
+        bot.release(
+            str_param(&content, "sessionKey")?,
+            u64_param(&content, "qq")?,
+        )?;
+        Ok(json!({ "code": 0, "msg": "success" }))
+    }))
+}
+
+/// 其余接口，参数来自 query string 与 JSON body，`sessionKey` 也可以放在请求头中
+async fn http_command<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    Path(path): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut content = query
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect::<serde_json::Map<_, _>>();
+    if !body.is_empty() {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Object(body)) => content.extend(body),
+            _ => return StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+    let content = Value::Object(content);
+    // `resp/newFriendRequestEvent` 对应的命令为 `resp_newFriendRequestEvent`
+    let command = path.trim_matches('/').replace('/', "_");
+    if command != "about" {
+        let session = str_param(&content, "sessionKey")
+            .ok()
+            .or_else(|| headers.get("sessionKey").and_then(|v| v.to_str().ok()))
+            .unwrap_or_default();
+        if let Err(err) = bot.check_session(session) {
+            return Json(err.to_json()).into_response();
+        }
+    }
+    Json(bot.handle_command(&command, &content).await).into_response()
+}
+
+/// 连接参数可以在 query string 或请求头中，提供 `sessionKey` 时复用已绑定的 session
+///
+/// 返回的 `bool` 表示 session 是否由本次握手创建，只有这样的 session 才在连接断开后释放
+fn authenticate<S: SsoSender>(
+    bot: &MiraiApiHttp<S>,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<(String, bool), ApiError> {
+    let param = |key: &str| {
+        query
+            .get(key)
+            .map(String::as_str)
+            .or_else(|| headers.get(key).and_then(|v| v.to_str().ok()))
+    };
+    if let Some(session) = param("sessionKey") {
+        bot.check_session(session)?;
+        return Ok((session.to_string(), false));
+    }
+    let session = bot.verify(param("verifyKey").unwrap_or_default())?;
+    let bound = param("qq")
+        .and_then(|qq| qq.parse().ok())
+        .ok_or(ApiError::BotNotExist)
+        .and_then(|qq| bot.bind(&session, qq));
+    if let Err(err) = bound {
+        let _ = bot.release(&session, bot.uin());
+        return Err(err);
+    }
+    Ok((session, true))
+}
+
+async fn handle_text<S: SsoSender + Sync>(bot: &MiraiApiHttp<S>, text: &str) -> Value {
+    let Ok(req) = serde_json::from_str::<Value>(text) else {
+        return json!({ "syncId": "", "data": ApiError::BadRequest("invalid json".to_string()).to_json() });
+    };
+    let data = match req.get("command").and_then(Value::as_str) {
+        Some(command) => {
+            let content = req.get("content").cloned().unwrap_or_default();
+            bot.handle_command(command, &content).await
+        }
+        None => ApiError::BadRequest("command".to_string()).to_json(),
+    };
+    json!({ "syncId": req.get("syncId").cloned().unwrap_or_default(), "data": data })
+}
+
+/// 认证失败时发送错误后关闭连接，连接断开后释放握手时创建的 session
+async fn run_connection<S: SsoSender + Sync>(
+    bot: Arc<MiraiApiHttp<S>>,
+    channel: Channel,
+    session: Result<(String, bool), ApiError>,
+    socket: ws::WebSocket,
+) {
+    let (mut tx, mut rx) = socket.split();
+    let (session, created) = match session {
+        Ok(session) => session,
+        Err(err) => {
+            let msg = json!({ "syncId": "", "data": err.to_json() }).to_string();
+            let _ = tx.send(ws::Message::Text(msg)).await;
+            let _ = tx.close().await;
+            return;
+        }
+    };
+    let mut events = bot.subscribe();
+    let connected = ws_connected(&session).to_string();
+    if tx.send(ws::Message::Text(connected)).await.is_ok() {
+        loop {
+            let out = tokio::select! {
+                msg = rx.next() => match msg {
+                    Some(Ok(ws::Message::Text(text))) => handle_text(&bot, &text).await,
+                    Some(Ok(ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+                event = events.recv() => match event {
+                    Ok(event) if channel.accepts(&event) => ws_event(event),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if tx.send(ws::Message::Text(out.to_string())).await.is_err() {
+                break;
+            }
+        }
+    }
+    if created {
+        let _ = bot.release(&session, bot.uin());
+    }
+}
+
+async fn ws_handler<S: SsoSender + Send + Sync + 'static>(
+    bot: Arc<MiraiApiHttp<S>>,
+    channel: Channel,
+    headers: HeaderMap,
+    query: HashMap<String, String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let session = authenticate(&bot, &headers, &query);
+    upgrade.on_upgrade(move |socket| run_connection(bot, channel, session, socket))
+}
+
+async fn ws_all<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Channel::All, headers, query, upgrade).await
+}
+
+async fn ws_message<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Channel::Message, headers, query, upgrade).await
+}
+
+async fn ws_event_channel<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<MiraiApiHttp<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Channel::Event, headers, query, upgrade).await
+}
+
+/// 在同一端口上提供 HTTP adapter 与 WebSocket adapter
+///
+/// WebSocket 路径为 `/all`、`/message` 与 `/event`，其余路径作为 HTTP 接口。
+/// 未设置 `verify_key` 时任何人都能控制 bot，因此拒绝启动
+pub async fn serve<S: SsoSender + Send + Sync + 'static>(
+    bot: Arc<MiraiApiHttp<S>>,
+    listener: TcpListener,
+) -> io::Result<()> {
+    if bot.config().verify_key.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mirai-api-http requires a verify key",
+        ));
+    }
+    let app = Router::new()
+        .route("/verify", post(verify::<S>))
+        .route("/bind", post(bind::<S>))
+        .route("/release", post(release::<S>))
+        .route("/all", get(ws_all::<S>))
+        .route("/message", get(ws_message::<S>))
+        .route("/event", get(ws_event_channel::<S>))
+        .route("/*command", get(http_command::<S>).post(http_command::<S>))
+        .with_state(bot);
+    listener.set_nonblocking(true)?;
+    axum::Server::from_tcp(listener)
+        .map_err(io::Error::other)?
+        .serve(app.into_make_service())
+        .await
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::protobuf::DynamicProtoMessage,
+        events::{request::NewFriendRequestEvent, Event},
+        mirai_api_http::MiraiApiHttpConfig,
+        network::test::MockSender,
+    };
+    use tokio_tungstenite::tungstenite;
+
+    fn bot(responses: Vec<Vec<u8>>) -> Arc<MiraiApiHttp<MockSender>> {
+        let config = MiraiApiHttpConfig {
+            verify_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        Arc::new(MiraiApiHttp::new(
+            MockSender::new(responses),
+            10086,
+            16,
+            config,
+        ))
+    }
+
+    async fn start(bot: Arc<MiraiApiHttp<MockSender>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(bot, listener));
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_http_adapter() {
+        let send = DynamicProtoMessage::new().with(1, 0u32).encode().unwrap();
+        let addr = start(bot(vec![send])).await;
+        let client = reqwest::Client::new();
+        let call = |path: &str, body: Value| {
+            let req = client.post(format!("http://{}/{}", addr, path)).json(&body);
+            async move { req.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+
+        let rsp = call("verify", json!({ "verifyKey": "bad" })).await;
+        assert_eq!(rsp["code"], 1);
+        let rsp = call("verify", json!({ "verifyKey": "key" })).await;
+        let session = rsp["session"].as_str().unwrap().to_string();
+
+        let message = json!({
+            "sessionKey": session,
+            "target": 10001,
+            "messageChain": [{ "type": "Plain", "text": "hi" }],
+        });
+        let rsp = call("sendGroupMessage", message.clone()).await;
+        assert_eq!(rsp["code"], 4);
+        let rsp = call("bind", json!({ "sessionKey": session, "qq": 10086 })).await;
+        assert_eq!(rsp["code"], 0);
+        let rsp = call("sendGroupMessage", message).await;
+        assert_eq!(
+            (rsp["code"].clone(), rsp["messageId"].clone()),
+            (json!(0), json!(1))
+        );
+
+        let rsp: Value = client
+            .get(format!("http://{}/about", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(rsp["data"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let bot = bot(Vec::new());
+        let query = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        let headers = HeaderMap::new();
+        let (session, created) = authenticate(
+            &bot,
+            &headers,
+            &query(&[("verifyKey", "key"), ("qq", "10086")]),
+        )
+        .unwrap();
+        assert!(created);
+        let reused = authenticate(&bot, &headers, &query(&[("sessionKey", &session)]));
+        assert_eq!(reused.unwrap(), (session, false));
+
+        // 绑定失败时不留下未绑定的 session
+        let failed = authenticate(
+            &bot,
+            &headers,
+            &query(&[("verifyKey", "key"), ("qq", "10010")]),
+        );
+        assert!(matches!(failed, Err(ApiError::BotNotExist)));
+        assert_eq!(bot.sessions.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ws_all() {
+        let bot = bot(Vec::new());
+        let addr = start(bot.clone()).await;
+        let recv = |msg: Option<Result<tungstenite::Message, _>>| {
+            serde_json::from_str::<Value>(&msg.unwrap().unwrap().into_text().unwrap()).unwrap()
+        };
+
+        let url = format!("ws://{}/all?verifyKey=bad&qq=10086", addr);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(recv(socket.next().await)["data"]["code"], 1);
+
+        let url = format!("ws://{}/all?verifyKey=key&qq=10086", addr);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let connected = recv(socket.next().await);
+        assert_eq!(connected["data"]["code"], 0);
+        let session = connected["data"]["session"].as_str().unwrap().to_string();
+        bot.check_session(&session).unwrap();
+
+        bot.push_event(&Event::NewFriendRequest(NewFriendRequestEvent {
+            request_id: 1,
+            message: "hi".to_string(),
+            requester_uin: 10010,
+            requester_nick: String::new(),
+        }));
+        let event = recv(socket.next().await);
+        assert_eq!(event["syncId"], "-1");
+        assert_eq!(event["data"]["type"], "NewFriendRequestEvent");
+
+        let req = json!({ "syncId": "5", "command": "about", "content": {} }).to_string();
+        socket.send(tungstenite::Message::Text(req)).await.unwrap();
+        let rsp = recv(socket.next().await);
+        assert_eq!(rsp["syncId"], "5");
+        assert_eq!(rsp["data"]["code"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_serve_requires_verify_key() {
+        let bot = Arc::new(MiraiApiHttp::new(
+            MockSender::new(vec![]),
+            10086,
+            16,
+            MiraiApiHttpConfig::default(),
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let err = serve(bot, listener).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,200 @@
+//! bot 自身的资料与在线状态
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/client.go)
+
+use std::io;
+
+use crate::binary::{
+    data_writer::DataWriter,
+    jce::{JceStruct, RequestPacket},
+    protobuf::DynamicProtoMessage,
+};
+
+use super::{
+    highway::{HighwaySession, Transaction},
+    oidb::oidb_request_with_name,
+    profile::{get_user_profile, UserProfile},
+    send_uni_request, NetworkResult, SsoSender,
+};
+
+pub const CMD_UPDATE_PROFILE: &str = "OidbSvc.0x4ff_9_IMCore";
+pub const CMD_SET_STATUS: &str = "StatSvc.SetStatusFromClient";
+
+const OIDB_UPDATE_PROFILE: u32 = 0x4ff;
+const SERVICE_UPDATE_PROFILE: u32 = 9;
+const HIGHWAY_AVATAR: u32 = 5;
+
+/// 与 `OidbSvc.0x5eb_22` 的字段编号相同
+const PROFILE_SIGN: u16 = 102;
+const PROFILE_NICK: u16 = 20002;
+
+/// 自定义状态在 `extOnlineStatus` 中的值
+const EXT_STATUS_CUSTOM: i64 = 2000;
+
+/// `SvcReqRegister.iStatus`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnlineStatus {
+    Online,
+    Away,
+    Busy,
+    Invisible,
+    /// 自定义状态，显示为表情加文字
+    Custom {
+        face_id: u32,
+        wording: String,
+    },
+}
+
+impl OnlineStatus {
+    fn status(&self) -> i64 {
+        match self {
+            OnlineStatus::Online | OnlineStatus::Custom { .. } => 11,
+            OnlineStatus::Away => 31,
+            OnlineStatus::Invisible => 41,
+            OnlineStatus::Busy => 50,
+        }
+    }
+}
+
+/// `0x4ff_9` 的 body：uin、资料项数量以及 tag-length-value 形式的资料项
+pub fn build_update_profile(uin: u64, records: &[(u16, &[u8])]) -> io::Result<Vec<u8>> {
+    let mut w = DataWriter::new();
+    w.write_data(&(uin as u32))?;
+    w.write_data(&0u8)?;
+    w.write_data(&(records.len() as u16))?;
+    for (tag, value) in records {
+        w.write_data(tag)?;
+        w.write_short_data(*value)?;
+    }
+    Ok(w.into_inner())
+}
+
+async fn update_profile<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    records: &[(u16, &[u8])],
+) -> NetworkResult<()> {
+    let body = build_update_profile(uin, records)?;
+    oidb_request_with_name(
+        sender,
+        CMD_UPDATE_PROFILE,
+        OIDB_UPDATE_PROFILE,
+        SERVICE_UPDATE_PROFILE,
+        body,
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn set_nickname<S: SsoSender>(sender: &S, uin: u64, nick: &str) -> NetworkResult<()> {
+    update_profile(sender, uin, &[(PROFILE_NICK, nick.as_bytes())]).await
+}
+
+/// 设置个性签名
+pub async fn set_signature<S: SsoSender>(sender: &S, uin: u64, sign: &str) -> NetworkResult<()> {
+    update_profile(sender, uin, &[(PROFILE_SIGN, sign.as_bytes())]).await
+}
+
+/// 读取 bot 当前的昵称、签名等资料
+pub async fn get_self_profile<S: SsoSender>(sender: &S, uin: u64) -> NetworkResult<UserProfile> {
+    get_user_profile(sender, uin).await
+}
+
+/// 通过 highway 上传头像，服务器处理后可以通过
+/// [`user_avatar_url`](super::profile::user_avatar_url) 获取
+pub async fn set_avatar(highway: &HighwaySession, image: Vec<u8>) -> NetworkResult<()> {
+    let ext = DynamicProtoMessage::new()
+        .with(1, 281u32)
+        .with(2, highway.uin)
+        .encode()?;
+    let trans = Transaction::new(HIGHWAY_AVATAR, highway.sig_session.clone(), image).with_ext(ext);
+    highway.upload(&highway.servers, trans, 0).await?;
+    Ok(())
+}
+
+/// `PushService.SvcReqRegister`，`IsSetStatus` 为 1 时只修改在线状态
+pub fn build_set_status(uin: u64, status: &OnlineStatus) -> io::Result<Vec<u8>> {
+    let mut req = JceStruct::new()
+        .with(0, uin)
+        .with(1, 7u8)
+        .with(4, status.status())
+        .with(12, 1u8)
+        .with(17, 2052i32)
+        .with(22, 1u8)
+        .with(23, 1551i32)
+        .with(34, 1u8);
+    if let OnlineStatus::Custom { face_id, wording } = status {
+        req.set(38, EXT_STATUS_CUSTOM);
+        req.set(
+            42,
+            DynamicProtoMessage::new()
+                .with(1, *face_id)
+                .with(2, wording.clone())
+                .encode()?,
+        );
+    }
+    Ok(RequestPacket::new("PushService", "SvcReqRegister")
+        .with_data("SvcReqRegister", req)
+        .encode())
+}
+
+pub async fn set_online_status<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    status: &OnlineStatus,
+) -> NetworkResult<()> {
+    let body = build_set_status(uin, status)?;
+    send_uni_request(sender, CMD_SET_STATUS, &body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{binary::data_reader::DataReader, network::test::MockSender};
+
+    #[tokio::test]
+    async fn test_set_nickname() {
+        let rsp = DynamicProtoMessage::new().with(3, 0u32).encode().unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        set_nickname(&sender, 10086, "bot").await.unwrap();
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_UPDATE_PROFILE);
+        let pkg = crate::binary::protobuf::ProtoReader::decode(&body).unwrap();
+        let mut reader = DataReader::new(pkg.get_bytes(4).unwrap().to_vec());
+        assert_eq!(reader.read_data::<u32>().unwrap(), 10086);
+        assert_eq!(reader.read_data::<u8>().unwrap(), 0);
+        assert_eq!(reader.read_data::<u16>().unwrap(), 1);
+        assert_eq!(reader.read_data::<u16>().unwrap(), PROFILE_NICK);
+        assert_eq!(reader.read_data_short::<String>().unwrap(), "bot");
+    }
+
+    #[tokio::test]
+    async fn test_set_online_status() {
+        let sender = MockSender::new(Vec::new());
+        let status = OnlineStatus::Custom {
+            face_id: 13,
+            wording: "coding".to_string(),
+        };
+        set_online_status(&sender, 10086, &status).await.unwrap();
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SET_STATUS);
+        let pkt = RequestPacket::decode(&body).unwrap();
+        assert_eq!(pkt.func_name, "SvcReqRegister");
+        let data = pkt.data().unwrap();
+        let req = &data["SvcReqRegister"];
+        assert_eq!(req.get_i64(0), Some(10086));
+        assert_eq!(req.get_i64(4), Some(11));
+        assert_eq!(req.get_i64(38), Some(EXT_STATUS_CUSTOM));
+    }
+
+    #[tokio::test]
+    async fn test_set_avatar() {
+        let (addr, received) = crate::network::highway::test::stand_in(None).await;
+        let mut highway = HighwaySession::new(10086, 16, vec![1, 2, 3]);
+        highway.servers = vec![addr];
+        set_avatar(&highway, vec![9u8; 100]).await.unwrap();
+        assert_eq!(*received.lock().unwrap(), vec![9u8; 100]);
+    }
+}
@@ -0,0 +1,210 @@
+//! `cmd0x346`，好友语音与离线文件共用的请求体
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/pb/cmd0x346/cmd0x346.proto)
+
+use std::io;
+
+use crate::binary::protobuf::{DynamicProtoMessage, ProtoReader};
+
+use super::{NetworkError, NetworkResult};
+
+pub const CMD_APPLY_UPLOAD: u32 = 500;
+pub const CMD_APPLY_DOWNLOAD: u32 = 1200;
+pub const CMD_APPLY_UPLOAD_V3: u32 = 1700;
+
+/// 好友语音使用的业务编号
+const PTT_BUSINESS_ID: u32 = 17;
+/// 离线文件使用的业务编号
+const FILE_BUSINESS_ID: u32 = 3;
+const CLIENT_TYPE: u32 = 104;
+
+/// `ApplyUploadReq`
+pub struct ApplyUploadReq<'a> {
+    pub sender_uin: u64,
+    pub recver_uin: u64,
+    pub file_type: u32,
+    pub file_size: u64,
+    pub file_name: &'a str,
+    pub md5: &'a [u8; 16],
+}
+
+/// `ApplyUploadRsp` 中需要的部分
+#[derive(Debug, Clone, Default)]
+pub struct ApplyUploadRsp {
+    pub uuid: Vec<u8>,
+    pub upload_key: Vec<u8>,
+    pub exists: bool,
+    pub upload_ip: String,
+    pub upload_domain: String,
+    pub upload_port: u16,
+    /// 服务器已收到的长度，用于续传
+    pub uploaded_size: u64,
+}
+
+fn ptt_extension(duration: u32) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(1, 3u32)
+        .with(90300, 1u32)
+        .with(90500, 3u32)
+        .with(90600, 2u32)
+        .with(90800, duration)
+}
+
+fn apply_upload(req: &ApplyUploadReq) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(10, req.sender_uin)
+        .with(20, req.recver_uin)
+        .with(30, req.file_type)
+        .with(40, req.file_size)
+        .with(50, req.file_name.to_string())
+        .with(60, req.md5.to_vec())
+}
+
+/// 好友语音上传时作为 highway ext 的 `C346ReqBody`
+pub fn build_ptt_apply_upload(
+    seq: u32,
+    req: &ApplyUploadReq,
+    duration: u32,
+) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, CMD_APPLY_UPLOAD)
+        .with(2, seq)
+        .with(7, apply_upload(req))
+        .with(101, PTT_BUSINESS_ID)
+        .with(102, CLIENT_TYPE)
+        .with(99999, ptt_extension(duration))
+        .encode()
+}
+
+pub fn build_ptt_apply_download(seq: u32, uin: u64, uuid: &[u8]) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, CMD_APPLY_DOWNLOAD)
+        .with(2, seq)
+        .with(
+            14,
+            DynamicProtoMessage::new()
+                .with(10, uin)
+                .with(20, uuid.to_vec())
+                .with(30, 2u32),
+        )
+        .with(101, PTT_BUSINESS_ID)
+        .with(102, CLIENT_TYPE)
+        .with(99999, ptt_extension(0).with(90200, 2u32))
+        .encode()
+}
+
+/// 离线文件上传申请，使用 `ApplyUploadReqV3`
+pub fn build_file_apply_upload_v3(
+    seq: u32,
+    req: &ApplyUploadReq,
+    sha1: &[u8; 20],
+) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, CMD_APPLY_UPLOAD_V3)
+        .with(2, seq)
+        .with(
+            19,
+            DynamicProtoMessage::new()
+                .with(10, req.sender_uin)
+                .with(20, req.recver_uin)
+                .with(30, req.file_size)
+                .with(40, req.file_name.to_string())
+                .with(50, req.md5.to_vec())
+                .with(60, sha1.to_vec())
+                .with(
+                    70,
+                    format!("/storage/emulated/0/Download/{}", req.file_name),
+                )
+                .with(80, 0u32)
+                .with(90, 0u64),
+        )
+        .with(101, FILE_BUSINESS_ID)
+        .with(102, CLIENT_TYPE)
+        .with(200, 1u32)
+        .encode()
+}
+
+pub fn build_file_apply_download(seq: u32, uin: u64, uuid: &[u8]) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, CMD_APPLY_DOWNLOAD)
+        .with(2, seq)
+        .with(
+            14,
+            DynamicProtoMessage::new()
+                .with(10, uin)
+                .with(20, uuid.to_vec())
+                .with(30, 2u32),
+        )
+        .with(101, FILE_BUSINESS_ID)
+        .with(102, CLIENT_TYPE)
+        .with(99999, DynamicProtoMessage::new().with(90200, 1u32))
+        .encode()
+}
+
+fn check_ret(command: &str, rsp: &ProtoReader) -> NetworkResult<()> {
+    match rsp.get_i64(10).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: rsp.get_string(20).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 解析 `C346RspBody.apply_upload_rsp`
+pub fn decode_apply_upload_rsp(command: &str, payload: &[u8]) -> NetworkResult<ApplyUploadRsp> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(7)?
+        .unwrap_or_default();
+    check_ret(command, &rsp)?;
+    Ok(ApplyUploadRsp {
+        uuid: rsp.get_bytes(90).unwrap_or_default().to_vec(),
+        upload_key: rsp.get_bytes(100).unwrap_or_default().to_vec(),
+        exists: rsp.get_u64(110).unwrap_or_default() != 0,
+        upload_ip: rsp.get_string(60).unwrap_or_default(),
+        upload_domain: rsp.get_string(70).unwrap_or_default(),
+        upload_port: rsp.get_u64(80).unwrap_or_default() as u16,
+        uploaded_size: 0,
+    })
+}
+
+/// 解析 `C346RspBody.apply_upload_rsp_v3`
+pub fn decode_apply_upload_v3_rsp(command: &str, payload: &[u8]) -> NetworkResult<ApplyUploadRsp> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(19)?
+        .unwrap_or_default();
+    check_ret(command, &rsp)?;
+    Ok(ApplyUploadRsp {
+        uuid: rsp.get_bytes(90).unwrap_or_default().to_vec(),
+        upload_key: rsp.get_bytes(100).unwrap_or_default().to_vec(),
+        exists: rsp.get_u64(110).unwrap_or_default() != 0,
+        upload_ip: rsp.get_string(60).unwrap_or_default(),
+        upload_domain: rsp.get_string(70).unwrap_or_default(),
+        upload_port: rsp.get_u64(80).unwrap_or_default() as u16,
+        uploaded_size: rsp.get_u64(50).unwrap_or_default(),
+    })
+}
+
+/// 解析 `C346RspBody.apply_download_rsp`，返回下载地址
+///
+/// `download_url` 不是完整地址时与 `download_domain`（或 `download_ip`）及端口拼接
+pub fn decode_apply_download_rsp(command: &str, payload: &[u8]) -> NetworkResult<String> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(14)?
+        .unwrap_or_default();
+    check_ret(command, &rsp)?;
+    let info = rsp.get_message(30)?.unwrap_or_default();
+    let url = info.get_string(50).unwrap_or_default();
+    if url.starts_with("http") {
+        return Ok(url);
+    }
+    let host = info
+        .get_string(30)
+        .filter(|d| !d.is_empty())
+        .or_else(|| info.get_string(20))
+        .unwrap_or_default();
+    match info.get_u64(40) {
+        Some(port) if port != 0 => Ok(format!("http://{}:{}{}", host, port, url)),
+        _ => Ok(format!("http://{}{}", host, url)),
+    }
+}
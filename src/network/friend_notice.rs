@@ -0,0 +1,154 @@
+//! `OnlinePush.ReqPush` 中 0x210 的好友通知
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/decoders.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::ProtoReader,
+    events::{
+        friend::{
+            FriendAddedEvent, FriendDeletedEvent, FriendInputStatusEvent, FriendNickChangeEvent,
+        },
+        Event,
+    },
+};
+
+/// 好友列表同步
+pub(crate) const SUB_FRIEND_SYNC: i64 = 0x44;
+/// 新增好友通知
+pub(crate) const SUB_FRIEND_ADDED: i64 = 0xb3;
+/// 正在输入
+pub(crate) const SUB_INPUT_STATUS: i64 = 0x115;
+
+/// `ModProfile` 中昵称的字段
+const PROFILE_NICK: u64 = 20002;
+/// `SubMsgType0x115` 中开始输入的事件类型
+const INPUT_STARTED: u64 = 1;
+
+/// 解析 0x44、0xb3 与 0x115，其他子类型返回空
+pub(crate) fn decode_friend_push(sub_type: i64, protobuf: &[u8]) -> io::Result<Vec<Event>> {
+    let body = ProtoReader::decode(protobuf)?;
+    let mut events = Vec::new();
+    match sub_type {
+        SUB_FRIEND_SYNC => {
+            if let Some(sync) = body.get_message(1)? {
+                if let Some(uin) = sync.get_u64(2).filter(|uin| *uin != 0) {
+                    events.push(Event::FriendAdded(FriendAddedEvent {
+                        uin,
+                        nick: String::new(),
+                    }));
+                }
+            }
+        }
+        SUB_FRIEND_ADDED => {
+            if let Some(notify) = body.get_message(2)? {
+                events.push(Event::FriendAdded(FriendAddedEvent {
+                    uin: notify.get_u64(1).unwrap_or_default(),
+                    nick: notify.get_string(5).unwrap_or_default(),
+                }));
+            }
+        }
+        SUB_INPUT_STATUS => {
+            if let Some(item) = body.get_message(3)? {
+                events.push(Event::FriendInputStatus(FriendInputStatusEvent {
+                    uin: body.get_u64(1).unwrap_or_default(),
+                    typing: item.get_u64(1) == Some(INPUT_STARTED),
+                }));
+            }
+        }
+        _ => {}
+    }
+    Ok(events)
+}
+
+/// 解析 0x210 子类型 0x27 中一条 `ForwardBody` 的好友相关修改
+pub(crate) fn decode_friend_profile_mod(body: &ProtoReader) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    if let Some(profile) = body.get_message(8)? {
+        let uin = profile.get_u64(1).unwrap_or_default();
+        for info in profile.get_repeated_message(2)? {
+            if info.get_u64(1) == Some(PROFILE_NICK) {
+                events.push(Event::FriendNickChange(FriendNickChangeEvent {
+                    uin,
+                    new_nick: info.get_string(2).unwrap_or_default(),
+                }));
+            }
+        }
+    }
+    if let Some(deleted) = body.get_message(14)? {
+        events.extend(
+            deleted
+                .get_repeated_u64(1)
+                .into_iter()
+                .map(|uin| Event::FriendDeleted(FriendDeletedEvent { uin })),
+        );
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binary::protobuf::DynamicProtoMessage;
+
+    #[test]
+    fn test_decode_friend_push() {
+        let added = DynamicProtoMessage::new()
+            .with(
+                2,
+                DynamicProtoMessage::new()
+                    .with(1, 10086u64)
+                    .with(5, "nick".to_string()),
+            )
+            .encode()
+            .unwrap();
+        let events = decode_friend_push(SUB_FRIEND_ADDED, &added).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::FriendAdded(e)] if e.uin == 10086 && e.nick == "nick"
+        ));
+
+        let typing = DynamicProtoMessage::new()
+            .with(1, 10086u64)
+            .with(3, DynamicProtoMessage::new().with(1, INPUT_STARTED))
+            .encode()
+            .unwrap();
+        let events = decode_friend_push(SUB_INPUT_STATUS, &typing).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::FriendInputStatus(FriendInputStatusEvent {
+                uin: 10086,
+                typing: true
+            })]
+        ));
+    }
+
+    #[test]
+    fn test_decode_friend_profile_mod() {
+        let body = DynamicProtoMessage::new()
+            .with(
+                8,
+                DynamicProtoMessage::new().with(1, 10086u64).with(
+                    2,
+                    vec![DynamicProtoMessage::new()
+                        .with(1, PROFILE_NICK)
+                        .with(2, "new nick".to_string())],
+                ),
+            )
+            .with(
+                14,
+                DynamicProtoMessage::new().with(1, vec![10010u64, 10011]),
+            )
+            .encode()
+            .unwrap();
+        let events = decode_friend_profile_mod(&ProtoReader::decode(&body).unwrap()).unwrap();
+        assert!(matches!(
+            &events[..],
+            [
+                Event::FriendNickChange(FriendNickChangeEvent { uin: 10086, new_nick }),
+                Event::FriendDeleted(FriendDeletedEvent { uin: 10010 }),
+                Event::FriendDeleted(FriendDeletedEvent { uin: 10011 }),
+            ] if new_nick == "new nick"
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! 群成员列表与禁言等群管理操作
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/group_info.go)
+
+use std::io;
+
+use crate::binary::{
+    data_writer::DataWriter,
+    jce::{JceStruct, RequestPacket},
+    protobuf::DynamicProtoMessage,
+};
+
+use super::{
+    group_notice::group_code_to_uin,
+    oidb::oidb_request,
+    profile::{Gender, GroupMemberInfo, MemberPermission},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_GET_TROOP_MEMBER_LIST: &str = "friendlist.GetTroopMemberListReq";
+
+const OIDB_MUTE_MEMBER: u32 = 0x570;
+const SERVICE_MUTE_MEMBER: u32 = 8;
+const OIDB_GROUP_SETTING: u32 = 0x89a;
+const SERVICE_GROUP_SETTING: u32 = 0;
+/// 全员禁言时 `shutupTime` 的值
+const MUTE_ALL_FOREVER: u32 = 0x0FFF_FFFF;
+/// `TroopMemberInfo.Flag` 中表示管理员的位
+const FLAG_ADMINISTRATOR: i64 = 1;
+
+/// 分页拉取成员列表的一页
+#[derive(Debug, Clone, Default)]
+pub struct MemberListPage {
+    pub members: Vec<GroupMemberInfo>,
+    /// 为 0 时表示没有更多成员
+    pub next_uin: u64,
+}
+
+pub fn build_troop_member_list(uin: u64, group_code: u64, next_uin: u64) -> Vec<u8> {
+    let req = JceStruct::new()
+        .with(0, uin)
+        .with(1, group_code)
+        .with(2, next_uin)
+        .with(3, group_code_to_uin(group_code))
+        .with(4, 2u8);
+    RequestPacket::new(
+        "mqq.IMService.FriendListServiceServantObj",
+        "GetTroopMemberListReq",
+    )
+    .with_data("GTML", req)
+    .encode()
+}
+
+/// 解析 `GTMLRESP`，群主需要通过 `owner_uin` 判断
+pub fn decode_troop_member_list(
+    group_code: u64,
+    owner_uin: u64,
+    payload: &[u8],
+) -> io::Result<MemberListPage> {
+    let data = RequestPacket::decode(payload)?.data()?;
+    let rsp = data
+        .get("GTMLRESP")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing GTMLRESP"))?;
+    let members = rsp
+        .get_struct_list(3)
+        .into_iter()
+        .map(|m| {
+            let uin = m.get_i64(0).unwrap_or_default() as u64;
+            GroupMemberInfo {
+                group_code,
+                uin,
+                nick: m.get_string(4).unwrap_or_default(),
+                card: m.get_string(8).unwrap_or_default(),
+                gender: match m.get_i64(3) {
+                    Some(0) => Gender::Male,
+                    Some(1) => Gender::Female,
+                    _ => Gender::Unknown,
+                },
+                level: m.get_i64(14).unwrap_or_default() as u32,
+                join_time: m.get_i64(15).unwrap_or_default() as u64,
+                last_speak_time: m.get_i64(16).unwrap_or_default() as u64,
+                special_title: m.get_string(23).unwrap_or_default(),
+                permission: if uin == owner_uin {
+                    MemberPermission::Owner
+                } else if m.get_i64(18).unwrap_or_default() & FLAG_ADMINISTRATOR != 0 {
+                    MemberPermission::Administrator
+                } else {
+                    MemberPermission::Member
+                },
+            }
+        })
+        .collect();
+    Ok(MemberListPage {
+        members,
+        next_uin: rsp.get_i64(4).unwrap_or_default() as u64,
+    })
+}
+
+/// 拉取完整的群成员列表，群主可以从 [`get_group_info`](super::profile::get_group_info) 得到
+pub async fn get_group_member_list<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    group_code: u64,
+    owner_uin: u64,
+) -> NetworkResult<Vec<GroupMemberInfo>> {
+    let mut members = Vec::new();
+    let mut next_uin = 0;
+    loop {
+        let req = build_troop_member_list(uin, group_code, next_uin);
+        let rsp = send_uni_request(sender, CMD_GET_TROOP_MEMBER_LIST, &req).await?;
+        let page = decode_troop_member_list(group_code, owner_uin, &rsp)?;
+        members.extend(page.members);
+        if page.next_uin == 0 {
+            return Ok(members);
+        }
+        next_uin = page.next_uin;
+    }
+}
+
+/// `0x570_8` 的 body：群号、固定的 0x20、成员数量以及各成员的禁言时长
+pub fn build_mute_member(group_code: u64, member_uin: u64, seconds: u32) -> io::Result<Vec<u8>> {
+    let mut w = DataWriter::new();
+    w.write_data(&(group_code as u32))?;
+    w.write_data(&0x20u8)?;
+    w.write_data(&1u16)?;
+    w.write_data(&(member_uin as u32))?;
+    w.write_data(&seconds)?;
+    Ok(w.into_inner())
+}
+
+/// 禁言群成员，`seconds` 为 0 时解除禁言，最长 30 天
+pub async fn mute_member<S: SsoSender>(
+    sender: &S,
+    group_code: u64,
+    member_uin: u64,
+    seconds: u32,
+) -> NetworkResult<()> {
+    if seconds > 30 * 24 * 3600 {
+        return Err(NetworkError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mute duration exceeds 30 days",
+        )));
+    }
+    let body = build_mute_member(group_code, member_uin, seconds)?;
+    oidb_request(sender, OIDB_MUTE_MEMBER, SERVICE_MUTE_MEMBER, body).await?;
+    Ok(())
+}
+
+pub fn build_mute_all(group_code: u64, mute: bool) -> io::Result<Vec<u8>> {
+    let time = if mute { MUTE_ALL_FOREVER } else { 0 };
+    DynamicProtoMessage::new()
+        .with(1, group_code)
+        .with(2, DynamicProtoMessage::new().with(17, time))
+        .encode()
+}
+
+/// 开启或关闭全员禁言
+pub async fn mute_all<S: SsoSender>(sender: &S, group_code: u64, mute: bool) -> NetworkResult<()> {
+    let body = build_mute_all(group_code, mute)?;
+    oidb_request(sender, OIDB_GROUP_SETTING, SERVICE_GROUP_SETTING, body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{binary::data_reader::DataReader, network::test::MockSender};
+
+    fn member_list_rsp(uins: &[(u64, i64)], next_uin: u64) -> Vec<u8> {
+        let members = uins
+            .iter()
+            .map(|(uin, flag)| {
+                JceStruct::new()
+                    .with(0, *uin)
+                    .with(4, format!("nick{}", uin))
+                    .with(18, *flag)
+            })
+            .collect::<Vec<_>>();
+        let rsp = JceStruct::new().with(3, members).with(4, next_uin);
+        RequestPacket::new(
+            "mqq.IMService.FriendListServiceServantObj",
+            "GetTroopMemberListResp",
+        )
+        .with_data("GTMLRESP", rsp)
+        .encode()
+    }
+
+    #[tokio::test]
+    async fn test_get_group_member_list() {
+        let sender = MockSender::new(vec![
+            member_list_rsp(&[(10001, 0), (10002, 1)], 10002),
+            member_list_rsp(&[(10003, 0)], 0),
+        ]);
+        let members = get_group_member_list(&sender, 10086, 114514, 10001)
+            .await
+            .unwrap();
+        let permissions = members.iter().map(|m| m.permission).collect::<Vec<_>>();
+        assert_eq!(
+            permissions,
+            [
+                MemberPermission::Owner,
+                MemberPermission::Administrator,
+                MemberPermission::Member
+            ]
+        );
+        assert_eq!(members[2].nick, "nick10003");
+
+        let (command, body) = sender.sent_body(1);
+        assert_eq!(command, CMD_GET_TROOP_MEMBER_LIST);
+        let data = RequestPacket::decode(&body).unwrap().data().unwrap();
+        assert_eq!(data["GTML"].get_i64(2), Some(10002));
+    }
+
+    #[tokio::test]
+    async fn test_mute_member() {
+        let rsp = DynamicProtoMessage::new().with(3, 0u32).encode().unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        mute_member(&sender, 114514, 10010, 600).await.unwrap();
+
+        let (_, body) = sender.sent_body(0);
+        let pkg = crate::binary::protobuf::ProtoReader::decode(&body).unwrap();
+        let mut reader = DataReader::new(pkg.get_bytes(4).unwrap().to_vec());
+        assert_eq!(reader.read_data::<u32>().unwrap(), 114514);
+        assert_eq!(reader.read_data::<u8>().unwrap(), 0x20);
+        assert_eq!(reader.read_data::<u16>().unwrap(), 1);
+        assert_eq!(reader.read_data::<u32>().unwrap(), 10010);
+        assert_eq!(reader.read_data::<u32>().unwrap(), 600);
+
+        assert!(mute_member(&sender, 114514, 10010, u32::MAX).await.is_err());
+    }
+}
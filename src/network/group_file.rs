@@ -0,0 +1,630 @@
+//! 群文件，文件相关请求为 `OidbSvc.0x6d6`，文件夹为 `0x6d7`，列表与容量为 `0x6d8`，
+//! 上传后的文件动态为 `0x6d9`
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/group_file.go)
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    utils::crypto::{md5_digest, sha1_digest},
+};
+
+use super::{
+    highway::{exciting::ExcitingUpload, HighwaySession, Transaction},
+    oidb::{oidb_command_name, oidb_request},
+    NetworkError, NetworkResult, SsoSender,
+};
+
+pub const ROOT_FOLDER_ID: &str = "/";
+
+const APP_ID: u32 = 3;
+const BUS_ID: u32 = 102;
+const PAGE_SIZE: u32 = 20;
+const HIGHWAY_GROUP_FILE: u32 = 71;
+
+const OIDB_FILE: u32 = 0x6d6;
+const OIDB_FOLDER: u32 = 0x6d7;
+const OIDB_LIST: u32 = 0x6d8;
+const OIDB_FEED: u32 = 0x6d9;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupFile {
+    pub group_code: u64,
+    pub file_id: String,
+    pub file_name: String,
+    pub size: u64,
+    pub bus_id: u32,
+    pub parent_folder_id: String,
+    pub upload_time: u32,
+    /// 过期时间，永久文件为 0
+    pub dead_time: u32,
+    pub modify_time: u32,
+    pub download_times: u32,
+    pub uploader_uin: u64,
+    pub uploader_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupFolder {
+    pub group_code: u64,
+    pub folder_id: String,
+    pub parent_folder_id: String,
+    pub folder_name: String,
+    pub create_time: u32,
+    pub modify_time: u32,
+    pub creator_uin: u64,
+    pub creator_name: String,
+    pub file_count: u32,
+}
+
+/// 一页文件列表
+#[derive(Debug, Clone, Default)]
+pub struct GroupFileList {
+    pub files: Vec<GroupFile>,
+    pub folders: Vec<GroupFolder>,
+    /// 请求下一页时使用的 `start_index`
+    pub next_index: u32,
+    pub is_end: bool,
+}
+
+/// 群文件的容量与数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroupFileQuota {
+    pub total_space: u64,
+    pub used_space: u64,
+    pub file_count: u32,
+    pub limit_count: u32,
+}
+
+/// 各请求的响应体均以 `ret_code`、`ret_msg`、`client_wording` 开头
+fn check_ret(command: u32, service_type: u32, rsp: &ProtoReader) -> NetworkResult<()> {
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: oidb_command_name(command, service_type),
+            code,
+            message: rsp
+                .get_string(3)
+                .filter(|m| !m.is_empty())
+                .or_else(|| rsp.get_string(2))
+                .unwrap_or_default(),
+        }),
+    }
+}
+
+fn decode_file(group_code: u64, info: &ProtoReader) -> GroupFile {
+    GroupFile {
+        group_code,
+        file_id: info.get_string(1).unwrap_or_default(),
+        file_name: info.get_string(2).unwrap_or_default(),
+        size: info.get_u64(3).unwrap_or_default(),
+        bus_id: info.get_u64(4).unwrap_or_default() as u32,
+        upload_time: info.get_u64(6).unwrap_or_default() as u32,
+        dead_time: info.get_u64(7).unwrap_or_default() as u32,
+        modify_time: info.get_u64(8).unwrap_or_default() as u32,
+        download_times: info.get_u64(9).unwrap_or_default() as u32,
+        uploader_name: info.get_string(14).unwrap_or_default(),
+        uploader_uin: info.get_u64(15).unwrap_or_default(),
+        parent_folder_id: info.get_string(16).unwrap_or_default(),
+    }
+}
+
+fn decode_folder(group_code: u64, info: &ProtoReader) -> GroupFolder {
+    GroupFolder {
+        group_code,
+        folder_id: info.get_string(1).unwrap_or_default(),
+        parent_folder_id: info.get_string(2).unwrap_or_default(),
+        folder_name: info.get_string(3).unwrap_or_default(),
+        create_time: info.get_u64(4).unwrap_or_default() as u32,
+        modify_time: info.get_u64(5).unwrap_or_default() as u32,
+        creator_uin: info.get_u64(6).unwrap_or_default(),
+        creator_name: info.get_string(7).unwrap_or_default(),
+        file_count: info.get_u64(8).unwrap_or_default() as u32,
+    }
+}
+
+pub fn build_file_list(group_code: u64, folder_id: &str, start_index: u32) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(
+            2,
+            DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, APP_ID)
+                .with(3, folder_id.to_string())
+                .with(5, PAGE_SIZE)
+                .with(8, 3u32)
+                .with(9, 1u32)
+                .with(13, start_index)
+                .with(14, Vec::<u8>::new()),
+        )
+        .encode()
+}
+
+/// 解析 `D6D8RspBody.file_list_info_rsp`
+pub fn decode_file_list(group_code: u64, payload: &[u8]) -> NetworkResult<GroupFileList> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(2)?
+        .unwrap_or_default();
+    check_ret(OIDB_LIST, 1, &rsp)?;
+    let mut list = GroupFileList {
+        next_index: rsp.get_u64(13).unwrap_or_default() as u32,
+        is_end: rsp.get_u64(4).unwrap_or_default() != 0,
+        ..Default::default()
+    };
+    for item in rsp.get_repeated_message(5)? {
+        match item.get_u64(1) {
+            Some(1) => {
+                if let Some(info) = item.get_message(3)? {
+                    list.files.push(decode_file(group_code, &info));
+                }
+            }
+            Some(2) => {
+                if let Some(info) = item.get_message(2)? {
+                    list.folders.push(decode_folder(group_code, &info));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(list)
+}
+
+/// `OidbSvc.0x6d6_0` 的响应
+#[derive(Debug, Clone, Default)]
+pub struct GroupFileUploadApply {
+    pub exists: bool,
+    pub file_id: String,
+    pub bus_id: u32,
+    pub check_key: Vec<u8>,
+    pub servers: Vec<SocketAddr>,
+}
+
+pub fn build_upload_apply(
+    group_code: u64,
+    parent_folder_id: &str,
+    file_name: &str,
+    size: u64,
+    md5: &[u8; 16],
+    sha1: &[u8; 20],
+) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(
+            1,
+            DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, APP_ID)
+                .with(3, BUS_ID)
+                .with(4, 5u32)
+                .with(5, parent_folder_id.to_string())
+                .with(6, file_name.to_string())
+                .with(7, format!("/storage/emulated/0/Download/{}", file_name))
+                .with(8, size)
+                .with(9, sha1.to_vec())
+                .with(11, md5.to_vec())
+                .with(15, true),
+        )
+        .encode()
+}
+
+pub fn decode_upload_apply(payload: &[u8]) -> NetworkResult<GroupFileUploadApply> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(1)?
+        .unwrap_or_default();
+    check_ret(OIDB_FILE, 0, &rsp)?;
+    let port = rsp.get_u64(14).unwrap_or_default() as u16;
+    let servers = rsp
+        .get_string(4)
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, port))
+        .into_iter()
+        .collect();
+    Ok(GroupFileUploadApply {
+        exists: rsp.get_u64(10).unwrap_or_default() != 0,
+        file_id: rsp.get_string(7).unwrap_or_default(),
+        bus_id: rsp.get_u64(6).unwrap_or_default() as u32,
+        check_key: rsp.get_bytes(8).unwrap_or_default().to_vec(),
+        servers,
+    })
+}
+
+pub fn build_file_feed(group_code: u64, file_id: &str, bus_id: u32) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(
+            5,
+            DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, APP_ID)
+                .with(
+                    3,
+                    vec![DynamicProtoMessage::new()
+                        .with(1, bus_id)
+                        .with(2, file_id.to_string())
+                        .with(3, rand::random::<u32>())
+                        .with(5, 1u32)],
+                ),
+        )
+        .encode()
+}
+
+/// 群文件的操作句柄
+pub struct GroupFileSystem<'a, S> {
+    sender: &'a S,
+    pub group_code: u64,
+}
+
+impl<'a, S: SsoSender> GroupFileSystem<'a, S> {
+    pub fn new(sender: &'a S, group_code: u64) -> Self {
+        Self { sender, group_code }
+    }
+
+    /// 发送 `D6D*ReqBody` 并取出响应中 `field` 对应的部分，同时检查返回码
+    async fn request(
+        &self,
+        command: u32,
+        service_type: u32,
+        field: u64,
+        body: io::Result<Vec<u8>>,
+    ) -> NetworkResult<ProtoReader> {
+        let rsp = oidb_request(self.sender, command, service_type, body?).await?;
+        let rsp = ProtoReader::decode(&rsp)?
+            .get_message(field)?
+            .unwrap_or_default();
+        check_ret(command, service_type, &rsp)?;
+        Ok(rsp)
+    }
+
+    fn group_req(&self) -> DynamicProtoMessage {
+        DynamicProtoMessage::new()
+            .with(1, self.group_code)
+            .with(2, APP_ID)
+    }
+
+    /// 查询已用容量与文件数量
+    pub async fn quota(&self) -> NetworkResult<GroupFileQuota> {
+        let body = DynamicProtoMessage::new()
+            .with(4, self.group_req())
+            .encode();
+        let space = self.request(OIDB_LIST, 3, 4, body).await?;
+        let body = DynamicProtoMessage::new()
+            .with(3, self.group_req().with(3, 0u32))
+            .encode();
+        let count = self.request(OIDB_LIST, 2, 3, body).await?;
+        Ok(GroupFileQuota {
+            total_space: space.get_u64(4).unwrap_or_default(),
+            used_space: space.get_u64(5).unwrap_or_default(),
+            file_count: count.get_u64(4).unwrap_or_default() as u32,
+            limit_count: count.get_u64(6).unwrap_or_default() as u32,
+        })
+    }
+
+    /// 获取文件夹中的一页内容，第一页的 `start_index` 为 0
+    pub async fn list(&self, folder_id: &str, start_index: u32) -> NetworkResult<GroupFileList> {
+        let body = build_file_list(self.group_code, folder_id, start_index)?;
+        let rsp = oidb_request(self.sender, OIDB_LIST, 1, body).await?;
+        decode_file_list(self.group_code, &rsp)
+    }
+
+    /// 依次请求所有分页
+    pub async fn list_all(&self, folder_id: &str) -> NetworkResult<GroupFileList> {
+        let mut all = GroupFileList::default();
+        loop {
+            let page = self.list(folder_id, all.next_index).await?;
+            all.files.extend(page.files);
+            all.folders.extend(page.folders);
+            all.is_end = page.is_end || page.next_index <= all.next_index;
+            all.next_index = page.next_index;
+            if all.is_end {
+                return Ok(all);
+            }
+        }
+    }
+
+    pub async fn root(&self) -> NetworkResult<GroupFileList> {
+        self.list_all(ROOT_FOLDER_ID).await
+    }
+
+    pub async fn create_folder(&self, parent_folder_id: &str, name: &str) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                1,
+                self.group_req()
+                    .with(3, parent_folder_id.to_string())
+                    .with(4, name.to_string()),
+            )
+            .encode();
+        self.request(OIDB_FOLDER, 0, 1, body).await.map(|_| ())
+    }
+
+    pub async fn rename_folder(&self, folder_id: &str, name: &str) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                3,
+                self.group_req()
+                    .with(3, folder_id.to_string())
+                    .with(4, name.to_string()),
+            )
+            .encode();
+        self.request(OIDB_FOLDER, 2, 3, body).await.map(|_| ())
+    }
+
+    /// 删除文件夹及其中的文件
+    pub async fn delete_folder(&self, folder_id: &str) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(2, self.group_req().with(3, folder_id.to_string()))
+            .encode();
+        self.request(OIDB_FOLDER, 1, 2, body).await.map(|_| ())
+    }
+
+    /// 上传文件到指定文件夹，完成后发送文件动态使群成员可见
+    pub async fn upload(
+        &self,
+        highway: &HighwaySession,
+        parent_folder_id: &str,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> NetworkResult<GroupFile> {
+        let size = data.len() as u64;
+        let (md5, sha1) = (md5_digest(&data), sha1_digest(&data));
+        let body = build_upload_apply(
+            self.group_code,
+            parent_folder_id,
+            file_name,
+            size,
+            &md5,
+            &sha1,
+        )?;
+        let rsp = oidb_request(self.sender, OIDB_FILE, 0, body).await?;
+        let apply = decode_upload_apply(&rsp)?;
+
+        if !apply.exists {
+            let ext = ExcitingUpload {
+                bus_id: apply.bus_id,
+                sender_uin: highway.uin,
+                receiver_uin: self.group_code,
+                group_code: self.group_code,
+                app_id: highway.app_id,
+                file_name,
+                size,
+                md5: &md5,
+                sha1: &sha1,
+                file_id: apply.file_id.as_bytes(),
+                upload_key: &apply.check_key,
+                servers: &apply.servers,
+            }
+            .encode_group()?;
+            let trans = Transaction::new(HIGHWAY_GROUP_FILE, highway.sig_session.clone(), data)
+                .with_ext(ext);
+            let servers = match apply.servers.is_empty() {
+                true => &highway.servers,
+                false => &apply.servers,
+            };
+            highway.upload(servers, trans, 0).await?;
+        }
+
+        let body = build_file_feed(self.group_code, &apply.file_id, apply.bus_id);
+        self.request(OIDB_FEED, 4, 5, body).await?;
+        Ok(GroupFile {
+            group_code: self.group_code,
+            file_id: apply.file_id,
+            file_name: file_name.to_string(),
+            size,
+            bus_id: apply.bus_id,
+            parent_folder_id: parent_folder_id.to_string(),
+            uploader_uin: highway.uin,
+            ..Default::default()
+        })
+    }
+
+    pub async fn download_url(&self, file: &GroupFile) -> NetworkResult<String> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                3,
+                self.group_req()
+                    .with(3, file.bus_id)
+                    .with(4, file.file_id.clone()),
+            )
+            .encode();
+        let rsp = self.request(OIDB_FILE, 2, 3, body).await?;
+        Ok(format!(
+            "http://{}/ftn_handler/{}/?fname={}",
+            rsp.get_string(4).unwrap_or_default(),
+            hex::encode(rsp.get_bytes(6).unwrap_or_default()),
+            hex::encode(file.file_id.as_bytes())
+        ))
+    }
+
+    pub async fn move_file(&self, file: &GroupFile, dest_folder_id: &str) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                6,
+                self.group_req()
+                    .with(3, file.bus_id)
+                    .with(4, file.file_id.clone())
+                    .with(5, file.parent_folder_id.clone())
+                    .with(6, dest_folder_id.to_string()),
+            )
+            .encode();
+        self.request(OIDB_FILE, 5, 6, body).await.map(|_| ())
+    }
+
+    pub async fn rename_file(&self, file: &GroupFile, name: &str) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                5,
+                self.group_req()
+                    .with(3, file.bus_id)
+                    .with(4, file.file_id.clone())
+                    .with(5, file.parent_folder_id.clone())
+                    .with(6, name.to_string()),
+            )
+            .encode();
+        self.request(OIDB_FILE, 4, 5, body).await.map(|_| ())
+    }
+
+    pub async fn delete_file(&self, file: &GroupFile) -> NetworkResult<()> {
+        let body = DynamicProtoMessage::new()
+            .with(
+                4,
+                self.group_req()
+                    .with(3, file.bus_id)
+                    .with(4, file.parent_folder_id.clone())
+                    .with(5, file.file_id.clone()),
+            )
+            .encode();
+        self.request(OIDB_FILE, 3, 4, body).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    fn oidb_rsp(body: DynamicProtoMessage) -> Vec<u8> {
+        DynamicProtoMessage::new()
+            .with(3, 0u32)
+            .with(4, body.encode().unwrap())
+            .encode()
+            .unwrap()
+    }
+
+    fn sent_oidb_body(sender: &MockSender, idx: usize) -> (String, ProtoReader) {
+        let (command, body) = sender.sent_body(idx);
+        let pkg = ProtoReader::decode(&body).unwrap();
+        let body = ProtoReader::decode(pkg.get_bytes(4).unwrap()).unwrap();
+        (command, body)
+    }
+
+    fn list_page(next_index: u32, is_end: bool, name: &str) -> Vec<u8> {
+        oidb_rsp(
+            DynamicProtoMessage::new().with(
+                2,
+                DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(4, is_end)
+                    .with(13, next_index)
+                    .with(
+                        5,
+                        vec![
+                            DynamicProtoMessage::new().with(1, 1u32).with(
+                                3,
+                                DynamicProtoMessage::new()
+                                    .with(1, format!("/{}", name))
+                                    .with(2, name.to_string())
+                                    .with(3, 1024u64)
+                                    .with(4, BUS_ID),
+                            ),
+                            DynamicProtoMessage::new().with(1, 2u32).with(
+                                2,
+                                DynamicProtoMessage::new()
+                                    .with(1, format!("/dir-{}", name))
+                                    .with(3, name.to_string()),
+                            ),
+                        ],
+                    ),
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_list_all_pages() {
+        let sender = MockSender::new(vec![list_page(20, false, "a"), list_page(40, true, "b")]);
+        let fs = GroupFileSystem::new(&sender, 114514);
+
+        let root = fs.root().await.unwrap();
+        assert!(root.is_end);
+        assert_eq!(root.files.len(), 2);
+        assert_eq!(root.files[1].file_name, "b");
+        assert_eq!(root.files[1].group_code, 114514);
+        assert_eq!(root.folders[0].folder_id, "/dir-a");
+
+        let (command, body) = sent_oidb_body(&sender, 1);
+        assert_eq!(command, "OidbSvc.0x6d8_1");
+        let req = body.get_message(2).unwrap().unwrap();
+        assert_eq!(req.get_string(3).unwrap(), ROOT_FOLDER_ID);
+        assert_eq!(req.get_u64(13), Some(20));
+    }
+
+    #[tokio::test]
+    async fn test_upload_existing_file() {
+        let sender = MockSender::new(vec![
+            oidb_rsp(
+                DynamicProtoMessage::new().with(
+                    1,
+                    DynamicProtoMessage::new()
+                        .with(1, 0u32)
+                        .with(6, BUS_ID)
+                        .with(7, "/file-id".to_string())
+                        .with(10, true),
+                ),
+            ),
+            oidb_rsp(DynamicProtoMessage::new().with(5, DynamicProtoMessage::new().with(1, 0u32))),
+        ]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+        let fs = GroupFileSystem::new(&sender, 114514);
+
+        let data = b"build artifact".to_vec();
+        let file = fs
+            .upload(&highway, ROOT_FOLDER_ID, "app.zip", data.clone())
+            .await
+            .unwrap();
+        assert_eq!(file.file_id, "/file-id");
+        assert_eq!(file.size, data.len() as u64);
+
+        let (_, body) = sent_oidb_body(&sender, 0);
+        let req = body.get_message(1).unwrap().unwrap();
+        assert_eq!(req.get_bytes(9), Some(&sha1_digest(&data)[..]));
+        let (command, body) = sent_oidb_body(&sender, 1);
+        assert_eq!(command, "OidbSvc.0x6d9_4");
+        let feed = body.get_message(5).unwrap().unwrap();
+        let info = feed.get_message(3).unwrap().unwrap();
+        assert_eq!(info.get_string(2).unwrap(), "/file-id");
+    }
+
+    #[tokio::test]
+    async fn test_quota_and_errors() {
+        let sender = MockSender::new(vec![
+            oidb_rsp(
+                DynamicProtoMessage::new().with(
+                    4,
+                    DynamicProtoMessage::new()
+                        .with(1, 0u32)
+                        .with(4, 10u64 << 30)
+                        .with(5, 1u64 << 30),
+                ),
+            ),
+            oidb_rsp(
+                DynamicProtoMessage::new().with(
+                    3,
+                    DynamicProtoMessage::new()
+                        .with(1, 0u32)
+                        .with(4, 12u32)
+                        .with(6, 10000u32),
+                ),
+            ),
+            oidb_rsp(
+                DynamicProtoMessage::new().with(
+                    2,
+                    DynamicProtoMessage::new()
+                        .with(1, 2u32)
+                        .with(3, "没有权限".to_string()),
+                ),
+            ),
+        ]);
+        let fs = GroupFileSystem::new(&sender, 114514);
+
+        let quota = fs.quota().await.unwrap();
+        assert_eq!(quota.used_space, 1 << 30);
+        assert_eq!((quota.file_count, quota.limit_count), (12, 10000));
+
+        match fs.delete_folder("/dir").await {
+            Err(NetworkError::Server { command, code, .. }) => {
+                assert_eq!((command.as_str(), code), ("OidbSvc.0x6d7_1", 2));
+            }
+            _ => panic!("except server error"),
+        }
+    }
+}
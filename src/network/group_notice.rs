@@ -0,0 +1,307 @@
+//! 群成员变动与群资料变更通知，分散在 `OnlinePush.PbPushTransMsg`、
+//! `OnlinePush.ReqPush` 的 0x2dc 与 0x210 中
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/online_push.go)
+
+use std::io;
+
+use crate::{
+    binary::{data_reader::DataReader, protobuf::ProtoReader},
+    events::{
+        group::{
+            GroupMuteAllEvent, GroupNameChangeEvent, GroupOwnerTransferEvent,
+            MemberCardChangeEvent, MemberJoinEvent, MemberLeaveEvent, MemberMuteEvent,
+            MemberPermissionChangeEvent, MemberSpecialTitleChangeEvent,
+        },
+        Event,
+    },
+};
+
+/// `PbPushTransMsg` 中的成员退出
+pub(crate) const TRANS_MEMBER_LEAVE: u64 = 0x22;
+/// `PbPushTransMsg` 中的管理员变更与群转让
+pub(crate) const TRANS_PERMISSION: u64 = 0x2c;
+
+/// 新成员入群的消息类型，位于 `MessageSvc.PbGetMsg` 拉取到的消息中
+pub const MSG_TYPE_MEMBER_JOIN: u64 = 33;
+
+/// 专属头衔的灰条提示模板 id
+const TITLE_TEMPLATE: u64 = 2407;
+
+/// `ModGroupProfile` 中群名称的字段
+const PROFILE_GROUP_NAME: u64 = 1;
+/// `ModGroupMemberProfile` 中群名片的字段
+const PROFILE_MEMBER_CARD: u64 = 1;
+
+/// 群号 (uin) 转为群号码 (code)
+pub fn group_uin_to_code(uin: u64) -> u64 {
+    let mut left = uin / 1_000_000;
+    match left {
+        202..=212 => left -= 202,
+        480..=488 => left -= 469,
+        2010..=2099 => left -= 1943,
+        2100..=2146 => left -= 2080,
+        2147..=2199 => left -= 1990,
+        2600..=2651 => left -= 2265,
+        3800..=3989 => left -= 3490,
+        4100..=4199 => left -= 3890,
+        _ => {}
+    }
+    left * 1_000_000 + uin % 1_000_000
+}
+
+/// 群号码 (code) 转为群号 (uin)，[`group_uin_to_code`] 的逆运算
+pub fn group_code_to_uin(code: u64) -> u64 {
+    let mut left = code / 1_000_000;
+    match left {
+        0..=10 => left += 202,
+        11..=19 => left += 469,
+        20..=66 => left += 2080,
+        67..=156 => left += 1943,
+        157..=209 => left += 1990,
+        210..=309 => left += 3890,
+        310..=335 => left += 3490,
+        336..=386 => left += 2265,
+        387..=499 => left += 3490,
+        _ => {}
+    }
+    left * 1_000_000 + code % 1_000_000
+}
+
+/// 从 `msg_type` 为 33 的 `MsgHead` 中解析新成员入群，由 `message::decode_get_msg_events` 调用
+pub fn decode_member_join(head: &ProtoReader) -> Option<MemberJoinEvent> {
+    if head.get_u64(3)? != MSG_TYPE_MEMBER_JOIN {
+        return None;
+    }
+    Some(MemberJoinEvent {
+        group_code: group_uin_to_code(head.get_u64(1)?),
+        member_uin: head.get_u64(13)?,
+        member_nick: head.get_string(14).unwrap_or_default(),
+    })
+}
+
+fn skip(reader: &mut DataReader, size: usize) -> io::Result<()> {
+    reader.read_data_limited::<Vec<u8>>(size).map(|_| ())
+}
+
+/// 解析 `TransMsgInfo.msg_data`，`group_code` 由 `from_uin` 转换而来
+pub(crate) fn decode_trans_data(
+    msg_type: u64,
+    group_code: u64,
+    data: &[u8],
+) -> io::Result<Vec<Event>> {
+    let mut reader = DataReader::new(data.to_vec());
+    let mut events = Vec::new();
+    match msg_type {
+        TRANS_MEMBER_LEAVE => {
+            skip(&mut reader, 5)?;
+            let member_uin = reader.read_data::<u32>()? as u64;
+            let leave_type = reader.read_data::<u8>()?;
+            let operator = reader.read_data::<u32>()? as u64;
+            // 0x02 与 0x82 为主动退出，0x03 与 0x83 为被踢出
+            let operator_uin = match leave_type {
+                0x02 | 0x82 => None,
+                0x03 | 0x83 => Some(operator),
+                _ => return Ok(events),
+            };
+            events.push(Event::MemberLeave(MemberLeaveEvent {
+                group_code,
+                member_uin,
+                operator_uin,
+            }));
+        }
+        TRANS_PERMISSION => {
+            skip(&mut reader, 5)?;
+            let sub_type = reader.read_data::<u8>()?;
+            let target = reader.read_data::<u32>()? as u64;
+            let new_owner = match sub_type {
+                0 | 1 => 0,
+                _ => reader.read_data::<u32>()? as u64,
+            };
+            if new_owner == 0 && reader.len() == 1 {
+                events.push(Event::MemberPermissionChange(MemberPermissionChangeEvent {
+                    group_code,
+                    member_uin: target,
+                    is_admin: reader.read_data::<u8>()? == 1,
+                }));
+            } else if new_owner != 0 {
+                events.push(Event::GroupOwnerTransfer(GroupOwnerTransferEvent {
+                    group_code,
+                    old_owner_uin: target,
+                    new_owner_uin: new_owner,
+                }));
+            }
+        }
+        _ => {}
+    }
+    Ok(events)
+}
+
+/// 解析 0x2dc 子类型 0x0c 的禁言通知，`reader` 位于群号与子类型之后
+pub(crate) fn decode_group_mute(group_code: u64, reader: &mut DataReader) -> io::Result<Event> {
+    let operator_uin = reader.read_data::<u32>()? as u64;
+    skip(reader, 6)?;
+    let member_uin = reader.read_data::<u32>()? as u64;
+    let duration = reader.read_data::<u32>()?;
+    Ok(if member_uin == 0 {
+        Event::GroupMuteAll(GroupMuteAllEvent {
+            group_code,
+            operator_uin,
+            enabled: duration != 0,
+        })
+    } else {
+        Event::MemberMute(MemberMuteEvent {
+            group_code,
+            operator_uin,
+            member_uin,
+            duration,
+        })
+    })
+}
+
+/// 解析群灰条提示中的头衔变更
+pub(crate) fn decode_group_gray_tip(
+    group_code: u64,
+    tip: &ProtoReader,
+) -> io::Result<Option<Event>> {
+    if tip.get_u64(6) != Some(TITLE_TEMPLATE) {
+        return Ok(None);
+    }
+    let mut event = MemberSpecialTitleChangeEvent {
+        group_code,
+        ..Default::default()
+    };
+    for param in tip.get_repeated_message(7)? {
+        let value = param.get_string(2).unwrap_or_default();
+        match param.get_string(1).unwrap_or_default().as_str() {
+            "uin" => event.member_uin = value.parse().unwrap_or_default(),
+            "title" => event.new_title = value,
+            _ => {}
+        }
+    }
+    Ok(Some(Event::MemberSpecialTitleChange(event)))
+}
+
+/// 解析 0x210 子类型 0x27 中一条 `ForwardBody` 的群相关修改
+pub(crate) fn decode_group_profile_mod(body: &ProtoReader) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    if let Some(profile) = body.get_message(12)? {
+        let group_code = profile.get_u64(3).unwrap_or_default();
+        for info in profile.get_repeated_message(2)? {
+            if info.get_u64(1) == Some(PROFILE_GROUP_NAME) {
+                events.push(Event::GroupNameChange(GroupNameChangeEvent {
+                    group_code,
+                    operator_uin: profile.get_u64(4).unwrap_or_default(),
+                    new_name: info.get_string(2).unwrap_or_default(),
+                }));
+            }
+        }
+    }
+    if let Some(profile) = body.get_message(13)? {
+        for info in profile.get_repeated_message(3)? {
+            if info.get_u64(1) == Some(PROFILE_MEMBER_CARD) {
+                events.push(Event::MemberCardChange(MemberCardChangeEvent {
+                    group_code: profile.get_u64(4).unwrap_or_default(),
+                    member_uin: profile.get_u64(2).unwrap_or_default(),
+                    new_card: info.get_string(2).unwrap_or_default(),
+                }));
+            }
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binary::protobuf::DynamicProtoMessage;
+
+    #[test]
+    fn test_group_uin_to_code() {
+        assert_eq!(group_code_to_uin(157_000_000), 2_147_000_000);
+        // 覆盖所有区间的每个百万段
+        for left in 0..=600u64 {
+            let code = left * 1_000_000 + 123_456;
+            assert_eq!(group_uin_to_code(group_code_to_uin(code)), code);
+        }
+    }
+
+    #[test]
+    fn test_decode_trans_data() {
+        let mut kick = 10086u32.to_be_bytes().to_vec();
+        kick.push(1);
+        kick.extend(12345u32.to_be_bytes());
+        kick.push(0x83);
+        kick.extend(10010u32.to_be_bytes());
+        let events = decode_trans_data(TRANS_MEMBER_LEAVE, 10086, &kick).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::MemberLeave(MemberLeaveEvent {
+                group_code: 10086,
+                member_uin: 12345,
+                operator_uin: Some(10010),
+            })]
+        ));
+
+        let mut admin = vec![0u8; 5];
+        admin.push(1);
+        admin.extend(12345u32.to_be_bytes());
+        admin.push(1);
+        let events = decode_trans_data(TRANS_PERMISSION, 10086, &admin).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::MemberPermissionChange(MemberPermissionChangeEvent {
+                member_uin: 12345,
+                is_admin: true,
+                ..
+            })]
+        ));
+
+        let mut transfer = vec![0u8; 5];
+        transfer.push(0xff);
+        transfer.extend(12345u32.to_be_bytes());
+        transfer.extend(10010u32.to_be_bytes());
+        let events = decode_trans_data(TRANS_PERMISSION, 10086, &transfer).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::GroupOwnerTransfer(GroupOwnerTransferEvent {
+                old_owner_uin: 12345,
+                new_owner_uin: 10010,
+                ..
+            })]
+        ));
+    }
+
+    #[test]
+    fn test_decode_group_profile_mod() {
+        let info = |field: u32, value: &str| {
+            DynamicProtoMessage::new()
+                .with(1, field)
+                .with(2, value.to_string())
+        };
+        let body = DynamicProtoMessage::new()
+            .with(
+                12,
+                DynamicProtoMessage::new()
+                    .with(2, vec![info(1, "new name")])
+                    .with(3, 10086u64)
+                    .with(4, 10010u64),
+            )
+            .with(
+                13,
+                DynamicProtoMessage::new()
+                    .with(2, 12345u64)
+                    .with(3, vec![info(1, "card")])
+                    .with(4, 10086u64),
+            )
+            .encode()
+            .unwrap();
+        let events = decode_group_profile_mod(&ProtoReader::decode(&body).unwrap()).unwrap();
+        assert!(matches!(
+            &events[..],
+            [
+                Event::GroupNameChange(GroupNameChangeEvent { operator_uin: 10010, new_name, .. }),
+                Event::MemberCardChange(MemberCardChangeEvent { member_uin: 12345, new_card, .. }),
+            ] if new_name == "new name" && new_card == "card"
+        ));
+    }
+}
@@ -0,0 +1,470 @@
+//! QQ 频道：频道列表同步、子频道、成员资料以及子频道消息的收发
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/guild.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    events::{message::GuildMessageEvent, Event},
+    message::{
+        image::{Image, ImageInfo, ImageKind},
+        MessageChain, MessageTarget,
+    },
+    utils::crypto::md5_digest,
+};
+
+use super::{
+    highway::{HighwaySession, Transaction},
+    image::{build_pic_up_req, decode_group_pic_up, group_pic_try_up},
+    oidb::oidb_request_with_name,
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_SYNC_FIRST_VIEW: &str = "trpc.group_pro.synclogic.SyncLogic.SyncFirstView";
+pub const CMD_PUSH_FIRST_VIEW: &str = "trpc.group_pro.synclogic.SyncLogic.PushFirstView";
+pub const CMD_PUSH_GUILD_MSG: &str = "MsgPush.PushGroupProMsg";
+pub const CMD_SEND_CHANNEL_MSG: &str = "MsgProxy.SendMsg";
+pub const CMD_CHANNEL_LIST: &str = "OidbSvcTrpcTcp.0xf5d_1";
+pub const CMD_MEMBER_PROFILE: &str = "OidbSvcTrpcTcp.0xfc9_1";
+pub const CMD_GUILD_PIC_UP: &str = "ImgStore.QQMeet.GroupPicUp";
+
+const OIDB_CHANNEL_LIST: u32 = 0xf5d;
+const OIDB_MEMBER_PROFILE: u32 = 0xfc9;
+/// highway 中的业务类型
+const HIGHWAY_GUILD_IMAGE: u32 = 83;
+/// `ChannelContentHead.type`，普通消息
+const CHANNEL_MSG_TYPE: u32 = 3840;
+
+/// 频道，与群不同，频道内的用户以 tiny id 区分
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Guild {
+    pub guild_id: u64,
+    /// 展示给用户的频道号
+    pub guild_code: u64,
+    pub name: String,
+    pub channels: Vec<Channel>,
+}
+
+/// 子频道
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Channel {
+    pub channel_id: u64,
+    pub name: String,
+    /// 1 文字，2 语音，5 直播，7 主题
+    pub channel_type: u32,
+}
+
+/// 登录后服务器推送的频道概览，bot 自己的 tiny id 见 [`sync_first_view`]
+#[derive(Debug, Clone, Default)]
+pub struct FirstView {
+    pub guilds: Vec<Guild>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuildMemberProfile {
+    pub tiny_id: u64,
+    pub nickname: String,
+    pub avatar_url: String,
+    /// 加入频道的时间，unix 时间戳
+    pub join_time: u32,
+}
+
+fn decode_channel_node(node: &ProtoReader) -> Channel {
+    Channel {
+        channel_id: node.get_u64(1).unwrap_or_default(),
+        name: node.get_string(8).unwrap_or_default(),
+        channel_type: node.get_u64(9).unwrap_or_default() as u32,
+    }
+}
+
+/// 解析 `FirstViewMsg`
+pub fn decode_first_view(payload: &[u8]) -> io::Result<FirstView> {
+    let msg = ProtoReader::decode(payload)?;
+    let guilds = msg
+        .get_repeated_message(3)?
+        .iter()
+        .map(|node| {
+            Ok(Guild {
+                guild_id: node.get_u64(1).unwrap_or_default(),
+                guild_code: node.get_u64(2).unwrap_or_default(),
+                name: node.get_string(4).unwrap_or_default(),
+                channels: node
+                    .get_repeated_message(3)?
+                    .iter()
+                    .map(decode_channel_node)
+                    .collect(),
+            })
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(FirstView { guilds })
+}
+
+pub fn build_sync_first_view() -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 0u64)
+        .with(3, 0u32)
+        .with(4, 1u32)
+        .encode()
+}
+
+/// 解析 `FirstViewRsp`，返回 bot 在频道中的 tiny id
+pub fn decode_sync_first_view(payload: &[u8]) -> NetworkResult<u64> {
+    let rsp = ProtoReader::decode(payload)?;
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(rsp.get_u64(6).unwrap_or_default()),
+        code => Err(NetworkError::Server {
+            command: CMD_SYNC_FIRST_VIEW.to_string(),
+            code,
+            message: rsp.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 请求同步频道概览，频道列表随后由 [`CMD_PUSH_FIRST_VIEW`] 推送
+pub async fn sync_first_view<S: SsoSender>(sender: &S) -> NetworkResult<u64> {
+    let rsp = send_uni_request(sender, CMD_SYNC_FIRST_VIEW, &build_sync_first_view()?).await?;
+    decode_sync_first_view(&rsp)
+}
+
+pub fn build_channel_list(guild_id: u64) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, guild_id)
+        .with(3, DynamicProtoMessage::new().with(1, 1u32).with(2, 1u32))
+        .encode()
+}
+
+/// 解析 `ChannelOidb0xf5dRsp`
+pub fn decode_channel_list(payload: &[u8]) -> io::Result<Vec<Channel>> {
+    let rsp = ProtoReader::decode(payload)?;
+    let list = rsp.get_message(1)?.unwrap_or_default();
+    Ok(list
+        .get_repeated_message(2)?
+        .iter()
+        .map(|info| Channel {
+            channel_id: info.get_u64(1).unwrap_or_default(),
+            name: info.get_string(2).unwrap_or_default(),
+            channel_type: info.get_u64(7).unwrap_or_default() as u32,
+        })
+        .collect())
+}
+
+/// 获取频道内的子频道列表，用于刷新 [`FirstView`] 中的数据
+pub async fn get_channel_list<S: SsoSender>(
+    sender: &S,
+    guild_id: u64,
+) -> NetworkResult<Vec<Channel>> {
+    let body = build_channel_list(guild_id)?;
+    let rsp = oidb_request_with_name(sender, CMD_CHANNEL_LIST, OIDB_CHANNEL_LIST, 1, body).await?;
+    Ok(decode_channel_list(&rsp)?)
+}
+
+pub fn build_member_profile(guild_id: u64, tiny_id: u64) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, guild_id)
+        .with(2, tiny_id)
+        .with(3, 1u32)
+        .with(
+            4,
+            DynamicProtoMessage::new()
+                .with(1, 1u32)
+                .with(2, 1u32)
+                .with(4, 1u32),
+        )
+        .encode()
+}
+
+/// 解析 `ChannelOidb0xfc9Rsp`
+pub fn decode_member_profile(payload: &[u8]) -> io::Result<GuildMemberProfile> {
+    let rsp = ProtoReader::decode(payload)?;
+    let profile = rsp.get_message(1)?.unwrap_or_default();
+    Ok(GuildMemberProfile {
+        tiny_id: profile.get_u64(1).unwrap_or_default(),
+        nickname: profile.get_string(2).unwrap_or_default(),
+        avatar_url: profile.get_string(6).unwrap_or_default(),
+        join_time: profile.get_u64(4).unwrap_or_default() as u32,
+    })
+}
+
+pub async fn get_guild_member_profile<S: SsoSender>(
+    sender: &S,
+    guild_id: u64,
+    tiny_id: u64,
+) -> NetworkResult<GuildMemberProfile> {
+    let body = build_member_profile(guild_id, tiny_id)?;
+    let rsp =
+        oidb_request_with_name(sender, CMD_MEMBER_PROFILE, OIDB_MEMBER_PROFILE, 1, body).await?;
+    Ok(decode_member_profile(&rsp)?)
+}
+
+/// 解析 `ChannelMsgContent`
+fn decode_channel_msg(msg: &ProtoReader) -> io::Result<GuildMessageEvent> {
+    let head = msg.get_message(1)?.unwrap_or_default();
+    let routing = head.get_message(1)?.unwrap_or_default();
+    let content = head.get_message(2)?.unwrap_or_default();
+    let rich = msg
+        .get_message(3)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .unwrap_or_default();
+    let ext = msg.get_message(4)?.unwrap_or_default();
+    Ok(GuildMessageEvent {
+        guild_id: routing.get_u64(1).unwrap_or_default(),
+        channel_id: routing.get_u64(2).unwrap_or_default(),
+        seq: content.get_u64(4).unwrap_or_default(),
+        time: content.get_u64(6).unwrap_or_default() as u32,
+        sender_tiny_id: routing.get_u64(4).unwrap_or_default(),
+        sender_nick: ext.get_string(1).unwrap_or_default(),
+        chain: MessageChain::from_rich_text(&rich)?,
+    })
+}
+
+/// 解析 `MsgPush.PushGroupProMsg`，一次推送可能包含多条消息
+pub fn decode_guild_messages(payload: &[u8]) -> io::Result<Vec<Event>> {
+    ProtoReader::decode(payload)?
+        .get_repeated_message(1)?
+        .iter()
+        .map(|msg| decode_channel_msg(msg).map(Event::GuildMessage))
+        .collect()
+}
+
+pub fn build_send_channel_msg(
+    guild_id: u64,
+    channel_id: u64,
+    chain: &MessageChain,
+) -> io::Result<Vec<u8>> {
+    // 子频道中的图片等元素与群消息的格式相同
+    let rich = chain.to_rich_text(MessageTarget::Group(channel_id));
+    let head = DynamicProtoMessage::new()
+        .with(
+            1,
+            DynamicProtoMessage::new()
+                .with(1, guild_id)
+                .with(2, channel_id),
+        )
+        .with(
+            2,
+            DynamicProtoMessage::new()
+                .with(1, CHANNEL_MSG_TYPE)
+                .with(3, rand::random::<u32>() as u64),
+        );
+    DynamicProtoMessage::new()
+        .with(
+            1,
+            DynamicProtoMessage::new()
+                .with(1, head)
+                .with(3, DynamicProtoMessage::new().with(1, rich)),
+        )
+        .encode()
+}
+
+/// 解析 `DF62RspBody`，返回消息的 seq
+pub fn decode_send_channel_msg(payload: &[u8]) -> NetworkResult<u64> {
+    let rsp = ProtoReader::decode(payload)?;
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => {}
+        code => {
+            return Err(NetworkError::Server {
+                command: CMD_SEND_CHANNEL_MSG.to_string(),
+                code,
+                message: rsp.get_string(2).unwrap_or_default(),
+            })
+        }
+    }
+    let content = rsp
+        .get_message(4)?
+        .unwrap_or_default()
+        .get_message(2)?
+        .unwrap_or_default();
+    Ok(content.get_u64(4).unwrap_or_default())
+}
+
+/// 向子频道发送消息，图片需要先通过 [`upload_guild_image`] 上传
+pub async fn send_channel_message<S: SsoSender>(
+    sender: &S,
+    guild_id: u64,
+    channel_id: u64,
+    chain: &MessageChain,
+) -> NetworkResult<u64> {
+    let req = build_send_channel_msg(guild_id, channel_id, chain)?;
+    let rsp = send_uni_request(sender, CMD_SEND_CHANNEL_MSG, &req).await?;
+    decode_send_channel_msg(&rsp)
+}
+
+/// 上传子频道图片，申请上传的请求与群图片相同，额外附带频道信息
+pub async fn upload_guild_image<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    guild_id: u64,
+    channel_id: u64,
+    data: Vec<u8>,
+) -> NetworkResult<Image> {
+    let info = ImageInfo::detect(&data)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported image format"))?;
+    let md5 = md5_digest(&data);
+    let size = data.len() as u32;
+
+    let try_up = group_pic_try_up(highway.uin, channel_id, &md5, size, &info)
+        .with(19, guild_id)
+        .with(20, channel_id);
+    let rsp = send_uni_request(sender, CMD_GUILD_PIC_UP, &build_pic_up_req(try_up)?).await?;
+    let apply = decode_group_pic_up(&rsp)?;
+    if !apply.exists {
+        let trans = Transaction::new(HIGHWAY_GUILD_IMAGE, apply.ticket.ukey.clone(), data);
+        highway
+            .upload(&apply.ticket.servers, trans, apply.ticket.offset)
+            .await?;
+    }
+
+    let mut image = Image::new(md5, size, info, ImageKind::Group);
+    image.file_id = apply.file_id;
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    #[test]
+    fn test_decode_first_view() {
+        let payload = DynamicProtoMessage::new()
+            .with(
+                3,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 1001u64)
+                    .with(2, 7788u64)
+                    .with(4, "rust 交流".to_string())
+                    .with(
+                        3,
+                        vec![
+                            DynamicProtoMessage::new()
+                                .with(1, 1u64)
+                                .with(8, "闲聊".to_string())
+                                .with(9, 1u32),
+                            DynamicProtoMessage::new()
+                                .with(1, 2u64)
+                                .with(8, "语音".to_string())
+                                .with(9, 2u32),
+                        ],
+                    )],
+            )
+            .with(5, 1700000000u64)
+            .encode()
+            .unwrap();
+        let view = decode_first_view(&payload).unwrap();
+        assert_eq!(view.guilds.len(), 1);
+        let guild = &view.guilds[0];
+        assert_eq!((guild.guild_id, guild.guild_code), (1001, 7788));
+        assert_eq!(guild.name, "rust 交流");
+        assert_eq!(guild.channels[1].name, "语音");
+        assert_eq!(guild.channels[1].channel_type, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sync_first_view() {
+        let rsp = DynamicProtoMessage::new()
+            .with(1, 0u32)
+            .with(5, 1u32)
+            .with(6, 144115218676725000u64)
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        assert_eq!(sync_first_view(&sender).await.unwrap(), 144115218676725000);
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SYNC_FIRST_VIEW);
+        assert_eq!(ProtoReader::decode(&body).unwrap().get_u64(4), Some(1));
+
+        let rsp = DynamicProtoMessage::new()
+            .with(1, 1u32)
+            .with(2, "busy".to_string())
+            .encode()
+            .unwrap();
+        assert!(matches!(
+            decode_sync_first_view(&rsp),
+            Err(NetworkError::Server { code: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_guild_messages() {
+        let chain = MessageChain::new().with("hello guild");
+        let msg = DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(
+                        1,
+                        DynamicProtoMessage::new()
+                            .with(1, 1001u64)
+                            .with(2, 2u64)
+                            .with(4, 5566u64),
+                    )
+                    .with(
+                        2,
+                        DynamicProtoMessage::new()
+                            .with(4, 99u64)
+                            .with(6, 1700000000u64),
+                    ),
+            )
+            .with(
+                3,
+                DynamicProtoMessage::new().with(1, chain.to_rich_text(MessageTarget::Group(2))),
+            )
+            .with(4, DynamicProtoMessage::new().with(1, "tester".to_string()));
+        let payload = DynamicProtoMessage::new()
+            .with(1, vec![msg])
+            .encode()
+            .unwrap();
+        let events = decode_guild_messages(&payload).unwrap();
+        let [Event::GuildMessage(event)] = &events[..] else {
+            panic!("except one guild message")
+        };
+        assert_eq!((event.guild_id, event.channel_id), (1001, 2));
+        assert_eq!((event.seq, event.sender_tiny_id), (99, 5566));
+        assert_eq!(event.sender_nick, "tester");
+        assert_eq!(event.chain, chain);
+    }
+
+    #[tokio::test]
+    async fn test_send_channel_message() {
+        let rsp = DynamicProtoMessage::new()
+            .with(1, 0u32)
+            .with(
+                4,
+                DynamicProtoMessage::new().with(2, DynamicProtoMessage::new().with(4, 100u64)),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let chain = MessageChain::new().with("hi");
+        let seq = send_channel_message(&sender, 1001, 2, &chain)
+            .await
+            .unwrap();
+        assert_eq!(seq, 100);
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SEND_CHANNEL_MSG);
+        let msg = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(1)
+            .unwrap()
+            .unwrap();
+        let routing = msg
+            .get_message(1)
+            .unwrap()
+            .unwrap()
+            .get_message(1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(routing.get_u64(1), Some(1001));
+        assert_eq!(routing.get_u64(2), Some(2));
+        let rich = msg
+            .get_message(3)
+            .unwrap()
+            .unwrap()
+            .get_message(1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(MessageChain::from_rich_text(&rich).unwrap(), chain);
+    }
+}
@@ -0,0 +1,95 @@
+//! 群文件与离线文件上传时作为 highway ext 的 `exciting` 系列结构
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/pb/exciting/group.proto)
+
+use std::{io, net::SocketAddr};
+
+use crate::binary::protobuf::DynamicProtoMessage;
+
+const CLIENT_VERSION: &str = "9e9c09dc";
+
+pub struct ExcitingUpload<'a> {
+    pub bus_id: u32,
+    pub sender_uin: u64,
+    pub receiver_uin: u64,
+    /// 离线文件为 0
+    pub group_code: u64,
+    pub app_id: u32,
+    pub file_name: &'a str,
+    pub size: u64,
+    pub md5: &'a [u8; 16],
+    pub sha1: &'a [u8; 20],
+    pub file_id: &'a [u8],
+    pub upload_key: &'a [u8],
+    pub servers: &'a [SocketAddr],
+}
+
+impl ExcitingUpload<'_> {
+    fn entry(&self) -> DynamicProtoMessage {
+        let hosts = self
+            .servers
+            .iter()
+            .map(|addr| {
+                DynamicProtoMessage::new()
+                    .with(
+                        1,
+                        DynamicProtoMessage::new()
+                            .with(1, 1u32)
+                            .with(2, addr.ip().to_string()),
+                    )
+                    .with(2, addr.port() as u32)
+            })
+            .collect::<Vec<_>>();
+        DynamicProtoMessage::new()
+            .with(
+                100,
+                DynamicProtoMessage::new()
+                    .with(1, self.bus_id)
+                    .with(100, self.sender_uin)
+                    .with(200, self.receiver_uin)
+                    .with(400, self.group_code),
+            )
+            .with(
+                200,
+                DynamicProtoMessage::new()
+                    .with(100, self.size)
+                    .with(200, self.md5.to_vec())
+                    .with(300, self.sha1.to_vec())
+                    .with(600, self.file_id.to_vec())
+                    .with(700, self.upload_key.to_vec()),
+            )
+            .with(
+                300,
+                DynamicProtoMessage::new()
+                    .with(100, 2u32)
+                    .with(200, self.app_id.to_string())
+                    .with(300, 2u32)
+                    .with(400, CLIENT_VERSION.to_string())
+                    .with(600, 4u32),
+            )
+            .with(
+                400,
+                DynamicProtoMessage::new().with(100, self.file_name.to_string()),
+            )
+            .with(500, DynamicProtoMessage::new().with(200, hosts))
+    }
+
+    /// `GroupFileUploadExt`
+    pub fn encode_group(&self) -> io::Result<Vec<u8>> {
+        DynamicProtoMessage::new()
+            .with(1, 100u32)
+            .with(2, 1u32)
+            .with(3, 0u32)
+            .with(100, self.entry())
+            .encode()
+    }
+
+    /// `FileUploadExt`
+    pub fn encode_offline(&self) -> io::Result<Vec<u8>> {
+        DynamicProtoMessage::new()
+            .with(1, 100u32)
+            .with(2, 2u32)
+            .with(100, self.entry())
+            .with(200, 1u32)
+            .encode()
+    }
+}
@@ -0,0 +1,83 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::binary::data_writer::DataWriter;
+
+const FRAME_START: u8 = 0x28;
+const FRAME_END: u8 = 0x29;
+/// head 与 body 各自的长度上限，与 MiraiGo 相同
+const MAX_FRAME_LEN: usize = 10 * 1024 * 1024;
+
+/// highway 数据帧 `0x28 | head_len | body_len | head | body | 0x29`
+pub fn encode_frame(head: &[u8], body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut w = DataWriter::new();
+    w.write_data(&FRAME_START)?;
+    w.write_data(&(head.len() as u32))?;
+    w.write_data(&(body.len() as u32))?;
+    w.write_data(&head)?;
+    w.write_data(&body)?;
+    w.write_data(&FRAME_END)?;
+    Ok(w.into_inner())
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    head: &[u8],
+    body: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&encode_frame(head, body)?).await?;
+    writer.flush().await
+}
+
+/// 读取一个数据帧，返回 (head, body)
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    if reader.read_u8().await? != FRAME_START {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "highway frame start mismatch",
+        ));
+    }
+    let head_len = reader.read_u32().await? as usize;
+    let body_len = reader.read_u32().await? as usize;
+    if head_len > MAX_FRAME_LEN || body_len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "highway frame too large",
+        ));
+    }
+
+    let mut head = vec![0u8; head_len];
+    reader.read_exact(&mut head).await?;
+    let mut body = vec![0u8; body_len];
+    reader.read_exact(&mut body).await?;
+
+    if reader.read_u8().await? != FRAME_END {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "highway frame end mismatch",
+        ));
+    }
+    Ok((head, body))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let frame = encode_frame(b"head", b"body").unwrap();
+        let (head, body) = read_frame(&mut frame.as_slice()).await.unwrap();
+        assert_eq!(
+            (head.as_slice(), body.as_slice()),
+            (&b"head"[..], &b"body"[..])
+        );
+
+        let mut huge = vec![FRAME_START];
+        huge.extend_from_slice(&4u32.to_be_bytes());
+        huge.extend_from_slice(&u32::MAX.to_be_bytes());
+        let err = read_frame(&mut huge.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,412 @@
+//! highway (BDH) 大数据通道，图片、语音、视频与群文件均通过此通道上传
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/internal/highway/highway.go)
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::{net::TcpStream, task::JoinSet};
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    utils::crypto::md5_digest,
+};
+
+use self::frame::{read_frame, write_frame};
+
+use super::{NetworkError, NetworkResult};
+
+pub mod exciting;
+pub mod frame;
+
+pub const DEFAULT_BLOCK_SIZE: usize = 256 * 1024;
+const DATA_UP_COMMAND: &str = "PicUp.DataUp";
+
+/// 申请上传（`ImgStore.GroupPicUp`、`LongConn.OffPicUp`、`PttStore` 等）的响应中与 highway 相关的部分
+#[derive(Debug, Clone, Default)]
+pub struct UploadTicket {
+    pub servers: Vec<SocketAddr>,
+    pub ukey: Vec<u8>,
+    /// 服务器已收到的数据长度，用于续传
+    pub offset: u64,
+}
+
+impl UploadTicket {
+    /// 不同响应中字段编号不同，由调用方给出 `(ip, port, ukey, offset)` 的字段编号
+    pub fn from_proto(rsp: &ProtoReader, fields: (u64, u64, u64, u64)) -> Self {
+        let (ip, port, ukey, offset) = fields;
+        Self {
+            servers: to_socket_addrs(&rsp.get_repeated_u64(ip), &rsp.get_repeated_u64(port)),
+            ukey: rsp.get_bytes(ukey).unwrap_or_default().to_vec(),
+            offset: rsp.get_u64(offset).unwrap_or_default(),
+        }
+    }
+}
+
+/// 服务器下发的 ip 为小端序的 u32
+pub fn to_socket_addrs(ips: &[u64], ports: &[u64]) -> Vec<SocketAddr> {
+    ips.iter()
+        .zip(ports)
+        .map(|(ip, port)| {
+            let ip = Ipv4Addr::from((*ip as u32).to_le_bytes());
+            SocketAddr::V4(SocketAddrV4::new(ip, *port as u16))
+        })
+        .collect()
+}
+
+/// 上传进度回调，参数为已上传长度与总长度
+pub type ProgressFn = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+/// 一次上传任务
+pub struct Transaction {
+    /// 业务类型，如好友图片为 1，群图片为 2
+    pub command_id: u32,
+    pub ticket: Vec<u8>,
+    pub ext: Vec<u8>,
+    pub body: Vec<u8>,
+    pub sum: [u8; 16],
+    pub progress: Option<ProgressFn>,
+}
+
+impl Transaction {
+    pub fn new(command_id: u32, ticket: Vec<u8>, body: Vec<u8>) -> Self {
+        Self {
+            command_id,
+            ticket,
+            ext: Vec::new(),
+            sum: md5_digest(&body),
+            body,
+            progress: None,
+        }
+    }
+
+    pub fn with_ext(mut self, ext: Vec<u8>) -> Self {
+        self.ext = ext;
+        self
+    }
+
+    /// 每个分块上传完成后调用 `progress`，并发上传时调用顺序不保证与偏移一致
+    pub fn with_progress(mut self, progress: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+pub struct HighwaySession {
+    pub uin: u64,
+    pub app_id: u32,
+    /// 部分业务（如头像）直接使用登录下发的 sig 作为 ticket
+    pub sig_session: Vec<u8>,
+    /// 登录后由 `ConfigPushSvc.PushReq` 下发的默认服务器，用于不经过申请上传的业务
+    pub servers: Vec<SocketAddr>,
+    pub block_size: usize,
+    /// 并发上传的连接数
+    pub concurrency: usize,
+    seq: Arc<AtomicU32>,
+}
+
+impl HighwaySession {
+    pub fn new(uin: u64, app_id: u32, sig_session: Vec<u8>) -> Self {
+        Self {
+            uin,
+            app_id,
+            sig_session,
+            servers: Vec::new(),
+            block_size: DEFAULT_BLOCK_SIZE,
+            concurrency: 1,
+            seq: Arc::new(AtomicU32::new(rand::random::<u16>() as u32)),
+        }
+    }
+
+    /// 依次尝试各服务器上传，已经完成的分块在切换服务器后不会重传
+    ///
+    /// `offset` 为服务器已经收到的长度，返回最后一个非空的 `rsp_extendinfo`
+    pub async fn upload(
+        &self,
+        servers: &[SocketAddr],
+        trans: Transaction,
+        offset: u64,
+    ) -> NetworkResult<Vec<u8>> {
+        let block_size = self.block_size.max(1);
+        let blocks = (offset as usize..trans.body.len())
+            .step_by(block_size)
+            .collect::<Vec<_>>();
+        let state = Arc::new(UploadState {
+            head: HeadTemplate {
+                uin: self.uin.to_string(),
+                app_id: self.app_id,
+                seq: Arc::clone(&self.seq),
+            },
+            trans,
+            block_size,
+            done: Mutex::new(vec![false; blocks.len()]),
+            blocks,
+            next: AtomicUsize::new(0),
+            uploaded: AtomicU64::new(offset),
+            ext: Mutex::new(Vec::new()),
+        });
+
+        let mut last_err = NetworkError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no highway server available",
+        ));
+        for addr in servers {
+            state.next.store(0, Ordering::SeqCst);
+            let mut workers = JoinSet::new();
+            for _ in 0..self.concurrency.clamp(1, state.blocks.len().max(1)) {
+                workers.spawn(upload_worker(*addr, Arc::clone(&state)));
+            }
+
+            let mut failed = None;
+            while let Some(res) = workers.join_next().await {
+                match res {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => failed = Some(err),
+                    Err(err) => failed = Some(io::Error::other(err).into()),
+                }
+            }
+            match failed {
+                None => return Ok(state.ext.lock().unwrap().clone()),
+                Some(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+#[derive(Clone)]
+struct HeadTemplate {
+    uin: String,
+    app_id: u32,
+    seq: Arc<AtomicU32>,
+}
+
+struct UploadState {
+    head: HeadTemplate,
+    trans: Transaction,
+    block_size: usize,
+    blocks: Vec<usize>,
+    done: Mutex<Vec<bool>>,
+    next: AtomicUsize,
+    uploaded: AtomicU64,
+    ext: Mutex<Vec<u8>>,
+}
+
+impl UploadState {
+    /// 取出下一个尚未完成的分块
+    fn take_block(&self) -> Option<usize> {
+        loop {
+            let idx = self.next.fetch_add(1, Ordering::SeqCst);
+            if idx >= self.blocks.len() {
+                return None;
+            }
+            if !self.done.lock().unwrap()[idx] {
+                return Some(idx);
+            }
+        }
+    }
+
+    fn build_head(&self, offset: usize, block: &[u8]) -> io::Result<Vec<u8>> {
+        let trans = &self.trans;
+        let seq = self.head.seq.fetch_add(1, Ordering::SeqCst);
+        DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, 1u32)
+                    .with(2, self.head.uin.clone())
+                    .with(3, DATA_UP_COMMAND.to_string())
+                    .with(4, seq)
+                    .with(6, self.head.app_id)
+                    .with(7, 4096u32)
+                    .with(8, trans.command_id)
+                    .with(10, 2052u32),
+            )
+            .with(
+                2,
+                DynamicProtoMessage::new()
+                    .with(2, trans.body.len() as u64)
+                    .with(3, offset as u64)
+                    .with(4, block.len() as u32)
+                    .with(6, trans.ticket.clone())
+                    .with(8, md5_digest(block).to_vec())
+                    .with(9, trans.sum.to_vec()),
+            )
+            .with(3, trans.ext.clone())
+            .with(4, 0u64)
+            .encode()
+    }
+}
+
+async fn upload_worker(addr: SocketAddr, state: Arc<UploadState>) -> NetworkResult<()> {
+    let mut conn = None;
+    while let Some(idx) = state.take_block() {
+        let conn = match &mut conn {
+            Some(conn) => conn,
+            None => conn.insert(TcpStream::connect(addr).await?),
+        };
+        let offset = state.blocks[idx];
+        let end = (offset + state.block_size).min(state.trans.body.len());
+        let block = &state.trans.body[offset..end];
+
+        write_frame(conn, &state.build_head(offset, block)?, block).await?;
+        let (head, _) = read_frame(conn).await?;
+        let rsp = ProtoReader::decode(&head)?;
+        let code = rsp.get_i64(3).unwrap_or_default() as i32;
+        if code != 0 {
+            return Err(NetworkError::Server {
+                command: DATA_UP_COMMAND.to_string(),
+                code,
+                message: String::new(),
+            });
+        }
+        if let Some(ext) = rsp.get_bytes(7).filter(|e| !e.is_empty()) {
+            *state.ext.lock().unwrap() = ext.to_vec();
+        }
+        state.done.lock().unwrap()[idx] = true;
+        let uploaded = state
+            .uploaded
+            .fetch_add(block.len() as u64, Ordering::SeqCst)
+            + block.len() as u64;
+        if let Some(progress) = &state.trans.progress {
+            progress(uploaded, state.trans.body.len() as u64);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// 本地模拟的 highway 服务器，按收到的偏移拼接数据并校验分块 md5
+    ///
+    /// 收到 `fail_after` 个分块后断开所有后续连接
+    pub async fn stand_in(fail_after: Option<usize>) -> (SocketAddr, Arc<Mutex<Vec<u8>>>) {
+        stand_in_with(fail_after, b"done".to_vec()).await
+    }
+
+    /// 与 [`stand_in`] 相同，每个分块都回复指定的 `rsp_extendinfo`
+    pub async fn stand_in_with(
+        fail_after: Option<usize>,
+        extend_info: Vec<u8>,
+    ) -> (SocketAddr, Arc<Mutex<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let count = Arc::new(AtomicUsize::new(0));
+
+        let buf = Arc::clone(&received);
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let buf = Arc::clone(&buf);
+                let count = Arc::clone(&count);
+                let extend_info = extend_info.clone();
+                tokio::spawn(async move {
+                    while let Ok((head, body)) = read_frame(&mut conn).await {
+                        if fail_after.is_some_and(|n| count.fetch_add(1, Ordering::SeqCst) >= n) {
+                            break;
+                        }
+                        let head = ProtoReader::decode(&head).unwrap();
+                        let seg = head.get_message(2).unwrap().unwrap();
+                        assert_eq!(seg.get_bytes(8).unwrap(), md5_digest(&body));
+                        let offset = seg.get_u64(3).unwrap() as usize;
+                        {
+                            let mut buf = buf.lock().unwrap();
+                            if buf.len() < offset + body.len() {
+                                buf.resize(offset + body.len(), 0);
+                            }
+                            buf[offset..offset + body.len()].copy_from_slice(&body);
+                        }
+
+                        let rsp = DynamicProtoMessage::new()
+                            .with(3, 0u32)
+                            .with(7, extend_info.clone())
+                            .encode()
+                            .unwrap();
+                        write_frame(&mut conn, &rsp, &[]).await.unwrap();
+                    }
+                });
+            }
+        });
+        (addr, received)
+    }
+
+    fn test_data(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_upload() {
+        let (addr, received) = stand_in(None).await;
+        let mut session = HighwaySession::new(12345, 537066738, Vec::new());
+        session.block_size = 1000;
+        let data = test_data(4500);
+
+        let trans = Transaction::new(2, b"ukey".to_vec(), data.clone());
+        let ext = session.upload(&[addr], trans, 0).await.unwrap();
+        assert_eq!(ext, b"done");
+        assert_eq!(*received.lock().unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_upload() {
+        let (addr, received) = stand_in(None).await;
+        let mut session = HighwaySession::new(12345, 537066738, Vec::new());
+        session.block_size = 100;
+        session.concurrency = 4;
+        let data = test_data(4567);
+
+        let trans = Transaction::new(2, b"ukey".to_vec(), data.clone());
+        session.upload(&[addr], trans, 0).await.unwrap();
+        assert_eq!(*received.lock().unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_resume_on_next_server() {
+        let (broken, first) = stand_in(Some(2)).await;
+        let (addr, second) = stand_in(None).await;
+        let mut session = HighwaySession::new(12345, 537066738, Vec::new());
+        session.block_size = 1000;
+        let data = test_data(4500);
+
+        let trans = Transaction::new(2, b"ukey".to_vec(), data.clone());
+        session.upload(&[broken, addr], trans, 0).await.unwrap();
+
+        let mut received = first.lock().unwrap().clone();
+        assert_eq!(received.len(), 2000);
+        let rest = second.lock().unwrap();
+        assert!(rest[..2000].iter().all(|b| *b == 0));
+        received.extend_from_slice(&rest[2000..]);
+        assert_eq!(received, data);
+    }
+
+    #[tokio::test]
+    async fn test_upload_from_offset() {
+        let (addr, received) = stand_in(None).await;
+        let mut session = HighwaySession::new(12345, 537066738, Vec::new());
+        session.block_size = 1000;
+        let data = test_data(2500);
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let progress = Arc::clone(&reported);
+        let trans = Transaction::new(2, b"ukey".to_vec(), data.clone())
+            .with_progress(move |done, total| progress.lock().unwrap().push((done, total)));
+        session.upload(&[addr], trans, 1500).await.unwrap();
+        assert_eq!(received.lock().unwrap()[1500..], data[1500..]);
+        assert_eq!(*reported.lock().unwrap(), vec![(2500, 2500)]);
+    }
+
+    #[test]
+    fn test_socket_addr() {
+        let addrs = to_socket_addrs(&[0x0100007f], &[8080]);
+        assert_eq!(addrs[0], "127.0.0.1:8080".parse().unwrap());
+    }
+}
@@ -0,0 +1,384 @@
+//! 群消息历史 `MessageSvc.PbGetGroupMsg` 与好友漫游消息 `MessageSvc.PbGetRoamMsg`
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/group_msg.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    message::MessageChain,
+};
+
+use super::{send_uni_request, NetworkError, NetworkResult, SsoSender};
+
+pub const CMD_GET_GROUP_MSG: &str = "MessageSvc.PbGetGroupMsg";
+pub const CMD_GET_ROAM_MSG: &str = "MessageSvc.PbGetRoamMsg";
+
+/// 单次请求的默认消息数量
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// 拉取到的一条历史消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryMessage {
+    pub seq: u64,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+    pub sender_uin: u64,
+    /// 群消息为群名片，为空时为昵称
+    pub sender_name: String,
+    pub chain: MessageChain,
+}
+
+/// 分页向前拉取时的终止条件，早于边界的消息不会返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryBound {
+    None,
+    /// 拉取到该 seq（含）为止
+    Seq(u64),
+    /// 拉取到该时间（含）为止
+    Time(u32),
+}
+
+impl HistoryBound {
+    fn contains(&self, msg: &HistoryMessage) -> bool {
+        match self {
+            HistoryBound::None => true,
+            HistoryBound::Seq(seq) => msg.seq >= *seq,
+            HistoryBound::Time(time) => msg.time >= *time,
+        }
+    }
+}
+
+fn check_result(command: &str, rsp: &ProtoReader) -> NetworkResult<()> {
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: rsp.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 解析 `msg_comm.Msg`
+pub(crate) fn decode_message(msg: &ProtoReader) -> io::Result<HistoryMessage> {
+    let head = msg.get_message(1)?.unwrap_or_default();
+    let rich = msg
+        .get_message(3)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .unwrap_or_default();
+    let sender_name = head
+        .get_message(9)?
+        .and_then(|g| g.get_string(4))
+        .filter(|n| !n.is_empty())
+        .or_else(|| head.get_string(14))
+        .unwrap_or_default();
+    Ok(HistoryMessage {
+        seq: head.get_u64(5).unwrap_or_default(),
+        time: head.get_u64(6).unwrap_or_default() as u32,
+        sender_uin: head.get_u64(1).unwrap_or_default(),
+        sender_name,
+        chain: MessageChain::from_rich_text(&rich)?,
+    })
+}
+
+pub fn build_get_group_msg(group_code: u64, begin_seq: u64, end_seq: u64) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, group_code)
+        .with(2, begin_seq)
+        .with(3, end_seq)
+        .with(6, false)
+        .encode()
+}
+
+/// 解析 `GetGroupMsgResp`，消息按 seq 升序
+pub fn decode_get_group_msg(payload: &[u8]) -> NetworkResult<Vec<HistoryMessage>> {
+    let rsp = ProtoReader::decode(payload)?;
+    check_result(CMD_GET_GROUP_MSG, &rsp)?;
+    let mut msgs = rsp
+        .get_repeated_message(6)?
+        .iter()
+        .map(decode_message)
+        .collect::<io::Result<Vec<_>>>()?;
+    msgs.sort_by_key(|m| m.seq);
+    Ok(msgs)
+}
+
+/// 获取 `from_seq` 及之前的至多 `count` 条群消息
+pub async fn get_group_history<S: SsoSender>(
+    sender: &S,
+    group_code: u64,
+    from_seq: u64,
+    count: u32,
+) -> NetworkResult<Vec<HistoryMessage>> {
+    let begin_seq = from_seq.saturating_sub(count.max(1) as u64 - 1).max(1);
+    let body = build_get_group_msg(group_code, begin_seq, from_seq)?;
+    let rsp = send_uni_request(sender, CMD_GET_GROUP_MSG, &body).await?;
+    decode_get_group_msg(&rsp)
+}
+
+/// 一页漫游消息，下一页需要带上其中的 `last_time` 与 `random`
+#[derive(Debug, Clone, Default)]
+pub struct RoamPage {
+    pub messages: Vec<HistoryMessage>,
+    pub last_time: u32,
+    pub random: u64,
+}
+
+pub fn build_get_roam_msg(
+    peer_uin: u64,
+    last_time: u32,
+    random: u64,
+    count: u32,
+) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, peer_uin)
+        .with(2, last_time)
+        .with(3, random)
+        .with(4, count)
+        .with(5, 1u32)
+        .with(7, vec![0u8; 16])
+        .with(8, 1u32)
+        .with(10, 1u32)
+        .encode()
+}
+
+/// 解析 `PbGetRoamMsgResp`，消息按时间升序
+pub fn decode_get_roam_msg(payload: &[u8]) -> NetworkResult<RoamPage> {
+    let rsp = ProtoReader::decode(payload)?;
+    check_result(CMD_GET_ROAM_MSG, &rsp)?;
+    let mut messages = rsp
+        .get_repeated_message(6)?
+        .iter()
+        .map(decode_message)
+        .collect::<io::Result<Vec<_>>>()?;
+    messages.sort_by_key(|m| (m.time, m.seq));
+    Ok(RoamPage {
+        messages,
+        last_time: rsp.get_u64(4).unwrap_or_default() as u32,
+        random: rsp.get_u64(5).unwrap_or_default(),
+    })
+}
+
+/// 获取与好友的漫游消息，`last_time` 为 0 时从最新的消息开始
+pub async fn get_roam_messages<S: SsoSender>(
+    sender: &S,
+    peer_uin: u64,
+    last_time: u32,
+    random: u64,
+    count: u32,
+) -> NetworkResult<RoamPage> {
+    let body = build_get_roam_msg(peer_uin, last_time, random, count)?;
+    let rsp = send_uni_request(sender, CMD_GET_ROAM_MSG, &body).await?;
+    decode_get_roam_msg(&rsp)
+}
+
+/// 从给定 seq 向前逐页拉取群消息
+///
+/// ```ignore
+/// let mut history =
+///     GroupHistory::new(&client, group_code, latest_seq).with_bound(HistoryBound::Time(since));
+/// while let Some(page) = history.next_page().await? {
+///     archive(page);
+/// }
+/// ```
+pub struct GroupHistory<'a, S> {
+    sender: &'a S,
+    group_code: u64,
+    next_seq: u64,
+    bound: HistoryBound,
+    page_size: u32,
+}
+
+impl<'a, S: SsoSender> GroupHistory<'a, S> {
+    pub fn new(sender: &'a S, group_code: u64, from_seq: u64) -> Self {
+        Self {
+            sender,
+            group_code,
+            next_seq: from_seq,
+            bound: HistoryBound::None,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    pub fn with_bound(mut self, bound: HistoryBound) -> Self {
+        self.bound = bound;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// 下一页更早的消息，页内按 seq 升序，到达边界或没有更多消息时返回 `None`
+    pub async fn next_page(&mut self) -> NetworkResult<Option<Vec<HistoryMessage>>> {
+        if self.next_seq == 0 {
+            return Ok(None);
+        }
+        let mut msgs =
+            get_group_history(self.sender, self.group_code, self.next_seq, self.page_size).await?;
+        let Some(first) = msgs.first() else {
+            self.next_seq = 0;
+            return Ok(None);
+        };
+        self.next_seq = if self.bound.contains(first) {
+            first.seq.saturating_sub(1)
+        } else {
+            0
+        };
+        msgs.retain(|m| self.bound.contains(m));
+        Ok((!msgs.is_empty()).then_some(msgs))
+    }
+}
+
+/// 从最新的消息开始向前逐页拉取好友漫游消息
+pub struct FriendHistory<'a, S> {
+    sender: &'a S,
+    peer_uin: u64,
+    last_time: u32,
+    random: u64,
+    bound: HistoryBound,
+    page_size: u32,
+    done: bool,
+}
+
+impl<'a, S: SsoSender> FriendHistory<'a, S> {
+    pub fn new(sender: &'a S, peer_uin: u64) -> Self {
+        Self {
+            sender,
+            peer_uin,
+            last_time: 0,
+            random: 0,
+            bound: HistoryBound::None,
+            page_size: DEFAULT_PAGE_SIZE,
+            done: false,
+        }
+    }
+
+    pub fn with_bound(mut self, bound: HistoryBound) -> Self {
+        self.bound = bound;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// 下一页更早的消息，页内按时间升序，到达边界或没有更多消息时返回 `None`
+    pub async fn next_page(&mut self) -> NetworkResult<Option<Vec<HistoryMessage>>> {
+        if self.done {
+            return Ok(None);
+        }
+        let mut page = get_roam_messages(
+            self.sender,
+            self.peer_uin,
+            self.last_time,
+            self.random,
+            self.page_size,
+        )
+        .await?;
+        let Some(first) = page.messages.first() else {
+            self.done = true;
+            return Ok(None);
+        };
+        self.done = !self.bound.contains(first)
+            || page.last_time == 0
+            || (page.last_time, page.random) == (self.last_time, self.random);
+        self.last_time = page.last_time;
+        self.random = page.random;
+        page.messages.retain(|m| self.bound.contains(m));
+        Ok((!page.messages.is_empty()).then_some(page.messages))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    fn message(seq: u64, time: u32, text: &str) -> DynamicProtoMessage {
+        let rich = MessageChain::new()
+            .with(text)
+            .to_rich_text(crate::message::MessageTarget::Group(10086));
+        DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, 10010u64)
+                    .with(5, seq)
+                    .with(6, time)
+                    .with(9, DynamicProtoMessage::new().with(4, "card".to_string())),
+            )
+            .with(3, DynamicProtoMessage::new().with(1, rich))
+    }
+
+    fn group_rsp(seqs: std::ops::RangeInclusive<u64>) -> Vec<u8> {
+        let msgs = seqs
+            .rev()
+            .map(|seq| message(seq, 1000 + seq as u32, &format!("msg {}", seq)))
+            .collect::<Vec<_>>();
+        DynamicProtoMessage::new()
+            .with(1, 0u32)
+            .with(6, msgs)
+            .encode()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_group_history_paging() {
+        let sender = MockSender::new(vec![group_rsp(8..=10), group_rsp(5..=7)]);
+        let mut history = GroupHistory::new(&sender, 10086, 10)
+            .with_page_size(3)
+            .with_bound(HistoryBound::Seq(6));
+
+        let page = history.next_page().await.unwrap().unwrap();
+        assert_eq!(
+            page.iter().map(|m| m.seq).collect::<Vec<_>>(),
+            vec![8, 9, 10]
+        );
+        assert_eq!(page[0].sender_name, "card");
+        assert_eq!(page[0].chain, MessageChain::new().with("msg 8"));
+
+        let page = history.next_page().await.unwrap().unwrap();
+        assert_eq!(page.iter().map(|m| m.seq).collect::<Vec<_>>(), vec![6, 7]);
+        assert!(history.next_page().await.unwrap().is_none());
+
+        let (command, body) = sender.sent_body(1);
+        assert_eq!(command, CMD_GET_GROUP_MSG);
+        let req = ProtoReader::decode(&body).unwrap();
+        assert_eq!((req.get_u64(2), req.get_u64(3)), (Some(5), Some(7)));
+    }
+
+    #[tokio::test]
+    async fn test_friend_history_paging() {
+        let page = |last_time: u32, times: &[u32]| {
+            DynamicProtoMessage::new()
+                .with(1, 0u32)
+                .with(4, last_time)
+                .with(5, 42u64)
+                .with(
+                    6,
+                    times
+                        .iter()
+                        .map(|t| message(*t as u64, *t, "hi"))
+                        .collect::<Vec<_>>(),
+                )
+                .encode()
+                .unwrap()
+        };
+        let empty = DynamicProtoMessage::new().with(1, 0u32).encode().unwrap();
+        let sender = MockSender::new(vec![page(100, &[100, 110]), page(90, &[90, 95]), empty]);
+        let mut history = FriendHistory::new(&sender, 10010);
+
+        assert_eq!(history.next_page().await.unwrap().unwrap().len(), 2);
+        assert_eq!(history.next_page().await.unwrap().unwrap()[0].time, 90);
+        assert!(history.next_page().await.unwrap().is_none());
+
+        let (command, body) = sender.sent_body(1);
+        assert_eq!(command, CMD_GET_ROAM_MSG);
+        let req = ProtoReader::decode(&body).unwrap();
+        assert_eq!((req.get_u64(2), req.get_u64(3)), (Some(100), Some(42)));
+    }
+}
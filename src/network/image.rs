@@ -0,0 +1,442 @@
+//! 图片上传与下载，上传时服务器已存在相同 md5 的图片则跳过上传
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/image.go)
+
+use std::{io, path::Path};
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    message::{
+        image::{Image, ImageInfo, ImageKind},
+        MessageTarget,
+    },
+    utils::crypto::md5_digest,
+};
+
+use super::{
+    download,
+    highway::{HighwaySession, Transaction, UploadTicket},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_GROUP_PIC_UP: &str = "ImgStore.GroupPicUp";
+pub const CMD_OFF_PIC_UP: &str = "LongConn.OffPicUp";
+pub const CMD_GROUP_PIC_DOWN: &str = "ImgStore.GroupPicDown";
+pub const CMD_OFF_PIC_DOWN: &str = "LongConn.OffPicDown";
+
+/// highway 中的业务类型
+const HIGHWAY_FRIEND_IMAGE: u32 = 1;
+const HIGHWAY_GROUP_IMAGE: u32 = 2;
+
+/// 申请上传图片的结果
+#[derive(Debug, Clone, Default)]
+pub struct ImageUploadApply {
+    /// 服务器已有此图片，无需上传
+    pub exists: bool,
+    pub ticket: UploadTicket,
+    pub file_id: u64,
+    pub res_id: String,
+}
+
+/// `D388ReqBody.tryupImgReq` 中的一项，频道图片在此基础上附加频道信息
+pub(crate) fn group_pic_try_up(
+    uin: u64,
+    group_code: u64,
+    md5: &[u8; 16],
+    size: u32,
+    info: &ImageInfo,
+) -> DynamicProtoMessage {
+    let name = format!("{}.{}", hex::encode_upper(md5), info.image_type.extension());
+    DynamicProtoMessage::new()
+        .with(1, group_code)
+        .with(2, uin)
+        .with(3, 0u32)
+        .with(4, md5.to_vec())
+        .with(5, size)
+        .with(6, name)
+        .with(7, 5u32)
+        .with(8, 9u32)
+        .with(9, 1u32)
+        .with(10, info.width)
+        .with(11, info.height)
+        .with(12, info.image_type.code())
+        .with(13, "8.2.7.4410".to_string())
+        .with(15, 1006u32)
+}
+
+pub(crate) fn build_pic_up_req(try_up: DynamicProtoMessage) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 3u32)
+        .with(2, 1u32)
+        .with(3, vec![try_up])
+        .encode()
+}
+
+pub fn build_group_pic_up(
+    uin: u64,
+    group_code: u64,
+    md5: &[u8; 16],
+    size: u32,
+    info: &ImageInfo,
+) -> io::Result<Vec<u8>> {
+    build_pic_up_req(group_pic_try_up(uin, group_code, md5, size, info))
+}
+
+/// 解析 `D388RspBody`
+pub fn decode_group_pic_up(payload: &[u8]) -> NetworkResult<ImageUploadApply> {
+    let rsp = ProtoReader::decode(payload)?;
+    let try_up = rsp.get_repeated_message(3)?.pop().unwrap_or_default();
+    check_result(CMD_GROUP_PIC_UP, &try_up, (2, 3))?;
+    Ok(ImageUploadApply {
+        exists: try_up.get_u64(4).unwrap_or_default() != 0,
+        ticket: UploadTicket::from_proto(&try_up, (6, 7, 8, 10)),
+        file_id: try_up.get_u64(9).unwrap_or_default(),
+        res_id: String::new(),
+    })
+}
+
+pub fn build_off_pic_up(
+    uin: u64,
+    target: u64,
+    md5: &[u8; 16],
+    size: u32,
+    info: &ImageInfo,
+) -> io::Result<Vec<u8>> {
+    let name = format!("{}.{}", hex::encode_upper(md5), info.image_type.extension());
+    DynamicProtoMessage::new()
+        .with(1, 1u32)
+        .with(
+            2,
+            vec![DynamicProtoMessage::new()
+                .with(1, uin)
+                .with(2, target)
+                .with(4, md5.to_vec())
+                .with(5, size)
+                .with(6, name)
+                .with(7, 5u32)
+                .with(8, 9u32)
+                .with(12, 1u32)
+                .with(13, 1u32)
+                .with(14, info.width)
+                .with(15, info.height)
+                .with(16, info.image_type.code())
+                .with(17, "8.2.7.4410".to_string())],
+        )
+        .with(10, 3u32)
+        .encode()
+}
+
+/// 解析 `cmd0x352.RspBody`
+pub fn decode_off_pic_up(payload: &[u8]) -> NetworkResult<ImageUploadApply> {
+    let rsp = ProtoReader::decode(payload)?;
+    let try_up = rsp.get_repeated_message(2)?.pop().unwrap_or_default();
+    check_result(CMD_OFF_PIC_UP, &try_up, (3, 4))?;
+    Ok(ImageUploadApply {
+        exists: try_up.get_u64(5).unwrap_or_default() != 0,
+        ticket: UploadTicket::from_proto(&try_up, (7, 8, 9, 12)),
+        file_id: 0,
+        res_id: try_up.get_string(10).unwrap_or_default(),
+    })
+}
+
+fn check_result(command: &str, rsp: &ProtoReader, fields: (u64, u64)) -> NetworkResult<()> {
+    match rsp.get_i64(fields.0).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: rsp.get_string(fields.1).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 上传图片并得到可以放入 [`MessageChain`](crate::message::MessageChain) 的图片元素
+pub async fn upload_image<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: MessageTarget,
+    data: Vec<u8>,
+) -> NetworkResult<Image> {
+    let info = ImageInfo::detect(&data)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported image format"))?;
+    let md5 = md5_digest(&data);
+    let size = data.len() as u32;
+
+    let (apply, command_id, kind) = match target {
+        MessageTarget::Group(code) => {
+            let req = build_group_pic_up(highway.uin, code, &md5, size, &info)?;
+            let rsp = send_uni_request(sender, CMD_GROUP_PIC_UP, &req).await?;
+            (
+                decode_group_pic_up(&rsp)?,
+                HIGHWAY_GROUP_IMAGE,
+                ImageKind::Group,
+            )
+        }
+        MessageTarget::Friend(uin) => {
+            let req = build_off_pic_up(highway.uin, uin, &md5, size, &info)?;
+            let rsp = send_uni_request(sender, CMD_OFF_PIC_UP, &req).await?;
+            (
+                decode_off_pic_up(&rsp)?,
+                HIGHWAY_FRIEND_IMAGE,
+                ImageKind::Friend,
+            )
+        }
+    };
+
+    if !apply.exists {
+        let trans = Transaction::new(command_id, apply.ticket.ukey.clone(), data);
+        highway
+            .upload(&apply.ticket.servers, trans, apply.ticket.offset)
+            .await?;
+    }
+
+    let mut image = Image::new(md5, size, info, kind);
+    image.file_id = apply.file_id;
+    image.res_id = apply.res_id;
+    Ok(image)
+}
+
+pub async fn upload_image_file<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: MessageTarget,
+    path: impl AsRef<Path>,
+) -> NetworkResult<Image> {
+    let data = tokio::fs::read(path).await?;
+    upload_image(sender, highway, target, data).await
+}
+
+pub fn build_group_pic_down(uin: u64, group_code: u64, image: &Image) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 3u32)
+        .with(2, 2u32)
+        .with(
+            4,
+            vec![DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, uin)
+                .with(3, image.file_id)
+                .with(4, image.md5.to_vec())
+                .with(5, 6u32)
+                .with(6, 3u32)
+                .with(7, 5u32)
+                .with(8, 9u32)
+                .with(10, 1u32)
+                .with(13, image.size)
+                .with(14, 1u32)],
+        )
+        .encode()
+}
+
+/// 解析 `D388RspBody.getimg_url_rsp`，返回原图地址
+pub fn decode_group_pic_down(payload: &[u8]) -> NetworkResult<String> {
+    let rsp = ProtoReader::decode(payload)?;
+    let url_rsp = rsp.get_repeated_message(4)?.pop().unwrap_or_default();
+    check_result(CMD_GROUP_PIC_DOWN, &url_rsp, (3, 4))?;
+    Ok(join_down_url(
+        url_rsp.get_string(11),
+        url_rsp.get_string(13),
+        url_rsp.get_repeated_bytes(7).first(),
+    ))
+}
+
+pub fn build_off_pic_down(uin: u64, image: &Image) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 2u32)
+        .with(
+            3,
+            vec![DynamicProtoMessage::new()
+                .with(1, uin)
+                .with(3, image.res_id.clone())
+                .with(4, 6u32)
+                .with(5, 3u32)
+                .with(6, 5u32)
+                .with(7, 9u32)
+                .with(8, 1u32)],
+        )
+        .encode()
+}
+
+/// 解析 `cmd0x352.RspBody.getimg_url_rsp`，返回原图地址
+pub fn decode_off_pic_down(payload: &[u8]) -> NetworkResult<String> {
+    let rsp = ProtoReader::decode(payload)?;
+    let url_rsp = rsp.get_repeated_message(3)?.pop().unwrap_or_default();
+    check_result(CMD_OFF_PIC_DOWN, &url_rsp, (3, 4))?;
+    Ok(join_down_url(
+        url_rsp.get_string(12),
+        url_rsp.get_string(11),
+        url_rsp.get_repeated_bytes(6).first(),
+    ))
+}
+
+fn join_down_url(domain: Option<String>, para: Option<String>, full: Option<&&[u8]>) -> String {
+    match (domain, para) {
+        (Some(domain), Some(para)) if !domain.is_empty() => format!("https://{}{}", domain, para),
+        _ => full
+            .map(|u| String::from_utf8_lossy(u).to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// 查询图片的原图地址
+///
+/// 收到的图片通常已携带下载路径，可以直接使用 [`Image::original_url`]，
+/// 仅在路径缺失或失效时需要向服务器查询
+pub async fn query_image_url<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    target: MessageTarget,
+    image: &Image,
+) -> NetworkResult<String> {
+    match target {
+        MessageTarget::Group(code) => {
+            let req = build_group_pic_down(uin, code, image)?;
+            let rsp = send_uni_request(sender, CMD_GROUP_PIC_DOWN, &req).await?;
+            decode_group_pic_down(&rsp)
+        }
+        MessageTarget::Friend(_) => {
+            let req = build_off_pic_down(uin, image)?;
+            let rsp = send_uni_request(sender, CMD_OFF_PIC_DOWN, &req).await?;
+            decode_off_pic_down(&rsp)
+        }
+    }
+}
+
+/// 下载图片，超过 `max_size` 或 md5 不一致时返回错误
+pub async fn download_image(image: &Image, max_size: usize) -> NetworkResult<Vec<u8>> {
+    download(&image.original_url(), max_size, Some(&image.md5)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        message::image::ImageType,
+        network::{
+            highway::test::stand_in,
+            test::{http_stand_in, MockSender},
+        },
+    };
+
+    fn gif() -> Vec<u8> {
+        let mut data = b"GIF89a\x40\x01\xf0\x00".to_vec();
+        data.extend((0..2000).map(|i| i as u8));
+        data
+    }
+
+    #[tokio::test]
+    async fn test_skip_existing_group_image() {
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                3,
+                vec![DynamicProtoMessage::new()
+                    .with(2, 0u32)
+                    .with(4, true)
+                    .with(9, 4396u64)],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+
+        let data = gif();
+        let image = upload_image(
+            &sender,
+            &highway,
+            MessageTarget::Group(114514),
+            data.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(image.file_id, 4396);
+        assert_eq!(image.image_type, ImageType::Gif);
+        assert_eq!((image.width, image.height), (320, 240));
+        assert_eq!(image.md5, md5_digest(&data));
+        assert!(image.image_id.ends_with("}.gif"));
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_GROUP_PIC_UP);
+        let req = ProtoReader::decode(&body).unwrap();
+        let try_up = req.get_message(3).unwrap().unwrap();
+        assert_eq!(try_up.get_u64(1), Some(114514));
+        assert_eq!(try_up.get_bytes(4), Some(&image.md5[..]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_friend_image() {
+        let (addr, received) = stand_in(None).await;
+        let std::net::SocketAddr::V4(v4) = addr else {
+            unreachable!()
+        };
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                2,
+                vec![DynamicProtoMessage::new()
+                    .with(3, 0u32)
+                    .with(5, false)
+                    .with(7, vec![u32::from_le_bytes(v4.ip().octets()) as u64])
+                    .with(8, vec![v4.port() as u64])
+                    .with(9, b"ukey".to_vec())
+                    .with(10, "/12345-abcdef".to_string())],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+
+        let data = gif();
+        let image = upload_image(
+            &sender,
+            &highway,
+            MessageTarget::Friend(10086),
+            data.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(image.res_id, "/12345-abcdef");
+        assert_eq!(*received.lock().unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_download_image() {
+        let data = gif();
+        let info = ImageInfo::detect(&data).unwrap();
+        let mut image = Image::new(md5_digest(&data), 0, info, ImageKind::Group);
+
+        image.url = http_stand_in(data.clone()).await;
+        assert_eq!(download_image(&image, 4096).await.unwrap(), data);
+
+        image.url = http_stand_in(data.clone()).await;
+        assert!(download_image(&image, 1024).await.is_err());
+
+        image.md5 = [0u8; 16];
+        image.url = http_stand_in(data).await;
+        assert!(download_image(&image, 4096).await.is_err());
+    }
+
+    #[test]
+    fn test_decode_group_pic_down() {
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                4,
+                vec![DynamicProtoMessage::new()
+                    .with(3, 0u32)
+                    .with(11, "gchat.qpic.cn".to_string())
+                    .with(13, "/gchatpic_new/1/2-3-AB/0".to_string())],
+            )
+            .encode()
+            .unwrap();
+        assert_eq!(
+            decode_group_pic_down(&rsp).unwrap(),
+            "https://gchat.qpic.cn/gchatpic_new/1/2-3-AB/0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_format() {
+        let sender = MockSender::new(Vec::new());
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+        let res = upload_image(&sender, &highway, MessageTarget::Group(1), b"text".to_vec()).await;
+        assert!(res.is_err());
+        assert!(sender.sent.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,392 @@
+//! 发送与撤回群消息、好友消息，以及群消息推送与拉取消息的解析
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/builders.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    events::{message::GroupMessageEvent, Event},
+    message::{MessageChain, MessageTarget},
+};
+
+use super::{
+    group_notice::decode_member_join, multi_msg::expand_long_message,
+    other_client::decode_self_group_message, send_uni_request, NetworkError, NetworkResult,
+    SsoSender,
+};
+
+pub const CMD_SEND_MSG: &str = "MessageSvc.PbSendMsg";
+pub const CMD_MSG_WITHDRAW: &str = "PbMessageSvc.PbMsgWithDraw";
+pub const CMD_GET_MSG: &str = "MessageSvc.PbGetMsg";
+
+/// 已发送消息的凭据，撤回时需要
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageReceipt {
+    pub target: MessageTarget,
+    pub seq: u32,
+    pub random: u32,
+    /// 发送时间，unix 时间戳
+    pub time: u32,
+}
+
+fn routing_head(target: MessageTarget) -> DynamicProtoMessage {
+    match target {
+        MessageTarget::Friend(uin) => {
+            DynamicProtoMessage::new().with(1, DynamicProtoMessage::new().with(1, uin))
+        }
+        MessageTarget::Group(code) => {
+            DynamicProtoMessage::new().with(2, DynamicProtoMessage::new().with(1, code))
+        }
+    }
+}
+
+pub fn build_send_msg(
+    target: MessageTarget,
+    seq: u32,
+    random: u32,
+    chain: &MessageChain,
+) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, routing_head(target))
+        .with(
+            2,
+            DynamicProtoMessage::new()
+                .with(1, 1u32)
+                .with(2, 0u32)
+                .with(3, 0u32),
+        )
+        .with(
+            3,
+            DynamicProtoMessage::new().with(1, chain.to_rich_text(target)),
+        )
+        .with(4, seq)
+        .with(5, random)
+        .encode()
+}
+
+/// 解析 `PbSendMsgResp`，返回服务器时间
+pub fn decode_send_msg(payload: &[u8]) -> NetworkResult<u32> {
+    let rsp = ProtoReader::decode(payload)?;
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(rsp.get_u64(3).unwrap_or_default() as u32),
+        code => Err(NetworkError::Server {
+            command: CMD_SEND_MSG.to_string(),
+            code,
+            message: rsp.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 发送消息，`seq` 由调用方维护
+///
+/// 群消息的 seq 由服务器重新分配，返回的凭据中仍是 `seq` 参数，不能直接用于撤回；
+/// 真实的值随 `OnlinePush.PbPushGroupMsg` 作为 [`Event::SelfMessage`] 推送回来，
+/// 按 `random` 匹配后替换凭据中的 seq。超长的消息需要先经过
+/// [`pack_long_message`](super::multi_msg::pack_long_message)
+pub async fn send_message<S: SsoSender>(
+    sender: &S,
+    target: MessageTarget,
+    seq: u32,
+    chain: &MessageChain,
+) -> NetworkResult<MessageReceipt> {
+    let random = rand::random::<u32>();
+    let req = build_send_msg(target, seq, random, chain)?;
+    let rsp = send_uni_request(sender, CMD_SEND_MSG, &req).await?;
+    Ok(MessageReceipt {
+        target,
+        seq,
+        random,
+        time: decode_send_msg(&rsp)?,
+    })
+}
+
+/// `PbMsgWithDrawReq`，撤回好友消息时需要 bot 自身的 uin
+pub fn build_msg_withdraw(self_uin: u64, receipt: &MessageReceipt) -> io::Result<Vec<u8>> {
+    match receipt.target {
+        MessageTarget::Group(code) => DynamicProtoMessage::new()
+            .with(
+                2,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 1u32)
+                    .with(2, 0u32)
+                    .with(3, code)
+                    .with(
+                        4,
+                        vec![DynamicProtoMessage::new()
+                            .with(1, receipt.seq)
+                            .with(2, receipt.random)
+                            .with(3, 0u32)],
+                    )],
+            )
+            .encode(),
+        MessageTarget::Friend(uin) => DynamicProtoMessage::new()
+            .with(
+                1,
+                vec![DynamicProtoMessage::new()
+                    .with(
+                        1,
+                        vec![DynamicProtoMessage::new()
+                            .with(1, self_uin)
+                            .with(2, uin)
+                            .with(3, receipt.seq)
+                            .with(4, (1u64 << 56) | receipt.random as u64)
+                            .with(5, receipt.time)
+                            .with(6, receipt.random)],
+                    )
+                    .with(2, 0u32)],
+            )
+            .encode(),
+    }
+}
+
+/// 解析 `PbMsgWithDrawResp`
+pub fn decode_msg_withdraw(target: MessageTarget, payload: &[u8]) -> NetworkResult<()> {
+    let rsp = ProtoReader::decode(payload)?;
+    let field = match target {
+        MessageTarget::Friend(_) => 1,
+        MessageTarget::Group(_) => 2,
+    };
+    let result = rsp.get_repeated_message(field)?.pop().unwrap_or_default();
+    match result.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: CMD_MSG_WITHDRAW.to_string(),
+            code,
+            message: result.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 撤回消息，群消息的凭据需要带有服务器分配的 seq，见 [`send_message`]
+pub async fn recall_message<S: SsoSender>(
+    sender: &S,
+    self_uin: u64,
+    receipt: &MessageReceipt,
+) -> NetworkResult<()> {
+    let req = build_msg_withdraw(self_uin, receipt)?;
+    let rsp = send_uni_request(sender, CMD_MSG_WITHDRAW, &req).await?;
+    decode_msg_withdraw(receipt.target, &rsp)
+}
+
+/// 解析 `OnlinePush.PbPushGroupMsg`，bot 自己发出的消息作为 [`Event::SelfMessage`]，
+/// 长消息只有占位，需要展开时使用 [`receive_group_message`]
+pub fn decode_group_message(self_uin: u64, payload: &[u8]) -> io::Result<Event> {
+    if let Some(event) = decode_self_group_message(self_uin, payload)? {
+        return Ok(event);
+    }
+    let msg = ProtoReader::decode(payload)?
+        .get_message(1)?
+        .unwrap_or_default();
+    let head = msg.get_message(1)?.unwrap_or_default();
+    let group = head.get_message(9)?.unwrap_or_default();
+    let rich = msg
+        .get_message(3)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .unwrap_or_default();
+    let attr = rich.get_message(1)?.unwrap_or_default();
+    Ok(Event::GroupMessage(GroupMessageEvent {
+        group_code: group.get_u64(1).unwrap_or_default(),
+        sender_uin: head.get_u64(1).unwrap_or_default(),
+        sender_name: group.get_string(4).unwrap_or_default(),
+        seq: head.get_u64(5).unwrap_or_default() as u32,
+        random: attr.get_u64(3).unwrap_or_default() as u32,
+        time: head.get_u64(6).unwrap_or_default() as u32,
+        chain: MessageChain::from_rich_text(&rich)?,
+    }))
+}
+
+/// 处理 `OnlinePush.PbPushGroupMsg`，与 [`decode_group_message`] 相同，并下载展开其中的长消息，
+/// 下载失败时保留只含摘要的占位
+pub async fn receive_group_message<S: SsoSender>(
+    sender: &S,
+    self_uin: u64,
+    payload: &[u8],
+) -> NetworkResult<Event> {
+    let mut event = decode_group_message(self_uin, payload)?;
+    let chain = match &mut event {
+        Event::GroupMessage(e) => &mut e.chain,
+        Event::SelfMessage(e) => &mut e.chain,
+        _ => return Ok(event),
+    };
+    if let Ok(expanded) = expand_long_message(sender, chain.clone()).await {
+        *chain = expanded;
+    }
+    Ok(event)
+}
+
+/// 解析 `MessageSvc.PbGetMsg` 拉取到的消息，目前只处理新成员入群，其余类型忽略
+pub fn decode_get_msg_events(payload: &[u8]) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    for pair in ProtoReader::decode(payload)?.get_repeated_message(5)? {
+        for msg in pair.get_repeated_message(4)? {
+            let head = msg.get_message(1)?.unwrap_or_default();
+            events.extend(decode_member_join(&head).map(Event::MemberJoin));
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        message::{
+            forward::{ForwardMessage, LongMessage},
+            MessageElement,
+        },
+        network::{
+            group_notice::MSG_TYPE_MEMBER_JOIN,
+            multi_msg::{build_forward_transmit, test::apply_down_stand_in},
+            test::MockSender,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_send_and_recall_group_message() {
+        let rsp = DynamicProtoMessage::new()
+            .with(1, 0u32)
+            .with(3, 1700000000u32)
+            .encode()
+            .unwrap();
+        let withdraw = DynamicProtoMessage::new()
+            .with(2, vec![DynamicProtoMessage::new().with(1, 0u32)])
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp, withdraw]);
+        let chain = MessageChain::new().with("hello");
+        let receipt = send_message(&sender, MessageTarget::Group(10001), 7, &chain)
+            .await
+            .unwrap();
+        assert_eq!(receipt.time, 1700000000);
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SEND_MSG);
+        let req = ProtoReader::decode(&body).unwrap();
+        let grp = req
+            .get_message(1)
+            .unwrap()
+            .unwrap()
+            .get_message(2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(grp.get_u64(1), Some(10001));
+        assert_eq!(req.get_u64(5), Some(receipt.random as u64));
+
+        recall_message(&sender, 10086, &receipt).await.unwrap();
+        let (command, body) = sender.sent_body(1);
+        assert_eq!(command, CMD_MSG_WITHDRAW);
+        let info = ProtoReader::decode(&body)
+            .unwrap()
+            .get_repeated_message(2)
+            .unwrap()[0]
+            .get_repeated_message(4)
+            .unwrap()
+            .remove(0);
+        assert_eq!(info.get_u64(1), Some(7));
+        assert_eq!(info.get_u64(2), Some(receipt.random as u64));
+    }
+
+    fn group_push(from: u64, chain: &MessageChain) -> Vec<u8> {
+        let mut rich = chain.to_rich_text(MessageTarget::Group(10001));
+        rich.set(1, DynamicProtoMessage::new().with(3, 4242u32));
+        let msg = DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, from)
+                    .with(5, 33u32)
+                    .with(6, 1700000000u32)
+                    .with(
+                        9,
+                        DynamicProtoMessage::new()
+                            .with(1, 10001u64)
+                            .with(4, "card".to_string()),
+                    ),
+            )
+            .with(3, DynamicProtoMessage::new().with(1, rich));
+        DynamicProtoMessage::new().with(1, msg).encode().unwrap()
+    }
+
+    #[test]
+    fn test_decode_group_message() {
+        let chain = MessageChain::new().with("hi");
+        let push = |from: u64| group_push(from, &chain);
+
+        let Event::GroupMessage(event) = decode_group_message(10086, &push(10010)).unwrap() else {
+            panic!("except group message")
+        };
+        assert_eq!((event.group_code, event.sender_uin), (10001, 10010));
+        assert_eq!((event.seq, event.random), (33, 4242));
+        assert_eq!(event.sender_name, "card");
+        assert_eq!(event.chain, chain);
+
+        assert!(matches!(
+            decode_group_message(10086, &push(10086)).unwrap(),
+            Event::SelfMessage(e) if (e.seq, e.random) == (33, 4242)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_receive_long_message() {
+        let placeholder = MessageChain::new().with(MessageElement::LongMessage(LongMessage {
+            res_id: "long-res".to_string(),
+            brief: "长".to_string(),
+        }));
+        let full = MessageChain::new().with("长".repeat(100));
+        let transmit = build_forward_transmit(
+            10001,
+            &ForwardMessage::new().with_node(10010, "card", 1700000000, full.clone()),
+        )
+        .unwrap();
+        let sender = MockSender::new(vec![apply_down_stand_in(transmit).await]);
+        let payload = group_push(10010, &placeholder);
+        let Event::GroupMessage(event) = receive_group_message(&sender, 10086, &payload)
+            .await
+            .unwrap()
+        else {
+            panic!("except group message")
+        };
+        assert_eq!(event.chain, full);
+
+        // 下载失败时保留占位
+        let sender = MockSender::new(vec![]);
+        let Event::GroupMessage(event) = receive_group_message(&sender, 10086, &payload)
+            .await
+            .unwrap()
+        else {
+            panic!("except group message")
+        };
+        assert_eq!(event.chain, placeholder);
+    }
+
+    #[test]
+    fn test_decode_get_msg_events() {
+        let msg = |msg_type: u64| {
+            DynamicProtoMessage::new().with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, 2_147_123_456u64)
+                    .with(3, msg_type)
+                    .with(13, 10010u64)
+                    .with(14, "newbie".to_string()),
+            )
+        };
+        let pair = DynamicProtoMessage::new()
+            .with(2, 2_147_123_456u64)
+            .with(4, vec![msg(166), msg(MSG_TYPE_MEMBER_JOIN)]);
+        let payload = DynamicProtoMessage::new()
+            .with(1, 0u32)
+            .with(5, vec![pair])
+            .encode()
+            .unwrap();
+
+        let events = decode_get_msg_events(&payload).unwrap();
+        assert_eq!(events.len(), 1);
+        let Event::MemberJoin(event) = &events[0] else {
+            panic!("except member join")
+        };
+        assert_eq!((event.group_code, event.member_uin), (157_123_456, 10010));
+        assert_eq!(event.member_nick, "newbie");
+    }
+}
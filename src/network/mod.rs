@@ -0,0 +1,247 @@
+use std::{fmt::Display, future::Future, io};
+
+use crate::{
+    binary::data_writer::DataWriter,
+    utils::crypto::{md5_digest, CryptoError},
+};
+
+use self::oidb::OidbError;
+
+pub mod account;
+pub mod cmd0x346;
+pub mod friend_notice;
+pub mod group_admin;
+pub mod group_file;
+pub mod group_notice;
+pub mod guild;
+pub mod highway;
+pub mod history;
+pub mod image;
+pub mod message;
+pub mod multi_msg;
+pub mod nudge;
+pub mod offline_file;
+pub mod oidb;
+pub mod online_push;
+pub mod other_client;
+pub mod profile;
+pub mod share;
+pub mod system_msg;
+pub mod video;
+pub mod voice;
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    Crypto(CryptoError),
+    Http(reqwest::Error),
+    /// 服务器返回了非零的结果码
+    Server {
+        command: String,
+        code: i32,
+        message: String,
+    },
+    Oidb(OidbError),
+}
+
+pub type NetworkResult<T> = Result<T, NetworkError>;
+
+impl std::error::Error for NetworkError {}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::Io(err) => Display::fmt(err, f),
+            NetworkError::Crypto(err) => Display::fmt(err, f),
+            NetworkError::Http(err) => Display::fmt(err, f),
+            NetworkError::Server {
+                command,
+                code,
+                message,
+            } => write!(
+                f,
+                "{} Failure, code: {}, message: {}",
+                command, code, message
+            ),
+            NetworkError::Oidb(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(err: io::Error) -> Self {
+        NetworkError::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for NetworkError {
+    fn from(err: reqwest::Error) -> Self {
+        NetworkError::Http(err)
+    }
+}
+
+impl From<OidbError> for NetworkError {
+    fn from(err: OidbError) -> Self {
+        NetworkError::Oidb(err)
+    }
+}
+
+impl From<CryptoError> for NetworkError {
+    fn from(err: CryptoError) -> Self {
+        NetworkError::Crypto(err)
+    }
+}
+
+/// SSO 层的发送端，负责加密、分包以及按 seq 匹配响应
+///
+/// 收到的 `packet` 已经是 [`DataWriter::write_in_uni_package`] 打包好的数据，
+/// 返回值为响应包解密后的 body
+pub trait SsoSender {
+    fn session_id(&self) -> &[u8];
+
+    fn send_packet(
+        &self,
+        command: &str,
+        packet: Vec<u8>,
+    ) -> impl Future<Output = NetworkResult<Vec<u8>>> + Send;
+
+    /// 发送服务器不会响应的包，例如对推送的回执
+    fn send_oneway(
+        &self,
+        command: &str,
+        packet: Vec<u8>,
+    ) -> impl Future<Output = NetworkResult<()>> + Send;
+}
+
+/// 将 body 打包为 uni package 发送，并等待响应
+pub async fn send_uni_request<S: SsoSender>(
+    sender: &S,
+    command: &str,
+    body: &[u8],
+) -> NetworkResult<Vec<u8>> {
+    let mut w = DataWriter::new();
+    w.write_in_uni_package(command, sender.session_id(), &[], body)?;
+    sender.send_packet(command, w.into_inner()).await
+}
+
+/// 与 [`send_uni_request`] 相同，但不等待响应
+pub async fn send_uni_oneway<S: SsoSender>(
+    sender: &S,
+    command: &str,
+    body: &[u8],
+) -> NetworkResult<()> {
+    let mut w = DataWriter::new();
+    w.write_in_uni_package(command, sender.session_id(), &[], body)?;
+    sender.send_oneway(command, w.into_inner()).await
+}
+
+/// 下载文件，超过 `max_size` 或 md5 不一致时返回错误
+pub async fn download(
+    url: &str,
+    max_size: usize,
+    md5: Option<&[u8; 16]>,
+) -> NetworkResult<Vec<u8>> {
+    let mut rsp = reqwest::get(url).await?.error_for_status()?;
+    let too_large = || io::Error::new(io::ErrorKind::InvalidData, "file exceeds size limit");
+    if rsp.content_length().unwrap_or_default() as usize > max_size {
+        return Err(too_large().into());
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = rsp.chunk().await? {
+        if data.len() + chunk.len() > max_size {
+            return Err(too_large().into());
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    if let Some(md5) = md5 {
+        if md5_digest(&data) != *md5 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "file md5 mismatch").into());
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// 记录发送的请求，并按顺序返回预设响应
+    pub struct MockSender {
+        pub sent: Mutex<Vec<(String, Vec<u8>)>>,
+        pub responses: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl MockSender {
+        pub fn new(responses: Vec<Vec<u8>>) -> Self {
+            Self {
+                sent: Mutex::new(Vec::new()),
+                responses: Mutex::new(responses),
+            }
+        }
+
+        pub fn sent_body(&self, idx: usize) -> (String, Vec<u8>) {
+            let sent = self.sent.lock().unwrap();
+            let (command, packet) = &sent[idx];
+            (command.clone(), unpack_body(packet))
+        }
+    }
+
+    /// 取出 uni package 中的 body
+    pub fn unpack_body(packet: &[u8]) -> Vec<u8> {
+        let head = u32::from_be_bytes(packet[0..4].try_into().unwrap()) as usize;
+        let body = &packet[head..];
+        let size = u32::from_be_bytes(body[0..4].try_into().unwrap()) as usize;
+        body[4..size].to_vec()
+    }
+
+    impl SsoSender for MockSender {
+        fn session_id(&self) -> &[u8] {
+            &[0x02, 0xb0, 0x5b, 0x8b]
+        }
+
+        async fn send_packet(&self, command: &str, packet: Vec<u8>) -> NetworkResult<Vec<u8>> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((command.to_string(), packet));
+            let mut responses = self.responses.lock().unwrap();
+            if responses.is_empty() {
+                Ok(Vec::new())
+            } else {
+                Ok(responses.remove(0))
+            }
+        }
+
+        async fn send_oneway(&self, command: &str, packet: Vec<u8>) -> NetworkResult<()> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((command.to_string(), packet));
+            Ok(())
+        }
+    }
+
+    /// 只响应一次请求的 http 服务器，返回其地址
+    pub async fn http_stand_in(body: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = conn.read(&mut buf).await.unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            conn.write_all(head.as_bytes()).await.unwrap();
+            conn.write_all(&body).await.unwrap();
+        });
+        format!("http://{}/download", addr)
+    }
+}
@@ -0,0 +1,609 @@
+//! 合并转发与长消息使用的 `MultiMsg` 服务
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/multimsg.go)
+
+use std::{collections::HashMap, io};
+
+use crate::{
+    binary::{
+        protobuf::{DynamicProtoMessage, ProtoReader},
+        utils::{gzip_compress, gzip_uncompress},
+    },
+    message::{
+        forward::{ForwardMessage, ForwardNode, LongMessage},
+        MessageChain, MessageElement, MessageTarget,
+    },
+    utils::crypto::{md5_digest, tea::Tea},
+};
+
+use super::{
+    download,
+    highway::{to_socket_addrs, HighwaySession, Transaction, UploadTicket},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_MULTI_MSG_APPLY_UP: &str = "MultiMsg.ApplyUp";
+pub const CMD_MULTI_MSG_APPLY_DOWN: &str = "MultiMsg.ApplyDown";
+
+/// `MultiReqBody.bu_type`
+pub const BU_TYPE_LONG_MSG: u32 = 1;
+pub const BU_TYPE_FORWARD: u32 = 2;
+
+const HIGHWAY_MULTI_MSG: u32 = 27;
+const BUILD_VER: &str = "8.2.0.1296";
+/// 顶层消息在 `PbMultiMsgTransmit.pb_item_list` 中的文件名
+const ROOT_FILE_NAME: &str = "MultiMsg";
+/// 下载的消息体大小上限
+const MAX_MULTI_MSG_SIZE: usize = 16 * 1024 * 1024;
+/// 编码后的 `RichText` 超过此长度时服务器会丢弃消息，需要改为长消息发送
+pub const LONG_MESSAGE_THRESHOLD: usize = 5000;
+/// 长消息卡片中 brief 的最大字符数
+const LONG_MESSAGE_BRIEF_CHARS: usize = 30;
+
+/// 申请上传的结果
+#[derive(Debug, Clone, Default)]
+pub struct MultiMsgApplyUp {
+    pub res_id: String,
+    pub msg_ukey: Vec<u8>,
+    /// ukey 为 `msg_sig`
+    pub ticket: UploadTicket,
+}
+
+fn multi_req(sub_cmd: u32, bu_type: u32) -> DynamicProtoMessage {
+    DynamicProtoMessage::new()
+        .with(1, sub_cmd)
+        .with(2, 5u32)
+        .with(3, 9u32)
+        .with(4, 3u32)
+        .with(5, BUILD_VER.to_string())
+        .with(8, bu_type)
+        .with(9, 0u32)
+}
+
+pub fn build_multi_apply_up(
+    dst_uin: u64,
+    size: u64,
+    md5: &[u8; 16],
+    bu_type: u32,
+) -> io::Result<Vec<u8>> {
+    multi_req(1, bu_type)
+        .with(
+            6,
+            vec![DynamicProtoMessage::new()
+                .with(1, dst_uin)
+                .with(2, size)
+                .with(3, md5.to_vec())
+                .with(4, 3u32)],
+        )
+        .encode()
+}
+
+fn check_result(command: &str, rsp: &ProtoReader) -> NetworkResult<()> {
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: String::new(),
+        }),
+    }
+}
+
+/// 解析 `MultiRspBody.multimsg_applyup_rsp`
+pub fn decode_multi_apply_up(payload: &[u8]) -> NetworkResult<MultiMsgApplyUp> {
+    let rsp = ProtoReader::decode(payload)?;
+    let up = rsp.get_repeated_message(2)?.pop().unwrap_or_default();
+    check_result(CMD_MULTI_MSG_APPLY_UP, &up)?;
+    Ok(MultiMsgApplyUp {
+        res_id: up.get_string(2).unwrap_or_default(),
+        msg_ukey: up.get_bytes(3).unwrap_or_default().to_vec(),
+        ticket: UploadTicket::from_proto(&up, (4, 5, 10, 7)),
+    })
+}
+
+/// highway 上传的数据，即 `LongReqBody`
+pub fn build_long_req(dst_uin: u64, content: Vec<u8>, msg_ukey: Vec<u8>) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 1u32)
+        .with(2, 5u32)
+        .with(3, 9u32)
+        .with(
+            4,
+            vec![DynamicProtoMessage::new()
+                .with(1, 3u32)
+                .with(2, dst_uin)
+                .with(4, content)
+                .with(5, 2u32)
+                .with(6, msg_ukey)],
+        )
+        .encode()
+}
+
+/// 上传 gzip 压缩后的 `PbMultiMsgTransmit`，返回 resid
+pub async fn upload_multi_msg<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    group_code: u64,
+    transmit: &[u8],
+    bu_type: u32,
+) -> NetworkResult<String> {
+    let content = gzip_compress(transmit.to_vec())?;
+    let req = build_multi_apply_up(
+        group_code,
+        content.len() as u64,
+        &md5_digest(&content),
+        bu_type,
+    )?;
+    let rsp = send_uni_request(sender, CMD_MULTI_MSG_APPLY_UP, &req).await?;
+    let apply = decode_multi_apply_up(&rsp)?;
+
+    let body = build_long_req(group_code, content, apply.msg_ukey)?;
+    let trans = Transaction::new(HIGHWAY_MULTI_MSG, apply.ticket.ukey, body);
+    highway
+        .upload(&apply.ticket.servers, trans, apply.ticket.offset)
+        .await?;
+    Ok(apply.res_id)
+}
+
+pub fn build_multi_apply_down(res_id: &str, bu_type: u32) -> io::Result<Vec<u8>> {
+    multi_req(2, bu_type)
+        .with(
+            7,
+            vec![DynamicProtoMessage::new()
+                .with(1, res_id.as_bytes().to_vec())
+                .with(2, 3u32)],
+        )
+        .encode()
+}
+
+/// 解析 `MultiRspBody.multimsg_applydown_rsp`，返回下载地址与解密用的 key
+pub fn decode_multi_apply_down(payload: &[u8]) -> NetworkResult<(String, Vec<u8>)> {
+    let rsp = ProtoReader::decode(payload)?;
+    let down = rsp.get_repeated_message(3)?.pop().unwrap_or_default();
+    check_result(CMD_MULTI_MSG_APPLY_DOWN, &down)?;
+    let server = to_socket_addrs(&down.get_repeated_u64(4), &down.get_repeated_u64(5))
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no download server"))?;
+    let para = String::from_utf8_lossy(down.get_bytes(2).unwrap_or_default()).to_string();
+    Ok((
+        format!("http://{}{}", server, para),
+        down.get_bytes(3).unwrap_or_default().to_vec(),
+    ))
+}
+
+/// 解析下载到的数据：`0x28 | head 长度 | body 长度 | head | body | 0x29`，
+/// body 为加密的 `LongRspBody`，返回解压后的 `PbMultiMsgTransmit`
+pub fn decode_multi_msg_download(data: &[u8], msg_key: &[u8]) -> NetworkResult<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid multi msg frame");
+    if data.len() < 10 || data[0] != 0x28 {
+        return Err(invalid().into());
+    }
+    let head_len = u32::from_be_bytes(data[1..5].try_into().unwrap()) as usize;
+    let body_len = u32::from_be_bytes(data[5..9].try_into().unwrap()) as usize;
+    let body = data
+        .get(9 + head_len..9 + head_len + body_len)
+        .ok_or_else(invalid)?;
+    let body = Tea::new(msg_key)?.decrypt(body)?;
+
+    let rsp = ProtoReader::decode(&body)?;
+    let down = rsp.get_repeated_message(3)?.pop().unwrap_or_default();
+    check_result(CMD_MULTI_MSG_APPLY_DOWN, &down)?;
+    Ok(gzip_uncompress(
+        down.get_bytes(3).unwrap_or_default().to_vec(),
+    )?)
+}
+
+/// 下载 resid 对应的 `PbMultiMsgTransmit`
+pub async fn download_multi_msg<S: SsoSender>(
+    sender: &S,
+    res_id: &str,
+    bu_type: u32,
+) -> NetworkResult<Vec<u8>> {
+    let req = build_multi_apply_down(res_id, bu_type)?;
+    let rsp = send_uni_request(sender, CMD_MULTI_MSG_APPLY_DOWN, &req).await?;
+    let (url, msg_key) = decode_multi_apply_down(&rsp)?;
+    let data = download(&url, MAX_MULTI_MSG_SIZE, None).await?;
+    decode_multi_msg_download(&data, &msg_key)
+}
+
+fn encode_node(group_code: u64, seq: u32, node: &ForwardNode) -> DynamicProtoMessage {
+    let head = DynamicProtoMessage::new()
+        .with(1, node.sender_uin)
+        .with(3, 82u32)
+        .with(5, seq)
+        .with(6, node.time)
+        .with(7, 0x0100_0000_0000_0000u64 | rand::random::<u32>() as u64)
+        .with(
+            9,
+            DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(4, node.sender_name.as_bytes().to_vec()),
+        )
+        .with(14, node.sender_name.clone())
+        .with(15, DynamicProtoMessage::new().with(2, 1u32));
+    let rich = node.chain.to_rich_text(MessageTarget::Group(group_code));
+    DynamicProtoMessage::new()
+        .with(1, head)
+        .with(3, DynamicProtoMessage::new().with(1, rich))
+}
+
+/// 为嵌套的合并转发分配在 `pb_item_list` 中的文件名
+fn assign_file_names(nodes: &mut [ForwardNode]) {
+    for elem in nodes.iter_mut().flat_map(|n| n.chain.0.iter_mut()) {
+        if let MessageElement::Forward(inner) = elem {
+            if !inner.nodes.is_empty() && inner.file_name.is_empty() {
+                inner.file_name = format!("{:X}", rand::random::<u64>());
+            }
+            assign_file_names(&mut inner.nodes);
+        }
+    }
+}
+
+/// 将节点编码为 `Message` 列表，嵌套的合并转发放入 `items`
+fn encode_nodes(
+    group_code: u64,
+    nodes: &[ForwardNode],
+    items: &mut Vec<(String, Vec<DynamicProtoMessage>)>,
+) -> Vec<DynamicProtoMessage> {
+    for elem in nodes.iter().flat_map(|n| n.chain.iter()) {
+        if let MessageElement::Forward(inner) = elem {
+            if !inner.nodes.is_empty() {
+                let msgs = encode_nodes(group_code, &inner.nodes, items);
+                items.push((inner.file_name.clone(), msgs));
+            }
+        }
+    }
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(seq, node)| encode_node(group_code, seq as u32, node))
+        .collect()
+}
+
+/// 编码为 `PbMultiMsgTransmit`，顶层消息同时放在 `msg` 与名为 `MultiMsg` 的 item 中
+pub fn build_forward_transmit(group_code: u64, forward: &ForwardMessage) -> io::Result<Vec<u8>> {
+    let mut nodes = forward.nodes.clone();
+    assign_file_names(&mut nodes);
+
+    let mut items = vec![(
+        ROOT_FILE_NAME.to_string(),
+        encode_nodes(group_code, &nodes, &mut Vec::new()),
+    )];
+    let root = encode_nodes(group_code, &nodes, &mut items);
+    let mut item_list = Vec::with_capacity(items.len());
+    for (name, msgs) in items {
+        item_list.push(
+            DynamicProtoMessage::new()
+                .with(1, name)
+                .with(2, DynamicProtoMessage::new().with(1, msgs).encode()?),
+        );
+    }
+    DynamicProtoMessage::new()
+        .with(1, root)
+        .with(2, item_list)
+        .encode()
+}
+
+fn decode_node(msg: &ProtoReader) -> io::Result<ForwardNode> {
+    let head = msg.get_message(1)?.unwrap_or_default();
+    let rich = msg
+        .get_message(3)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .unwrap_or_default();
+    let sender_name = head
+        .get_message(9)?
+        .and_then(|g| g.get_string(4))
+        .filter(|n| !n.is_empty())
+        .or_else(|| head.get_string(14))
+        .unwrap_or_default();
+    Ok(ForwardNode {
+        sender_uin: head.get_u64(1).unwrap_or_default(),
+        sender_name,
+        time: head.get_u64(6).unwrap_or_default() as u32,
+        chain: MessageChain::from_rich_text(&rich)?,
+    })
+}
+
+/// 解码节点，并用 `items` 中同名的内容展开嵌套的合并转发；每个文件名只展开一次以避免循环
+fn decode_nodes(
+    msgs: &[ProtoReader],
+    items: &mut HashMap<String, Vec<ProtoReader>>,
+) -> io::Result<Vec<ForwardNode>> {
+    let mut nodes = Vec::with_capacity(msgs.len());
+    for msg in msgs {
+        let mut node = decode_node(msg)?;
+        for elem in node.chain.0.iter_mut() {
+            if let MessageElement::Forward(inner) = elem {
+                if let Some(inner_msgs) = items.remove(&inner.file_name) {
+                    inner.nodes = decode_nodes(&inner_msgs, items)?;
+                    inner.count = inner.nodes.len();
+                }
+            }
+        }
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+/// 解码 `PbMultiMsgTransmit`
+pub fn decode_forward_transmit(data: &[u8]) -> io::Result<Vec<ForwardNode>> {
+    let transmit = ProtoReader::decode(data)?;
+    let mut items = HashMap::new();
+    for item in transmit.get_repeated_message(2)? {
+        let name = item.get_string(1).unwrap_or_default();
+        let msgs = ProtoReader::decode(item.get_bytes(2).unwrap_or_default())?;
+        items.insert(name, msgs.get_repeated_message(1)?);
+    }
+    let root = match items.remove(ROOT_FILE_NAME) {
+        Some(root) => root,
+        None => transmit.get_repeated_message(1)?,
+    };
+    decode_nodes(&root, &mut items)
+}
+
+/// 上传合并转发，返回填入 resid 后可以放入消息链的元素
+pub async fn upload_forward<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    group_code: u64,
+    forward: &ForwardMessage,
+) -> NetworkResult<ForwardMessage> {
+    let transmit = build_forward_transmit(group_code, forward)?;
+    let res_id = upload_multi_msg(sender, highway, group_code, &transmit, BU_TYPE_FORWARD).await?;
+    Ok(ForwardMessage {
+        res_id,
+        file_name: forward.file_name.clone(),
+        count: forward.nodes.len(),
+        nodes: forward.nodes.clone(),
+    })
+}
+
+/// 下载收到的合并转发的内容
+pub async fn download_forward<S: SsoSender>(
+    sender: &S,
+    forward: &ForwardMessage,
+) -> NetworkResult<ForwardMessage> {
+    let transmit = download_multi_msg(sender, &forward.res_id, BU_TYPE_FORWARD).await?;
+    let nodes = decode_forward_transmit(&transmit)?;
+    Ok(ForwardMessage {
+        count: nodes.len(),
+        nodes,
+        ..forward.clone()
+    })
+}
+
+/// 消息编码后是否超过单个数据包的限制
+pub fn is_long_message(chain: &MessageChain, target: MessageTarget) -> io::Result<bool> {
+    Ok(chain.to_rich_text(target).encode()?.len() > LONG_MESSAGE_THRESHOLD)
+}
+
+/// 发送前调用，超过限制的消息上传为长消息并返回只含占位卡片的消息链，否则原样返回
+pub async fn pack_long_message<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: MessageTarget,
+    chain: MessageChain,
+) -> NetworkResult<MessageChain> {
+    if !is_long_message(&chain, target)? {
+        return Ok(chain);
+    }
+    let dst = match target {
+        MessageTarget::Group(code) | MessageTarget::Friend(code) => code,
+    };
+    let brief = chain
+        .summary()
+        .chars()
+        .take(LONG_MESSAGE_BRIEF_CHARS)
+        .collect();
+    let node = ForwardNode {
+        sender_uin: highway.uin,
+        sender_name: String::new(),
+        time: 0,
+        chain,
+    };
+    let transmit = build_forward_transmit(
+        dst,
+        &ForwardMessage {
+            nodes: vec![node],
+            ..Default::default()
+        },
+    )?;
+    let res_id = upload_multi_msg(sender, highway, dst, &transmit, BU_TYPE_LONG_MSG).await?;
+    Ok(MessageChain::new().with(MessageElement::LongMessage(LongMessage { res_id, brief })))
+}
+
+/// 收到消息后调用，含有长消息占位时下载完整内容替换，否则原样返回
+pub async fn expand_long_message<S: SsoSender>(
+    sender: &S,
+    chain: MessageChain,
+) -> NetworkResult<MessageChain> {
+    let res_id = chain.iter().find_map(|e| match e {
+        MessageElement::LongMessage(long) => Some(long.res_id.clone()),
+        _ => None,
+    });
+    let Some(res_id) = res_id else {
+        return Ok(chain);
+    };
+    let transmit = download_multi_msg(sender, &res_id, BU_TYPE_LONG_MSG).await?;
+    let mut nodes = decode_forward_transmit(&transmit)?;
+    match nodes.is_empty() {
+        true => Ok(chain),
+        false => Ok(nodes.swap_remove(0).chain),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::network::{
+        highway::test::stand_in,
+        test::{http_stand_in, MockSender},
+    };
+
+    fn nested_forward() -> ForwardMessage {
+        let inner = ForwardMessage::new().with_node(
+            3,
+            "inner",
+            1700000002,
+            MessageChain::new().with("deep"),
+        );
+        ForwardMessage::new()
+            .with_node(1, "alice", 1700000000, MessageChain::new().with("hello"))
+            .with_node(2, "bob", 1700000001, MessageChain::new().with(inner))
+    }
+
+    #[test]
+    fn test_transmit_round_trip() {
+        let forward = nested_forward();
+        let transmit = build_forward_transmit(114514, &forward).unwrap();
+        let nodes = decode_forward_transmit(&transmit).unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].sender_name, "alice");
+        assert_eq!(nodes[0].time, 1700000000);
+        assert_eq!(nodes[0].chain, MessageChain::new().with("hello"));
+        let MessageElement::Forward(inner) = &nodes[1].chain.0[0] else {
+            panic!("except forward")
+        };
+        assert_eq!(inner.nodes[0].chain.summary(), "deep");
+        assert_eq!(inner.nodes[0].sender_uin, 3);
+    }
+
+    #[tokio::test]
+    async fn test_upload_forward() {
+        let (addr, received) = stand_in(None).await;
+        let SocketAddr::V4(v4) = addr else {
+            unreachable!()
+        };
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                2,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(2, "res-id".to_string())
+                    .with(3, b"ukey".to_vec())
+                    .with(4, vec![u32::from_le_bytes(v4.ip().octets()) as u64])
+                    .with(5, vec![v4.port() as u64])
+                    .with(10, b"sig".to_vec())],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+
+        let forward = upload_forward(&sender, &highway, 114514, &nested_forward())
+            .await
+            .unwrap();
+        assert_eq!(forward.res_id, "res-id");
+        assert!(forward.to_xml().contains("m_resid=\"res-id\""));
+
+        let long_req = ProtoReader::decode(&received.lock().unwrap()).unwrap();
+        let up = long_req.get_message(4).unwrap().unwrap();
+        assert_eq!(up.get_bytes(6), Some(&b"ukey"[..]));
+        let transmit = gzip_uncompress(up.get_bytes(4).unwrap().to_vec()).unwrap();
+        assert_eq!(decode_forward_transmit(&transmit).unwrap().len(), 2);
+    }
+
+    /// 在本地提供 `transmit` 的下载，返回指向它的 `MultiMsg.ApplyDown` 响应
+    pub(crate) async fn apply_down_stand_in(transmit: Vec<u8>) -> Vec<u8> {
+        let key = [7u8; 16];
+        let body = DynamicProtoMessage::new()
+            .with(
+                3,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(3, gzip_compress(transmit).unwrap())],
+            )
+            .encode()
+            .unwrap();
+        let body = Tea::new(&key).unwrap().encrypt(&body).unwrap();
+        let mut frame = vec![0x28];
+        frame.extend((0u32).to_be_bytes());
+        frame.extend((body.len() as u32).to_be_bytes());
+        frame.extend(body);
+        frame.push(0x29);
+
+        let url = http_stand_in(frame).await;
+        let (host, para) = url.trim_start_matches("http://").split_once('/').unwrap();
+        let SocketAddr::V4(v4) = host.parse().unwrap() else {
+            unreachable!()
+        };
+        DynamicProtoMessage::new()
+            .with(
+                3,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(2, format!("/{}", para).into_bytes())
+                    .with(3, key.to_vec())
+                    .with(4, vec![u32::from_le_bytes(v4.ip().octets()) as u64])
+                    .with(5, vec![v4.port() as u64])],
+            )
+            .encode()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_download_forward() {
+        let transmit = build_forward_transmit(1, &nested_forward()).unwrap();
+        let sender = MockSender::new(vec![apply_down_stand_in(transmit).await]);
+
+        let received = ForwardMessage {
+            res_id: "res-id".to_string(),
+            ..Default::default()
+        };
+        let forward = download_forward(&sender, &received).await.unwrap();
+        assert_eq!(forward.count, 2);
+        assert_eq!(forward.nodes[1].sender_name, "bob");
+    }
+
+    #[tokio::test]
+    async fn test_pack_long_message() {
+        let sender = MockSender::new(Vec::new());
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+        let short = MessageChain::new().with("short");
+        let packed = pack_long_message(&sender, &highway, MessageTarget::Group(1), short.clone())
+            .await
+            .unwrap();
+        assert_eq!(packed, short);
+        assert!(sender.sent.lock().unwrap().is_empty());
+
+        let (addr, received) = stand_in(None).await;
+        let SocketAddr::V4(v4) = addr else {
+            unreachable!()
+        };
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                2,
+                vec![DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(2, "long-res".to_string())
+                    .with(4, vec![u32::from_le_bytes(v4.ip().octets()) as u64])
+                    .with(5, vec![v4.port() as u64])],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let long = MessageChain::new().with("长".repeat(LONG_MESSAGE_THRESHOLD));
+        let packed = pack_long_message(&sender, &highway, MessageTarget::Group(1), long.clone())
+            .await
+            .unwrap();
+        let MessageElement::LongMessage(placeholder) = &packed.0[0] else {
+            panic!("except long message")
+        };
+        assert_eq!(placeholder.res_id, "long-res");
+        assert_eq!(placeholder.brief.chars().count(), LONG_MESSAGE_BRIEF_CHARS);
+
+        let (_, body) = sender.sent_body(0);
+        let req = ProtoReader::decode(&body).unwrap();
+        assert_eq!(req.get_u64(8), Some(BU_TYPE_LONG_MSG as u64));
+
+        let long_req = ProtoReader::decode(&received.lock().unwrap()).unwrap();
+        let up = long_req.get_message(4).unwrap().unwrap();
+        let transmit = gzip_uncompress(up.get_bytes(4).unwrap().to_vec()).unwrap();
+        let nodes = decode_forward_transmit(&transmit).unwrap();
+        assert_eq!(nodes[0].chain, long);
+    }
+}
@@ -0,0 +1,128 @@
+//! 戳一戳，发送为 `OidbSvc.0xed3_1`，收到时是群或好友的灰条提示
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/notify.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    events::notice::NudgeEvent,
+};
+
+use super::{oidb::oidb_request_with_name, NetworkResult, SsoSender};
+
+pub const CMD_NUDGE: &str = "OidbSvc.0xed3_1";
+
+const OIDB_NUDGE: u32 = 0xed3;
+const SERVICE_NUDGE: u32 = 1;
+
+/// 戳一戳灰条提示的模板 id
+const NUDGE_TEMPLATES: [u64; 6] = [1132, 1133, 1134, 1135, 1136, 10043];
+
+/// `group_code` 为 `None` 时戳好友
+pub fn build_nudge(group_code: Option<u64>, target: u64) -> io::Result<Vec<u8>> {
+    let body = DynamicProtoMessage::new().with(1, target);
+    match group_code {
+        Some(code) => body.with(2, code),
+        None => body.with(5, target),
+    }
+    .encode()
+}
+
+pub async fn send_group_nudge<S: SsoSender>(
+    sender: &S,
+    group_code: u64,
+    target: u64,
+) -> NetworkResult<()> {
+    let body = build_nudge(Some(group_code), target)?;
+    oidb_request_with_name(sender, CMD_NUDGE, OIDB_NUDGE, SERVICE_NUDGE, body).await?;
+    Ok(())
+}
+
+pub async fn send_friend_nudge<S: SsoSender>(sender: &S, target: u64) -> NetworkResult<()> {
+    let body = build_nudge(None, target)?;
+    oidb_request_with_name(sender, CMD_NUDGE, OIDB_NUDGE, SERVICE_NUDGE, body).await?;
+    Ok(())
+}
+
+/// 解析 `GeneralGrayTipInfo`，不是戳一戳时返回 `None`
+pub fn decode_nudge_gray_tip(
+    group_code: Option<u64>,
+    tip: &ProtoReader,
+) -> io::Result<Option<NudgeEvent>> {
+    if !NUDGE_TEMPLATES.contains(&tip.get_u64(6).unwrap_or_default()) {
+        return Ok(None);
+    }
+    let mut event = NudgeEvent {
+        group_code,
+        ..Default::default()
+    };
+    for param in tip.get_repeated_message(7)? {
+        let value = param.get_string(2).unwrap_or_default();
+        match param.get_string(1).unwrap_or_default().as_str() {
+            "uin_str1" => event.sender_uin = value.parse().unwrap_or_default(),
+            "uin_str2" => event.receiver_uin = value.parse().unwrap_or_default(),
+            "action_str" | "alt_str1" if event.action.is_empty() => event.action = value,
+            "suffix_str" => event.suffix = value,
+            _ => {}
+        }
+    }
+    Ok(Some(event))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    fn param(name: &str, value: &str) -> DynamicProtoMessage {
+        DynamicProtoMessage::new()
+            .with(1, name.to_string())
+            .with(2, value.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_send_nudge() {
+        let rsp = DynamicProtoMessage::new().with(3, 0u32).encode().unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        send_group_nudge(&sender, 10086, 12345).await.unwrap();
+
+        let (command, sent) = sender.sent_body(0);
+        assert_eq!(command, "OidbSvc.0xed3_1");
+        let pkg = ProtoReader::decode(&sent).unwrap();
+        assert_eq!(pkg.get_u64(1), Some(0xed3));
+        let req = ProtoReader::decode(pkg.get_bytes(4).unwrap()).unwrap();
+        assert_eq!(req.get_u64(1), Some(12345));
+        assert_eq!(req.get_u64(2), Some(10086));
+        assert_eq!(req.get_u64(5), None);
+    }
+
+    #[test]
+    fn test_decode_nudge() {
+        let data = DynamicProtoMessage::new()
+            .with(6, 10043u64)
+            .with(
+                7,
+                vec![
+                    param("action_str", "戳了戳"),
+                    param("uin_str1", "10010"),
+                    param("uin_str2", "10086"),
+                    param("suffix_str", "的脸"),
+                ],
+            )
+            .encode()
+            .unwrap();
+        let tip = ProtoReader::decode(&data).unwrap();
+        let event = decode_nudge_gray_tip(Some(1), &tip).unwrap().unwrap();
+        assert_eq!(event.group_code, Some(1));
+        assert_eq!((event.sender_uin, event.receiver_uin), (10010, 10086));
+        assert_eq!(
+            (event.action.as_str(), event.suffix.as_str()),
+            ("戳了戳", "的脸")
+        );
+
+        let other =
+            ProtoReader::decode(&DynamicProtoMessage::new().with(6, 1u64).encode().unwrap())
+                .unwrap();
+        assert!(decode_nudge_gray_tip(None, &other).unwrap().is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! 好友离线文件的上传与下载
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/c2c_processor.go)
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::{
+    binary::protobuf::ProtoReader,
+    events::file::OfflineFileEvent,
+    utils::crypto::{md5_digest, sha1_digest},
+};
+
+use super::{
+    cmd0x346::{
+        build_file_apply_download, build_file_apply_upload_v3, decode_apply_download_rsp,
+        decode_apply_upload_v3_rsp, ApplyUploadReq,
+    },
+    highway::{exciting::ExcitingUpload, HighwaySession, Transaction},
+    send_uni_request, NetworkResult, SsoSender,
+};
+
+pub const CMD_APPLY_UPLOAD_V3: &str = "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3";
+pub const CMD_APPLY_DOWNLOAD: &str = "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_DOWNLOAD-1200";
+
+const HIGHWAY_OFFLINE_FILE: u32 = 69;
+const BUS_ID: u32 = 102;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OfflineFile {
+    pub uuid: Vec<u8>,
+    pub name: String,
+    pub size: u64,
+    pub md5: Vec<u8>,
+    /// 过期时间
+    pub expire_time: u32,
+}
+
+/// 解析 `NotOnlineFile`，`RichText.not_online_file` 与 `SubMsgType0x4` 中均为此结构
+pub fn decode_not_online_file(file: &ProtoReader) -> OfflineFile {
+    OfflineFile {
+        uuid: file.get_bytes(3).unwrap_or_default().to_vec(),
+        md5: file.get_bytes(4).unwrap_or_default().to_vec(),
+        name: file.get_string(5).unwrap_or_default(),
+        size: file.get_u64(6).unwrap_or_default(),
+        expire_time: file.get_u64(55).unwrap_or_default() as u32,
+    }
+}
+
+/// 解析 `msg_type` 为 529、`c2c_cmd` 为 4 的消息内容（`SubMsgType0x4.MsgBody`）
+pub fn decode_offline_file_push(
+    sender_uin: u64,
+    msg_content: &[u8],
+) -> io::Result<Option<OfflineFileEvent>> {
+    let body = ProtoReader::decode(msg_content)?;
+    Ok(body
+        .get_message(1)?
+        .map(|file| decode_not_online_file(&file))
+        .filter(|file| !file.uuid.is_empty())
+        .map(|file| OfflineFileEvent { sender_uin, file }))
+}
+
+/// 上传离线文件，`progress` 的参数为已上传长度与总长度
+///
+/// 返回的 uuid 需要再通过私聊消息发送给对方
+pub async fn upload_offline_file<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: u64,
+    file_name: &str,
+    data: Vec<u8>,
+    progress: impl Fn(u64, u64) + Send + Sync + 'static,
+) -> NetworkResult<OfflineFile> {
+    let size = data.len() as u64;
+    let (md5, sha1) = (md5_digest(&data), sha1_digest(&data));
+    let req = ApplyUploadReq {
+        sender_uin: highway.uin,
+        recver_uin: target,
+        file_type: 0,
+        file_size: size,
+        file_name,
+        md5: &md5,
+    };
+    let body = build_file_apply_upload_v3(rand::random::<u16>() as u32, &req, &sha1)?;
+    let rsp = send_uni_request(sender, CMD_APPLY_UPLOAD_V3, &body).await?;
+    let apply = decode_apply_upload_v3_rsp(CMD_APPLY_UPLOAD_V3, &rsp)?;
+
+    if apply.exists {
+        progress(size, size);
+    } else {
+        let servers = match apply.upload_ip.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, apply.upload_port)],
+            Err(_) => highway.servers.clone(),
+        };
+        let ext = ExcitingUpload {
+            bus_id: BUS_ID,
+            sender_uin: highway.uin,
+            receiver_uin: target,
+            group_code: 0,
+            app_id: highway.app_id,
+            file_name,
+            size,
+            md5: &md5,
+            sha1: &sha1,
+            file_id: &apply.uuid,
+            upload_key: &apply.upload_key,
+            servers: &servers,
+        }
+        .encode_offline()?;
+        let trans = Transaction::new(HIGHWAY_OFFLINE_FILE, highway.sig_session.clone(), data)
+            .with_ext(ext)
+            .with_progress(progress);
+        highway.upload(&servers, trans, apply.uploaded_size).await?;
+    }
+
+    Ok(OfflineFile {
+        uuid: apply.uuid,
+        name: file_name.to_string(),
+        size,
+        md5: md5.to_vec(),
+        expire_time: 0,
+    })
+}
+
+/// 获取收到的离线文件的下载地址
+pub async fn query_offline_file_url<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    file: &OfflineFile,
+) -> NetworkResult<String> {
+    let body = build_file_apply_download(rand::random::<u16>() as u32, uin, &file.uuid)?;
+    let rsp = send_uni_request(sender, CMD_APPLY_DOWNLOAD, &body).await?;
+    decode_apply_download_rsp(CMD_APPLY_DOWNLOAD, &rsp)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        binary::protobuf::DynamicProtoMessage,
+        network::{highway::test::stand_in, test::MockSender},
+    };
+
+    #[tokio::test]
+    async fn test_upload_offline_file() {
+        let (addr, received) = stand_in(None).await;
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                19,
+                DynamicProtoMessage::new()
+                    .with(10, 0u32)
+                    .with(60, addr.ip().to_string())
+                    .with(80, addr.port() as u32)
+                    .with(90, b"file-uuid".to_vec())
+                    .with(100, b"upload-key".to_vec()),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let mut highway = HighwaySession::new(12345, 537066738, Vec::new());
+        highway.block_size = 100;
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let progress = Arc::clone(&reported);
+        let data = vec![5u8; 250];
+        let file = upload_offline_file(
+            &sender,
+            &highway,
+            10086,
+            "report.pdf",
+            data.clone(),
+            move |done, total| progress.lock().unwrap().push((done, total)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(file.uuid, b"file-uuid");
+        assert_eq!(*received.lock().unwrap(), data);
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![(100, 250), (200, 250), (250, 250)]
+        );
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_APPLY_UPLOAD_V3);
+        let req = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(19)
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.get_u64(20), Some(10086));
+        assert_eq!(req.get_string(40).unwrap(), "report.pdf");
+    }
+
+    #[tokio::test]
+    async fn test_offline_file_push_and_url() {
+        let content = DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(3, b"file-uuid".to_vec())
+                    .with(5, "report.pdf".to_string())
+                    .with(6, 4096u64),
+            )
+            .encode()
+            .unwrap();
+        let event = decode_offline_file_push(10086, &content).unwrap().unwrap();
+        assert_eq!(event.sender_uin, 10086);
+        assert_eq!(event.file.name, "report.pdf");
+        assert_eq!(event.file.size, 4096);
+
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                14,
+                DynamicProtoMessage::new().with(10, 0u32).with(
+                    30,
+                    DynamicProtoMessage::new()
+                        .with(30, "sh.ftn.qq.com".to_string())
+                        .with(40, 80u32)
+                        .with(50, "/ftn_handler/abc".to_string()),
+                ),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let url = event.download_url(&sender, 12345).await.unwrap();
+        assert_eq!(url, "http://sh.ftn.qq.com:80/ftn_handler/abc");
+    }
+}
@@ -0,0 +1,119 @@
+//! `OidbSvc.0xXXX_Y` 系列请求的通用封装
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/pb/oidb/oidb.proto)
+
+use std::{fmt::Display, io};
+
+use crate::binary::protobuf::{DynamicProtoMessage, ProtoReader};
+
+use super::{send_uni_request, NetworkError, NetworkResult, SsoSender};
+
+/// `OIDBSSOPkg.result` 非零时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidbError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl std::error::Error for OidbError {}
+
+impl Display for OidbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Oidb Error, code: {}, message: {}",
+            self.code, self.message
+        )
+    }
+}
+
+pub fn oidb_command_name(command: u32, service_type: u32) -> String {
+    format!("OidbSvc.0x{:x}_{}", command, service_type)
+}
+
+pub fn build_oidb_package(command: u32, service_type: u32, body: Vec<u8>) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, command)
+        .with(2, service_type)
+        .with(4, body)
+        .with(6, "android 8.4.1".to_string())
+        .encode()
+}
+
+/// 解析 `OIDBSSOPkg`，返回其中的 bodybuffer
+pub fn decode_oidb_package(payload: &[u8]) -> NetworkResult<Vec<u8>> {
+    let pkg = ProtoReader::decode(payload)?;
+    match pkg.get_i64(3).unwrap_or_default() as i32 {
+        0 => Ok(pkg.get_bytes(4).unwrap_or_default().to_vec()),
+        code => Err(NetworkError::Oidb(OidbError {
+            code,
+            message: pkg.get_string(5).unwrap_or_default(),
+        })),
+    }
+}
+
+/// 发送 `OidbSvc.0x{command}_{service_type}`，成功时返回响应的 bodybuffer
+pub async fn oidb_request<S: SsoSender>(
+    sender: &S,
+    command: u32,
+    service_type: u32,
+    body: Vec<u8>,
+) -> NetworkResult<Vec<u8>> {
+    let name = oidb_command_name(command, service_type);
+    oidb_request_with_name(sender, &name, command, service_type, body).await
+}
+
+/// 与 [`oidb_request`] 相同，但使用不符合 `OidbSvc.0xXXX_Y` 格式的命令名
+pub async fn oidb_request_with_name<S: SsoSender>(
+    sender: &S,
+    command_name: &str,
+    command: u32,
+    service_type: u32,
+    body: Vec<u8>,
+) -> NetworkResult<Vec<u8>> {
+    let pkg = build_oidb_package(command, service_type, body)?;
+    let payload = send_uni_request(sender, command_name, &pkg).await?;
+    decode_oidb_package(&payload)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    #[tokio::test]
+    async fn test_oidb_request() {
+        let rsp = DynamicProtoMessage::new()
+            .with(3, 0u32)
+            .with(4, vec![0x08u8, 0x01])
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let body = oidb_request(&sender, 0x88d, 0, vec![0x10, 0x02])
+            .await
+            .unwrap();
+        assert_eq!(body, vec![0x08, 0x01]);
+
+        let (command, sent) = sender.sent_body(0);
+        assert_eq!(command, "OidbSvc.0x88d_0");
+        let pkg = ProtoReader::decode(&sent).unwrap();
+        assert_eq!(pkg.get_u64(1), Some(0x88d));
+        assert_eq!(pkg.get_u64(2), Some(0));
+        assert_eq!(pkg.get_bytes(4), Some(&[0x10u8, 0x02][..]));
+    }
+
+    #[test]
+    fn test_oidb_error() {
+        let rsp = DynamicProtoMessage::new()
+            .with(3, 1u32)
+            .with(5, "no permission".to_string())
+            .encode()
+            .unwrap();
+        match decode_oidb_package(&rsp) {
+            Err(NetworkError::Oidb(err)) => {
+                assert_eq!(err.code, 1);
+                assert_eq!(err.message, "no permission");
+            }
+            _ => panic!("except oidb error"),
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! `OnlinePush.ReqPush` 与 `OnlinePush.PbPushTransMsg` 推送的群与好友通知
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/online_push.go)
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+};
+
+use crate::{
+    binary::{
+        data_reader::DataReader,
+        jce::{JceStruct, JceValue, RequestPacket},
+        protobuf::ProtoReader,
+    },
+    events::Event,
+};
+
+use super::{
+    friend_notice::{
+        decode_friend_profile_mod, decode_friend_push, SUB_FRIEND_ADDED, SUB_FRIEND_SYNC,
+        SUB_INPUT_STATUS,
+    },
+    group_notice::{
+        decode_group_gray_tip, decode_group_mute, decode_group_profile_mod, decode_trans_data,
+        group_uin_to_code,
+    },
+    nudge::decode_nudge_gray_tip,
+    send_uni_oneway, NetworkResult, SsoSender,
+};
+
+pub const CMD_REQ_PUSH: &str = "OnlinePush.ReqPush";
+pub const CMD_PB_PUSH_TRANS_MSG: &str = "OnlinePush.PbPushTransMsg";
+pub const CMD_RESP_PUSH: &str = "OnlinePush.RespPush";
+
+const REQ_PUSH_KEY: &str = "req";
+const RESP_PUSH_KEY: &str = "resp";
+
+/// 去重缓存默认保留的推送数量
+pub const DEFAULT_DEDUP_CAPACITY: usize = 2048;
+
+/// 群通知
+const MSG_TYPE_GROUP_NOTIFY: i64 = 0x2dc;
+/// 好友通知，内容为 `MsgType0x210`
+const MSG_TYPE_FRIEND_NOTIFY: i64 = 0x210;
+
+/// 0x2dc 中内容为 `NotifyMsgBody` 的子类型
+const GROUP_NOTIFY_PB_TYPES: [u8; 4] = [0x10, 0x11, 0x14, 0x15];
+const GROUP_MUTE: u8 = 0x0c;
+const PROFILE_MOD: i64 = 0x27;
+const FRIEND_GRAY_TIP: i64 = 0x122;
+
+/// `SvcReqPushMsg.vMsgInfos` 中的一条推送
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushMessageInfo {
+    pub from_uin: i64,
+    pub msg_time: i64,
+    pub msg_type: i64,
+    pub msg_seq: i64,
+    pub msg_uid: i64,
+    pub msg_cookies: Vec<u8>,
+    pub v_msg: Vec<u8>,
+}
+
+/// 解码后的 `OnlinePush.ReqPush`
+#[derive(Debug, Clone, Default)]
+pub struct ReqPush {
+    pub request_id: i32,
+    pub uin: i64,
+    pub svrip: i64,
+    /// `SvcReqPushMsg` 中没有，回执 `PbC2CMsgSync` 时使用
+    pub push_token: Vec<u8>,
+    pub infos: Vec<PushMessageInfo>,
+}
+
+pub fn decode_req_push(payload: &[u8]) -> io::Result<ReqPush> {
+    let request = RequestPacket::decode(payload)?;
+    let data = request.data()?;
+    let Some(msg) = data.get(REQ_PUSH_KEY) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing SvcReqPushMsg",
+        ));
+    };
+    let infos = msg
+        .get_struct_list(2)
+        .into_iter()
+        .map(|info| PushMessageInfo {
+            from_uin: info.get_i64(0).unwrap_or_default(),
+            msg_time: info.get_i64(1).unwrap_or_default(),
+            msg_type: info.get_i64(2).unwrap_or_default(),
+            msg_seq: info.get_i64(3).unwrap_or_default(),
+            v_msg: info.get_bytes(6).unwrap_or_default().to_vec(),
+            msg_cookies: info.get_bytes(8).unwrap_or_default().to_vec(),
+            msg_uid: info.get_i64(10).unwrap_or_default(),
+        })
+        .collect();
+    Ok(ReqPush {
+        request_id: request.request_id,
+        uin: msg.get_i64(0).unwrap_or_default(),
+        svrip: msg.get_i64(3).unwrap_or_default(),
+        push_token: Vec::new(),
+        infos,
+    })
+}
+
+/// 构造 `OnlinePush.RespPush`，回执其中的全部推送，否则服务器会在重连后重复推送
+pub fn build_resp_push(push: &ReqPush) -> Vec<u8> {
+    let del_infos = push
+        .infos
+        .iter()
+        .map(|info| {
+            JceValue::Struct(
+                JceStruct::new()
+                    .with(0, info.from_uin)
+                    .with(1, info.msg_time)
+                    .with(2, info.msg_seq as i16)
+                    .with(3, info.msg_cookies.clone()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let resp = JceStruct::new()
+        .with(0, push.uin)
+        .with(1, del_infos)
+        .with(2, push.svrip as i32)
+        .with(3, push.push_token.clone())
+        .with(4, 0u8);
+    RequestPacket::new("OnlinePush", "SvcRespPushMsg")
+        .with_request_id(push.request_id)
+        .with_data(RESP_PUSH_KEY, resp)
+        .encode()
+}
+
+/// 有界的推送去重缓存，key 为 (msgSeq, msgUid, msgTime)
+///
+/// 需要在断线重连之间保留，超过容量时淘汰最早的记录
+#[derive(Debug)]
+pub struct PushDedup {
+    capacity: usize,
+    seen: HashSet<(i64, i64, i64)>,
+    order: VecDeque<(i64, i64, i64)>,
+}
+
+impl Default for PushDedup {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_CAPACITY)
+    }
+}
+
+impl PushDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// 记录一条推送，已经记录过时返回 `false`
+    pub fn insert(&mut self, seq: i64, uid: i64, time: i64) -> bool {
+        let key = (seq, uid, time);
+        if !self.seen.insert(key) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, seq: i64, uid: i64, time: i64) -> bool {
+        self.seen.contains(&(seq, uid, time))
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// 处理 `OnlinePush.ReqPush`：回执全部推送，再解析其中首次收到的推送
+///
+/// 每条推送单独解析，解析失败的推送被跳过且不计入去重，不影响同批的其他推送
+pub async fn process_req_push<S: SsoSender>(
+    sender: &S,
+    dedup: &mut PushDedup,
+    payload: &[u8],
+) -> NetworkResult<Vec<Event>> {
+    let push = decode_req_push(payload)?;
+    send_uni_oneway(sender, CMD_RESP_PUSH, &build_resp_push(&push)).await?;
+
+    let mut events = Vec::new();
+    for info in &push.infos {
+        if dedup.contains(info.msg_seq, info.msg_uid, info.msg_time) {
+            continue;
+        }
+        if let Ok(decoded) = decode_push_events(info) {
+            dedup.insert(info.msg_seq, info.msg_uid, info.msg_time);
+            events.extend(decoded);
+        }
+    }
+    Ok(events)
+}
+
+/// 处理 `OnlinePush.PbPushTransMsg`，重复的推送返回空，解析成功后才计入去重
+pub fn process_trans_msg(dedup: &mut PushDedup, payload: &[u8]) -> io::Result<Vec<Event>> {
+    let msg = decode_trans_msg(payload)?;
+    let key = (msg.msg_seq as i64, msg.msg_uid as i64, msg.msg_time as i64);
+    if dedup.contains(key.0, key.1, key.2) {
+        return Ok(Vec::new());
+    }
+    let events = decode_trans_events(&msg)?;
+    dedup.insert(key.0, key.1, key.2);
+    Ok(events)
+}
+
+/// `OnlinePush.PbPushTransMsg` 中的 `TransMsgInfo`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransMsg {
+    pub from_uin: u64,
+    pub msg_type: u64,
+    pub msg_seq: u64,
+    pub msg_uid: u64,
+    pub msg_time: u64,
+    pub data: Vec<u8>,
+}
+
+pub fn decode_trans_msg(payload: &[u8]) -> io::Result<TransMsg> {
+    let info = ProtoReader::decode(payload)?;
+    Ok(TransMsg {
+        from_uin: info.get_u64(1).unwrap_or_default(),
+        msg_type: info.get_u64(3).unwrap_or_default(),
+        msg_seq: info.get_u64(5).unwrap_or_default(),
+        msg_uid: info.get_u64(6).unwrap_or_default(),
+        msg_time: info.get_u64(7).unwrap_or_default(),
+        data: info.get_bytes(10).unwrap_or_default().to_vec(),
+    })
+}
+
+/// 解析群成员退出、管理员变更与群转让，`from_uin` 为群号 (uin)
+pub fn decode_trans_events(msg: &TransMsg) -> io::Result<Vec<Event>> {
+    decode_trans_data(msg.msg_type, group_uin_to_code(msg.from_uin), &msg.data)
+}
+
+/// 解析一条推送中的事件，尚未支持的类型会被忽略
+pub fn decode_push_events(info: &PushMessageInfo) -> io::Result<Vec<Event>> {
+    match info.msg_type {
+        MSG_TYPE_GROUP_NOTIFY => decode_group_notify(&info.v_msg),
+        MSG_TYPE_FRIEND_NOTIFY => decode_friend_notify(&info.v_msg),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn decode_group_notify(v_msg: &[u8]) -> io::Result<Vec<Event>> {
+    let mut reader = DataReader::new(v_msg.to_vec());
+    let group_code = reader.read_data::<u32>()? as u64;
+    let sub_type = reader.read_data::<u8>()?;
+    reader.read_data::<u8>()?;
+
+    let mut events = Vec::new();
+    if sub_type == GROUP_MUTE {
+        events.push(decode_group_mute(group_code, &mut reader)?);
+    } else if GROUP_NOTIFY_PB_TYPES.contains(&sub_type) {
+        reader.read_data::<u8>()?;
+        let body = ProtoReader::decode(&reader.read_available())?;
+        if let Some(tip) = body.get_message(26)? {
+            events.extend(decode_nudge_gray_tip(Some(group_code), &tip)?.map(Event::Nudge));
+            events.extend(decode_group_gray_tip(group_code, &tip)?);
+        }
+    }
+    Ok(events)
+}
+
+fn decode_friend_notify(v_msg: &[u8]) -> io::Result<Vec<Event>> {
+    let msg = JceStruct::decode(v_msg)?;
+    let sub_type = msg.get_i64(0).unwrap_or_default();
+    let protobuf = msg.get_bytes(10).unwrap_or_default();
+
+    let mut events = Vec::new();
+    match sub_type {
+        FRIEND_GRAY_TIP => {
+            let tip = ProtoReader::decode(protobuf)?;
+            events.extend(decode_nudge_gray_tip(None, &tip)?.map(Event::Nudge));
+        }
+        PROFILE_MOD => {
+            for body in ProtoReader::decode(protobuf)?.get_repeated_message(1)? {
+                events.extend(decode_group_profile_mod(&body)?);
+                events.extend(decode_friend_profile_mod(&body)?);
+            }
+        }
+        SUB_FRIEND_SYNC | SUB_FRIEND_ADDED | SUB_INPUT_STATUS => {
+            events.extend(decode_friend_push(sub_type, protobuf)?);
+        }
+        _ => {}
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::{
+        binary::protobuf::DynamicProtoMessage,
+        events::{group::GroupMuteAllEvent, notice::NudgeEvent},
+        network::{
+            group_notice::{TRANS_MEMBER_LEAVE, TRANS_PERMISSION},
+            test::MockSender,
+        },
+    };
+
+    /// 构造包含给定推送的 `OnlinePush.ReqPush`
+    pub(crate) fn req_push(infos: Vec<(i64, Vec<u8>)>) -> Vec<u8> {
+        let infos = infos
+            .into_iter()
+            .enumerate()
+            .map(|(seq, (msg_type, v_msg))| {
+                JceStruct::new()
+                    .with(0, 10010i64)
+                    .with(1, 1700000000i64)
+                    .with(2, msg_type)
+                    .with(3, seq as i64)
+                    .with(6, v_msg)
+                    .with(10, 1000 + seq as i64)
+            })
+            .collect::<Vec<_>>();
+        let msg = JceStruct::new().with(0, 12345i64).with(2, infos);
+        RequestPacket::new("OnlinePush", "SvcReqPushMsg")
+            .with_request_id(9)
+            .with_data(REQ_PUSH_KEY, msg)
+            .encode()
+    }
+
+    fn nudge_tip() -> DynamicProtoMessage {
+        let param = |name: &str, value: &str| {
+            DynamicProtoMessage::new()
+                .with(1, name.to_string())
+                .with(2, value.to_string())
+        };
+        DynamicProtoMessage::new().with(6, 10043u64).with(
+            7,
+            vec![
+                param("uin_str1", "10010"),
+                param("uin_str2", "10086"),
+                param("action_str", "戳了戳"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_decode_nudge_push() {
+        let mut group = vec![0, 0, 0x27, 0x11, 0x14, 0, 0];
+        group.extend(
+            DynamicProtoMessage::new()
+                .with(26, nudge_tip())
+                .encode()
+                .unwrap(),
+        );
+        let friend = JceStruct::new()
+            .with(0, FRIEND_GRAY_TIP)
+            .with(10, nudge_tip().encode().unwrap())
+            .encode();
+
+        let push = decode_req_push(&req_push(vec![
+            (MSG_TYPE_GROUP_NOTIFY, group),
+            (MSG_TYPE_FRIEND_NOTIFY, friend),
+            (0x20, Vec::new()),
+        ]))
+        .unwrap();
+        assert_eq!((push.uin, push.request_id), (12345, 9));
+        assert_eq!(push.infos.len(), 3);
+        assert_eq!(push.infos[1].msg_uid, 1001);
+
+        let events = push
+            .infos
+            .iter()
+            .map(|info| decode_push_events(info).unwrap())
+            .collect::<Vec<_>>();
+        let nudge = NudgeEvent {
+            group_code: Some(10001),
+            sender_uin: 10010,
+            receiver_uin: 10086,
+            action: "戳了戳".to_string(),
+            suffix: String::new(),
+        };
+        assert!(matches!(&events[0][..], [Event::Nudge(e)] if *e == nudge));
+        let nudge = NudgeEvent {
+            group_code: None,
+            ..nudge
+        };
+        assert!(matches!(&events[1][..], [Event::Nudge(e)] if *e == nudge));
+        assert!(events[2].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_req_push() {
+        let friend = JceStruct::new()
+            .with(0, FRIEND_GRAY_TIP)
+            .with(10, nudge_tip().encode().unwrap())
+            .encode();
+        let payload = req_push(vec![(MSG_TYPE_FRIEND_NOTIFY, friend)]);
+        let sender = MockSender::new(Vec::new());
+        let mut dedup = PushDedup::default();
+
+        let events = process_req_push(&sender, &mut dedup, &payload)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        // 重连后服务器重复推送，仍需回执但不再产生事件
+        let events = process_req_push(&sender, &mut dedup, &payload)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+
+        let (command, body) = sender.sent_body(1);
+        assert_eq!(command, CMD_RESP_PUSH);
+        let resp = RequestPacket::decode(&body).unwrap();
+        assert_eq!(resp.request_id, 9);
+        let data = resp.data().unwrap();
+        let del_infos = data[RESP_PUSH_KEY].get_struct_list(1);
+        assert_eq!(del_infos.len(), 1);
+        assert_eq!(del_infos[0].get_i64(0), Some(10010));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_push_in_batch() {
+        let friend = || {
+            JceStruct::new()
+                .with(0, FRIEND_GRAY_TIP)
+                .with(10, nudge_tip().encode().unwrap())
+                .encode()
+        };
+        let payload = req_push(vec![
+            (MSG_TYPE_FRIEND_NOTIFY, friend()),
+            (MSG_TYPE_GROUP_NOTIFY, vec![0, 0]),
+            (MSG_TYPE_FRIEND_NOTIFY, friend()),
+        ]);
+        let sender = MockSender::new(Vec::new());
+        let mut dedup = PushDedup::default();
+
+        let events = process_req_push(&sender, &mut dedup, &payload)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(dedup.len(), 2);
+        assert!(!dedup.contains(1, 1001, 1700000000));
+
+        let trans = DynamicProtoMessage::new()
+            .with(1, 10001u64)
+            .with(3, 0x2cu64)
+            .with(5, 7u64)
+            .with(10, vec![0u8])
+            .encode()
+            .unwrap();
+        assert!(process_trans_msg(&mut dedup, &trans).is_err());
+        assert!(!dedup.contains(7, 0, 0));
+    }
+
+    #[test]
+    fn test_trans_msg_group_code() {
+        let trans = |msg_type: u64, data: Vec<u8>| {
+            DynamicProtoMessage::new()
+                .with(1, 2_147_123_456u64)
+                .with(3, msg_type)
+                .with(5, msg_type)
+                .with(10, data)
+                .encode()
+                .unwrap()
+        };
+        let mut admin = vec![0u8; 5];
+        admin.push(1);
+        admin.extend(12345u32.to_be_bytes());
+        admin.push(1);
+        let mut leave = 2_147_123_456u32.to_be_bytes().to_vec();
+        leave.push(1);
+        leave.extend(12345u32.to_be_bytes());
+        leave.push(0x02);
+        leave.extend(0u32.to_be_bytes());
+
+        let mut dedup = PushDedup::new(16);
+        let events = process_trans_msg(&mut dedup, &trans(TRANS_PERMISSION, admin)).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::MemberPermissionChange(e)] if e.group_code == 157_123_456
+        ));
+        let events = process_trans_msg(&mut dedup, &trans(TRANS_MEMBER_LEAVE, leave)).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::MemberLeave(e)] if e.group_code == 157_123_456
+        ));
+    }
+
+    #[test]
+    fn test_push_dedup_capacity() {
+        let mut dedup = PushDedup::new(2);
+        assert!(dedup.insert(1, 1, 1));
+        assert!(!dedup.insert(1, 1, 1));
+        assert!(dedup.insert(2, 2, 2));
+        assert!(dedup.insert(3, 3, 3));
+        assert_eq!(dedup.len(), 2);
+        assert!(dedup.insert(1, 1, 1));
+    }
+
+    #[test]
+    fn test_decode_mute_push() {
+        let mut mute = 10001u32.to_be_bytes().to_vec();
+        mute.extend([GROUP_MUTE, 0]);
+        mute.extend(10010u32.to_be_bytes());
+        mute.extend([0u8; 6]);
+        mute.extend(0u32.to_be_bytes());
+        mute.extend(u32::MAX.to_be_bytes());
+        let push = decode_req_push(&req_push(vec![(MSG_TYPE_GROUP_NOTIFY, mute)])).unwrap();
+        let events = decode_push_events(&push.infos[0]).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::GroupMuteAll(GroupMuteAllEvent {
+                group_code: 10001,
+                operator_uin: 10010,
+                enabled: true,
+            })]
+        ));
+
+        let trans = DynamicProtoMessage::new()
+            .with(1, 10001u64)
+            .with(3, 0x2cu64)
+            .with(5, 7u64)
+            .with(10, vec![0u8, 0, 0, 0, 0, 1, 0, 0, 0x30, 0x39, 0])
+            .encode()
+            .unwrap();
+        let msg = decode_trans_msg(&trans).unwrap();
+        assert_eq!(msg.msg_seq, 7);
+        let events = decode_trans_events(&msg).unwrap();
+        assert!(matches!(
+            &events[..],
+            [Event::MemberPermissionChange(e)] if e.member_uin == 12345 && !e.is_admin
+        ));
+    }
+}
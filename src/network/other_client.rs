@@ -0,0 +1,249 @@
+//! 同一账号的其他客户端，以及它们发出的消息的同步
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/decoders.go)
+
+use std::io;
+
+use crate::{
+    binary::{jce::RequestPacket, protobuf::ProtoReader},
+    events::{client::OtherClientStatusEvent, message::SelfMessageEvent, Event},
+    message::MessageTarget,
+};
+
+use super::{
+    history::decode_message,
+    online_push::{build_resp_push, ReqPush, CMD_RESP_PUSH},
+    send_uni_oneway, NetworkResult, SsoSender,
+};
+
+pub const CMD_MSF_LOGIN_NOTIFY: &str = "StatSvc.SvcReqMSFLoginNotify";
+pub const CMD_C2C_MSG_SYNC: &str = "OnlinePush.PbC2CMsgSync";
+pub const CMD_PUSH_GROUP_MSG: &str = "OnlinePush.PbPushGroupMsg";
+
+const LOGIN_NOTIFY_KEY: &str = "SvcReqMSFLoginNotify";
+const STATUS_ONLINE: i64 = 1;
+const STATUS_OFFLINE: i64 = 2;
+
+/// 登录了同一账号的其他客户端，以 `app_id` 区分
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OtherClient {
+    pub app_id: i64,
+    pub platform: i64,
+    /// 设备名，例如 "电脑"
+    pub device_name: String,
+    /// 设备类型描述，例如 "Windows"
+    pub device_kind: String,
+}
+
+/// 当前在线的其他客户端
+#[derive(Debug, Clone, Default)]
+pub struct OtherClients(Vec<OtherClient>);
+
+impl OtherClients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OtherClient> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, app_id: i64) -> Option<&OtherClient> {
+        self.0.iter().find(|c| c.app_id == app_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 更新客户端状态，状态实际发生变化时返回事件
+    pub fn update(&mut self, client: OtherClient, online: bool) -> Option<OtherClientStatusEvent> {
+        let idx = self.0.iter().position(|c| c.app_id == client.app_id);
+        match (idx, online) {
+            (None, true) => {
+                self.0.push(client.clone());
+                Some(OtherClientStatusEvent {
+                    client,
+                    online: true,
+                })
+            }
+            (Some(idx), false) => Some(OtherClientStatusEvent {
+                client: self.0.remove(idx),
+                online: false,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 解析 `SvcReqMSFLoginNotify`，返回客户端以及是否上线
+pub fn decode_login_notify(payload: &[u8]) -> io::Result<Option<(OtherClient, bool)>> {
+    let data = RequestPacket::decode(payload)?.data()?;
+    let Some(notify) = data.get(LOGIN_NOTIFY_KEY) else {
+        return Ok(None);
+    };
+    let online = match notify.get_i64(1) {
+        Some(STATUS_ONLINE) => true,
+        Some(STATUS_OFFLINE) => false,
+        _ => return Ok(None),
+    };
+    let client = OtherClient {
+        app_id: notify.get_i64(0).unwrap_or_default(),
+        platform: notify.get_i64(3).unwrap_or_default(),
+        device_name: notify.get_string(4).unwrap_or_default(),
+        device_kind: notify.get_string(5).unwrap_or_default(),
+    };
+    Ok(Some((client, online)))
+}
+
+pub fn process_login_notify(
+    clients: &mut OtherClients,
+    payload: &[u8],
+) -> io::Result<Option<Event>> {
+    Ok(decode_login_notify(payload)?
+        .and_then(|(client, online)| clients.update(client, online))
+        .map(Event::OtherClientStatus))
+}
+
+/// 解析 `msg_comm.Msg`，发送者为 bot 自身时作为 [`SelfMessageEvent`]
+fn decode_self_message(self_uin: u64, msg: &ProtoReader) -> io::Result<Option<SelfMessageEvent>> {
+    let head = msg.get_message(1)?.unwrap_or_default();
+    if head.get_u64(1) != Some(self_uin) {
+        return Ok(None);
+    }
+    let target = match head.get_message(9)? {
+        Some(group) => MessageTarget::Group(group.get_u64(1).unwrap_or_default()),
+        None => MessageTarget::Friend(head.get_u64(2).unwrap_or_default()),
+    };
+    let random = msg
+        .get_message(3)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .unwrap_or_default()
+        .get_message(1)?
+        .and_then(|attr| attr.get_u64(3))
+        .unwrap_or_default() as u32;
+    let msg = decode_message(msg)?;
+    Ok(Some(SelfMessageEvent {
+        target,
+        seq: msg.seq,
+        random,
+        time: msg.time,
+        chain: msg.chain,
+    }))
+}
+
+/// 处理 `OnlinePush.PbC2CMsgSync`，即其他设备发出的私聊消息；需要回执，`seq` 为收到的包的 seq
+pub async fn process_c2c_msg_sync<S: SsoSender>(
+    sender: &S,
+    self_uin: u64,
+    seq: i32,
+    payload: &[u8],
+) -> NetworkResult<Option<Event>> {
+    let push = ProtoReader::decode(payload)?;
+    let resp = ReqPush {
+        request_id: seq,
+        uin: self_uin as i64,
+        svrip: push.get_i64(2).unwrap_or_default() as i32 as i64,
+        push_token: push.get_bytes(3).unwrap_or_default().to_vec(),
+        infos: Vec::new(),
+    };
+    send_uni_oneway(sender, CMD_RESP_PUSH, &build_resp_push(&resp)).await?;
+    let msg = push.get_message(1)?.unwrap_or_default();
+    Ok(decode_self_message(self_uin, &msg)?.map(Event::SelfMessage))
+}
+
+/// `OnlinePush.PbPushGroupMsg` 中 bot 自己发出的群消息，其他群消息返回 `None`
+pub fn decode_self_group_message(self_uin: u64, payload: &[u8]) -> io::Result<Option<Event>> {
+    let push = ProtoReader::decode(payload)?;
+    let msg = push.get_message(1)?.unwrap_or_default();
+    Ok(decode_self_message(self_uin, &msg)?.map(Event::SelfMessage))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::{jce::JceStruct, protobuf::DynamicProtoMessage},
+        message::MessageChain,
+        network::test::MockSender,
+    };
+
+    fn login_notify(status: i64) -> Vec<u8> {
+        let notify = JceStruct::new()
+            .with(0, 537064989i64)
+            .with(1, status)
+            .with(4, "电脑")
+            .with(5, "Windows");
+        RequestPacket::new("StatSvc", "SvcReqMSFLoginNotify")
+            .with_data(LOGIN_NOTIFY_KEY, notify)
+            .encode()
+    }
+
+    #[test]
+    fn test_other_clients() {
+        let mut clients = OtherClients::new();
+        let event = process_login_notify(&mut clients, &login_notify(STATUS_ONLINE)).unwrap();
+        assert!(matches!(event, Some(Event::OtherClientStatus(e)) if e.online));
+        assert_eq!(clients.get(537064989).unwrap().device_name, "电脑");
+        assert!(
+            process_login_notify(&mut clients, &login_notify(STATUS_ONLINE))
+                .unwrap()
+                .is_none()
+        );
+
+        let event = process_login_notify(&mut clients, &login_notify(STATUS_OFFLINE)).unwrap();
+        assert!(matches!(event, Some(Event::OtherClientStatus(e)) if !e.online));
+        assert!(clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_c2c_msg_sync() {
+        let chain = MessageChain::new().with("from pc");
+        let msg = DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, 10086u64)
+                    .with(2, 10010u64)
+                    .with(5, 3u64)
+                    .with(6, 1700000000u32),
+            )
+            .with(
+                3,
+                DynamicProtoMessage::new()
+                    .with(1, chain.to_rich_text(MessageTarget::Friend(10010))),
+            );
+        let payload = DynamicProtoMessage::new()
+            .with(1, msg)
+            .with(3, b"token".to_vec())
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(Vec::new());
+        let event = process_c2c_msg_sync(&sender, 10086, 42, &payload)
+            .await
+            .unwrap();
+        let Some(Event::SelfMessage(event)) = event else {
+            panic!("except self message")
+        };
+        assert_eq!(event.target, MessageTarget::Friend(10010));
+        assert_eq!(event.chain, chain);
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_RESP_PUSH);
+        let resp = RequestPacket::decode(&body).unwrap();
+        assert_eq!(resp.request_id, 42);
+        assert_eq!(
+            resp.data().unwrap()["resp"].get_bytes(3),
+            Some(&b"token"[..])
+        );
+
+        assert!(process_c2c_msg_sync(&sender, 1, 43, &payload)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! 用户资料 `OidbSvc.0x5eb_22`、群资料 `OidbSvc.0x88d_0` 与群成员资料查询
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/group_info.go)
+
+use std::io;
+
+use crate::binary::protobuf::{DynamicProtoMessage, ProtoReader};
+
+use super::{
+    oidb::{oidb_command_name, oidb_request},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_GROUP_MEMBER_CARD: &str = "group_member_card.get_group_member_card_info";
+
+const OIDB_USER_PROFILE: u32 = 0x5eb;
+const SERVICE_USER_PROFILE: u32 = 22;
+const OIDB_GROUP_INFO: u32 = 0x88d;
+const SERVICE_GROUP_INFO: u32 = 0;
+
+/// `UdcUinData` 中的资料字段，请求时以同样的编号标记需要的字段
+const FIELD_SIGN: u64 = 102;
+const FIELD_LEVEL: u64 = 105;
+const FIELD_NICK: u64 = 20002;
+const FIELD_GENDER: u64 = 20009;
+const FIELD_AGE: u64 = 20037;
+const FIELD_QID: u64 = 27394;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Gender {
+    Male,
+    Female,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserProfile {
+    pub uin: u64,
+    pub nick: String,
+    pub gender: Gender,
+    pub age: u32,
+    pub level: u32,
+    /// 个性签名
+    pub sign: String,
+    pub qid: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub group_code: u64,
+    pub name: String,
+    pub owner_uin: u64,
+    pub member_count: u32,
+    pub max_member_count: u32,
+    /// 创建时间，unix 时间戳
+    pub create_time: u32,
+    /// 群公告（旧版群介绍）
+    pub memo: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemberPermission {
+    Owner,
+    Administrator,
+    #[default]
+    Member,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMemberInfo {
+    pub group_code: u64,
+    pub uin: u64,
+    pub nick: String,
+    /// 群名片，未设置时为空
+    pub card: String,
+    pub gender: Gender,
+    pub level: u32,
+    pub join_time: u64,
+    pub last_speak_time: u64,
+    pub special_title: String,
+    pub permission: MemberPermission,
+}
+
+/// 用户头像地址，`size` 可以为 40、100、140 或 640
+pub fn user_avatar_url(uin: u64, size: u32) -> String {
+    format!("https://q1.qlogo.cn/g?b=qq&nk={}&s={}", uin, size)
+}
+
+/// 群头像地址
+pub fn group_avatar_url(group_code: u64, size: u32) -> String {
+    format!("https://p.qlogo.cn/gh/{0}/{0}/{1}", group_code, size)
+}
+
+pub fn build_user_profile(uins: &[u64]) -> io::Result<Vec<u8>> {
+    [
+        FIELD_SIGN,
+        FIELD_LEVEL,
+        FIELD_NICK,
+        FIELD_GENDER,
+        FIELD_AGE,
+        FIELD_QID,
+    ]
+    .into_iter()
+    .fold(
+        DynamicProtoMessage::new().with(1, uins.to_vec()),
+        |req, field| req.with(field, 1u32),
+    )
+    .encode()
+}
+
+pub fn decode_user_profile(payload: &[u8]) -> io::Result<Vec<UserProfile>> {
+    ProtoReader::decode(payload)?
+        .get_repeated_message(11)?
+        .into_iter()
+        .map(|data| {
+            Ok(UserProfile {
+                uin: data.get_u64(1).unwrap_or_default(),
+                nick: data.get_string(FIELD_NICK).unwrap_or_default(),
+                gender: match data.get_u64(FIELD_GENDER) {
+                    Some(1) => Gender::Male,
+                    Some(2) => Gender::Female,
+                    _ => Gender::Unknown,
+                },
+                age: data.get_u64(FIELD_AGE).unwrap_or_default() as u32,
+                level: data.get_u64(FIELD_LEVEL).unwrap_or_default() as u32,
+                sign: data.get_string(FIELD_SIGN).unwrap_or_default(),
+                qid: data.get_string(FIELD_QID).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// 查询好友或陌生人的资料
+pub async fn get_user_profile<S: SsoSender>(sender: &S, uin: u64) -> NetworkResult<UserProfile> {
+    let body = build_user_profile(&[uin])?;
+    let rsp = oidb_request(sender, OIDB_USER_PROFILE, SERVICE_USER_PROFILE, body).await?;
+    decode_user_profile(&rsp)?
+        .into_iter()
+        .find(|p| p.uin == uin)
+        .ok_or_else(|| NetworkError::Server {
+            command: oidb_command_name(OIDB_USER_PROFILE, SERVICE_USER_PROFILE),
+            code: -1,
+            message: format!("user {} not found", uin),
+        })
+}
+
+pub fn build_group_info(app_id: u32, group_code: u64) -> io::Result<Vec<u8>> {
+    // 需要返回的字段填入零值
+    let info = DynamicProtoMessage::new()
+        .with(1, 0u64)
+        .with(2, 0u32)
+        .with(5, 0u32)
+        .with(6, 0u32)
+        .with(15, Vec::<u8>::new())
+        .with(16, Vec::<u8>::new());
+    DynamicProtoMessage::new()
+        .with(1, app_id)
+        .with(
+            2,
+            vec![DynamicProtoMessage::new().with(1, group_code).with(2, info)],
+        )
+        .with(3, 0u32)
+        .encode()
+}
+
+pub fn decode_group_info(payload: &[u8]) -> NetworkResult<GroupInfo> {
+    let command = oidb_command_name(OIDB_GROUP_INFO, SERVICE_GROUP_INFO);
+    let rsp = ProtoReader::decode(payload)?;
+    let Some(group) = rsp.get_repeated_message(1)?.pop() else {
+        return Err(NetworkError::Server {
+            command,
+            code: -1,
+            message: rsp.get_string(2).unwrap_or_default(),
+        });
+    };
+    let code = group.get_i64(2).unwrap_or_default() as i32;
+    if code != 0 {
+        return Err(NetworkError::Server {
+            command,
+            code,
+            message: rsp.get_string(2).unwrap_or_default(),
+        });
+    }
+    let info = group.get_message(3)?.unwrap_or_default();
+    Ok(GroupInfo {
+        group_code: group.get_u64(1).unwrap_or_default(),
+        name: info.get_string(15).unwrap_or_default(),
+        owner_uin: info.get_u64(1).unwrap_or_default(),
+        member_count: info.get_u64(6).unwrap_or_default() as u32,
+        max_member_count: info.get_u64(5).unwrap_or_default() as u32,
+        create_time: info.get_u64(2).unwrap_or_default() as u32,
+        memo: info.get_string(16).unwrap_or_default(),
+    })
+}
+
+pub async fn get_group_info<S: SsoSender>(
+    sender: &S,
+    app_id: u32,
+    group_code: u64,
+) -> NetworkResult<GroupInfo> {
+    let body = build_group_info(app_id, group_code)?;
+    let rsp = oidb_request(sender, OIDB_GROUP_INFO, SERVICE_GROUP_INFO, body).await?;
+    decode_group_info(&rsp)
+}
+
+pub fn build_group_member_info(group_code: u64, uin: u64) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, group_code)
+        .with(2, uin)
+        .with(3, true)
+        .with(4, 1u32)
+        .with(5, 1u32)
+        .encode()
+}
+
+/// 解析 `GroupMemberRspBody`，成员不存在时返回 `None`
+pub fn decode_group_member_info(payload: &[u8]) -> io::Result<Option<GroupMemberInfo>> {
+    let rsp = ProtoReader::decode(payload)?;
+    let Some(info) = rsp.get_message(3)? else {
+        return Ok(None);
+    };
+    if info.get_bytes(11).is_none() && info.get_u64(12).is_none() {
+        return Ok(None);
+    }
+    Ok(Some(GroupMemberInfo {
+        group_code: rsp.get_u64(1).unwrap_or_default(),
+        uin: info.get_u64(1).unwrap_or_default(),
+        nick: info.get_string(11).unwrap_or_default(),
+        card: info.get_string(8).unwrap_or_default(),
+        gender: match info.get_u64(9) {
+            Some(0) => Gender::Male,
+            Some(1) => Gender::Female,
+            _ => Gender::Unknown,
+        },
+        level: info.get_u64(39).unwrap_or_default() as u32,
+        join_time: info.get_u64(14).unwrap_or_default(),
+        last_speak_time: info.get_u64(15).unwrap_or_default(),
+        special_title: info.get_string(31).unwrap_or_default(),
+        permission: match info.get_u64(27) {
+            Some(3) => MemberPermission::Owner,
+            Some(2) => MemberPermission::Administrator,
+            _ => MemberPermission::Member,
+        },
+    }))
+}
+
+pub async fn get_group_member_info<S: SsoSender>(
+    sender: &S,
+    group_code: u64,
+    uin: u64,
+) -> NetworkResult<Option<GroupMemberInfo>> {
+    let body = build_group_member_info(group_code, uin)?;
+    let rsp = send_uni_request(sender, CMD_GROUP_MEMBER_CARD, &body).await?;
+    Ok(decode_group_member_info(&rsp)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    fn oidb_rsp(body: DynamicProtoMessage) -> Vec<u8> {
+        DynamicProtoMessage::new()
+            .with(3, 0u32)
+            .with(4, body.encode().unwrap())
+            .encode()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_user_profile() {
+        let rsp = oidb_rsp(DynamicProtoMessage::new().with(
+            11,
+            vec![DynamicProtoMessage::new()
+                    .with(1, 10086u64)
+                    .with(FIELD_NICK, "nick".to_string())
+                    .with(FIELD_GENDER, 2u32)
+                    .with(FIELD_AGE, 18u32)
+                    .with(FIELD_SIGN, "hello".to_string())],
+        ));
+        let sender = MockSender::new(vec![rsp]);
+        let profile = get_user_profile(&sender, 10086).await.unwrap();
+        assert_eq!(profile.nick, "nick");
+        assert_eq!(profile.gender, Gender::Female);
+        assert_eq!((profile.age, profile.sign.as_str()), (18, "hello"));
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, "OidbSvc.0x5eb_22");
+        let req = ProtoReader::decode(&body).unwrap();
+        let req = ProtoReader::decode(req.get_bytes(4).unwrap()).unwrap();
+        assert_eq!(req.get_repeated_u64(1), vec![10086]);
+        assert_eq!(req.get_u64(FIELD_NICK), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_get_group_info() {
+        let rsp = oidb_rsp(DynamicProtoMessage::new().with(
+            1,
+            vec![DynamicProtoMessage::new().with(1, 10086u64).with(
+                    3,
+                    DynamicProtoMessage::new()
+                        .with(1, 10010u64)
+                        .with(2, 1600000000u32)
+                        .with(5, 500u32)
+                        .with(6, 42u32)
+                        .with(15, "group".to_string()),
+                )],
+        ));
+        let sender = MockSender::new(vec![rsp]);
+        let info = get_group_info(&sender, 16, 10086).await.unwrap();
+        assert_eq!(info.name, "group");
+        assert_eq!(info.owner_uin, 10010);
+        assert_eq!((info.member_count, info.max_member_count), (42, 500));
+        assert_eq!(
+            group_avatar_url(10086, 640),
+            "https://p.qlogo.cn/gh/10086/10086/640"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_group_member_info() {
+        let rsp = DynamicProtoMessage::new()
+            .with(1, 10086u64)
+            .with(
+                3,
+                DynamicProtoMessage::new()
+                    .with(1, 10010u64)
+                    .with(8, "card".to_string())
+                    .with(11, "nick".to_string())
+                    .with(27, 2u32)
+                    .with(31, "title".to_string()),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp, Vec::new()]);
+        let member = get_group_member_info(&sender, 10086, 10010)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(member.card, "card");
+        assert_eq!(member.permission, MemberPermission::Administrator);
+        assert_eq!(member.special_title, "title");
+        assert!(get_group_member_info(&sender, 10086, 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
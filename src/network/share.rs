@@ -0,0 +1,104 @@
+//! 音乐分享，通过 `OidbSvc.0xb77_9` 由服务器生成并发送卡片
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/sharing.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::DynamicProtoMessage,
+    message::{rich::MusicShare, MessageTarget},
+};
+
+use super::{oidb::oidb_request, NetworkResult, SsoSender};
+
+const OIDB_SHARE: u32 = 0xb77;
+const SERVICE_SHARE: u32 = 9;
+
+/// 有音频地址时为音乐卡片，否则为普通分享卡片
+const STYLE_MUSIC: u32 = 4;
+const STYLE_DEFAULT: u32 = 0;
+
+pub fn build_music_share(target: MessageTarget, share: &MusicShare) -> io::Result<Vec<u8>> {
+    let info = share.kind.app_info();
+    let (send_type, recv_uin) = match target {
+        MessageTarget::Friend(uin) => (0u32, uin),
+        MessageTarget::Group(code) => (1u32, code),
+    };
+    let style = if share.music_url.is_empty() {
+        STYLE_DEFAULT
+    } else {
+        STYLE_MUSIC
+    };
+    DynamicProtoMessage::new()
+        .with(1, info.app_id)
+        .with(2, 1u32)
+        .with(3, style)
+        .with(
+            5,
+            DynamicProtoMessage::new()
+                .with(1, 1u32)
+                .with(2, "0.0.0".to_string())
+                .with(3, info.package_name.to_string())
+                .with(4, info.signature.to_string()),
+        )
+        .with(7, DynamicProtoMessage::new().with(1, 0u32))
+        .with(10, send_type)
+        .with(11, recv_uin)
+        .with(
+            12,
+            DynamicProtoMessage::new()
+                .with(10, share.title.clone())
+                .with(11, share.summary.clone())
+                .with(12, format!("[分享]{}", share.title))
+                .with(13, share.jump_url.clone())
+                .with(14, share.picture_url.clone())
+                .with(16, share.music_url.clone()),
+        )
+        .encode()
+}
+
+/// 发送音乐分享，卡片消息随后会作为普通消息推送回来
+pub async fn send_music_share<S: SsoSender>(
+    sender: &S,
+    target: MessageTarget,
+    share: &MusicShare,
+) -> NetworkResult<()> {
+    let body = build_music_share(target, share)?;
+    oidb_request(sender, OIDB_SHARE, SERVICE_SHARE, body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::protobuf::ProtoReader, message::rich::MusicKind, network::test::MockSender,
+    };
+
+    #[tokio::test]
+    async fn test_send_music_share() {
+        let rsp = DynamicProtoMessage::new().with(3, 0u32).encode().unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let share = MusicShare {
+            kind: MusicKind::NetEase,
+            title: "song".to_string(),
+            summary: "singer".to_string(),
+            jump_url: "https://music.163.com/song?id=1".to_string(),
+            picture_url: String::new(),
+            music_url: "https://music.163.com/song/media/outer/url?id=1".to_string(),
+        };
+        send_music_share(&sender, MessageTarget::Group(10086), &share)
+            .await
+            .unwrap();
+
+        let (command, sent) = sender.sent_body(0);
+        assert_eq!(command, "OidbSvc.0xb77_9");
+        let pkg = ProtoReader::decode(&sent).unwrap();
+        let req = ProtoReader::decode(pkg.get_bytes(4).unwrap()).unwrap();
+        assert_eq!(req.get_u64(1), Some(100495085));
+        assert_eq!(req.get_u64(3), Some(STYLE_MUSIC as u64));
+        assert_eq!(req.get_u64(10), Some(1));
+        assert_eq!(req.get_u64(11), Some(10086));
+        let rich = req.get_message(12).unwrap().unwrap();
+        assert_eq!(rich.get_string(10).unwrap(), "song");
+    }
+}
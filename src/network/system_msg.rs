@@ -0,0 +1,447 @@
+//! 好友申请、加群申请与群邀请
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/system_msg.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    events::request::{
+        BotInvitedJoinGroupRequestEvent, MemberJoinRequestEvent, NewFriendRequestEvent,
+    },
+};
+
+use super::{send_uni_request, NetworkError, NetworkResult, SsoSender};
+
+pub const CMD_SYSTEM_MSG_NEW_GROUP: &str = "ProfileService.Pb.ReqSystemMsgNew.Group";
+pub const CMD_SYSTEM_MSG_NEW_FRIEND: &str = "ProfileService.Pb.ReqSystemMsgNew.Friend";
+pub const CMD_SYSTEM_MSG_ACTION_GROUP: &str = "ProfileService.Pb.ReqSystemMsgAction.Group";
+pub const CMD_SYSTEM_MSG_ACTION_FRIEND: &str = "ProfileService.Pb.ReqSystemMsgAction.Friend";
+
+/// 群系统消息中的 `group_msg_type`
+const GROUP_MSG_JOIN_REQUEST: u64 = 1;
+const GROUP_MSG_INVITED: u64 = 2;
+const GROUP_MSG_MEMBER_INVITE: u64 = 22;
+
+/// 处理申请的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestAction {
+    Accept,
+    Reject,
+    Ignore,
+}
+
+impl RequestAction {
+    fn group_action_type(&self) -> u64 {
+        match self {
+            RequestAction::Accept => 11,
+            RequestAction::Reject => 12,
+            RequestAction::Ignore => 14,
+        }
+    }
+
+    fn friend_action_type(&self) -> u64 {
+        match self {
+            RequestAction::Accept => 2,
+            RequestAction::Reject | RequestAction::Ignore => 3,
+        }
+    }
+}
+
+/// 一次系统消息拉取的结果
+#[derive(Debug, Default)]
+pub struct SystemMessages {
+    pub friend_requests: Vec<NewFriendRequestEvent>,
+    pub join_requests: Vec<MemberJoinRequestEvent>,
+    pub invitations: Vec<BotInvitedJoinGroupRequestEvent>,
+}
+
+fn system_msg_flag() -> DynamicProtoMessage {
+    (1..=17)
+        .filter(|f| *f != 4)
+        .fold(DynamicProtoMessage::new(), |msg, field| {
+            msg.with(field, 1u32)
+        })
+}
+
+/// `suspicious` 为真时拉取被服务器过滤的可疑加群申请
+pub fn build_system_msg_new_group(suspicious: bool) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 100u32)
+        .with(4, 1000u32)
+        .with(5, 3u32)
+        .with(6, system_msg_flag())
+        .with(8, false)
+        .with(9, false)
+        .with(10, 1u32)
+        .with(11, if suspicious { 2u32 } else { 1 })
+        .encode()
+}
+
+pub fn build_system_msg_new_friend() -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 20u32)
+        .with(4, 1000u32)
+        .with(5, 2u32)
+        .with(
+            6,
+            DynamicProtoMessage::new()
+                .with(7, 1u32)
+                .with(9, 1u32)
+                .with(10, 1u32),
+        )
+        .with(8, false)
+        .with(9, false)
+        .with(10, 1u32)
+        .encode()
+}
+
+/// 解析 `RspSystemMsgNew`，只保留尚未处理的申请
+pub fn decode_system_msg_new(payload: &[u8]) -> io::Result<SystemMessages> {
+    let rsp = ProtoReader::decode(payload)?;
+    let mut res = SystemMessages::default();
+
+    for st in rsp.get_repeated_message(9)? {
+        let msg = st.get_message(50)?.unwrap_or_default();
+        if msg.get_u64(1) != Some(1) {
+            continue;
+        }
+        res.friend_requests.push(NewFriendRequestEvent {
+            request_id: st.get_u64(3).unwrap_or_default(),
+            message: msg.get_string(4).unwrap_or_default(),
+            requester_uin: st.get_u64(5).unwrap_or_default(),
+            requester_nick: msg.get_string(51).unwrap_or_default(),
+        });
+    }
+
+    for st in rsp.get_repeated_message(10)? {
+        let msg = st.get_message(50)?.unwrap_or_default();
+        if msg.get_u64(1) != Some(1) {
+            continue;
+        }
+        let request_id = st.get_u64(3).unwrap_or_default();
+        let group_code = msg.get_u64(10).unwrap_or_default();
+        let group_name = msg.get_string(52).unwrap_or_default();
+        match msg.get_u64(12) {
+            Some(GROUP_MSG_JOIN_REQUEST) | Some(GROUP_MSG_MEMBER_INVITE) => {
+                let invited_by = msg.get_u64(12) == Some(GROUP_MSG_MEMBER_INVITE);
+                res.join_requests.push(MemberJoinRequestEvent {
+                    request_id,
+                    message: msg.get_string(4).unwrap_or_default(),
+                    requester_uin: st.get_u64(5).unwrap_or_default(),
+                    requester_nick: msg.get_string(51).unwrap_or_default(),
+                    group_code,
+                    group_name,
+                    invitor_uin: invited_by.then(|| msg.get_u64(11)).flatten(),
+                    suspicious: msg.get_bytes(32).is_some_and(|t| !t.is_empty()),
+                });
+            }
+            Some(GROUP_MSG_INVITED) => {
+                res.invitations.push(BotInvitedJoinGroupRequestEvent {
+                    request_id,
+                    invitor_uin: msg.get_u64(11).unwrap_or_default(),
+                    invitor_nick: msg.get_string(53).unwrap_or_default(),
+                    group_code,
+                    group_name,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(res)
+}
+
+pub struct GroupSystemMsgAction<'a> {
+    pub request_id: u64,
+    pub requester_uin: u64,
+    pub group_code: u64,
+    pub is_invite: bool,
+    pub suspicious: bool,
+    pub action: RequestAction,
+    pub blacklist: bool,
+    pub reason: &'a str,
+}
+
+pub fn build_group_system_msg_action(req: &GroupSystemMsgAction) -> io::Result<Vec<u8>> {
+    let (sub_src_id, group_msg_type) = if req.is_invite {
+        (10016u32, GROUP_MSG_INVITED)
+    } else {
+        (31, GROUP_MSG_JOIN_REQUEST)
+    };
+    DynamicProtoMessage::new()
+        .with(1, if req.suspicious { 2u32 } else { 1 })
+        .with(2, req.request_id)
+        .with(3, req.requester_uin)
+        .with(4, 1u32)
+        .with(5, 3u32)
+        .with(6, sub_src_id)
+        .with(7, group_msg_type)
+        .with(
+            8,
+            DynamicProtoMessage::new()
+                .with(1, req.action.group_action_type())
+                .with(2, req.group_code)
+                .with(50, req.reason.to_string())
+                .with(53, req.blacklist),
+        )
+        .with(9, 1000u32)
+        .encode()
+}
+
+/// 好友申请没有单独的忽略操作，[`RequestAction::Ignore`] 按不附带理由的拒绝处理
+pub fn build_friend_system_msg_action(
+    request_id: u64,
+    requester_uin: u64,
+    action: RequestAction,
+    blacklist: bool,
+    reason: &str,
+) -> io::Result<Vec<u8>> {
+    let reason = match action {
+        RequestAction::Reject => reason,
+        RequestAction::Accept | RequestAction::Ignore => "",
+    };
+    DynamicProtoMessage::new()
+        .with(1, 1u32)
+        .with(2, request_id)
+        .with(3, requester_uin)
+        .with(4, 1u32)
+        .with(5, 6u32)
+        .with(6, 7u32)
+        .with(
+            8,
+            DynamicProtoMessage::new()
+                .with(1, action.friend_action_type())
+                .with(50, reason.to_string())
+                .with(53, blacklist)
+                .with(54, DynamicProtoMessage::new()),
+        )
+        .encode()
+}
+
+/// 检查 `RspSystemMsgAction` 的 head
+fn check_action_response(command: &str, payload: &[u8]) -> NetworkResult<()> {
+    let rsp = ProtoReader::decode(payload)?;
+    let head = rsp.get_message(1)?.unwrap_or_default();
+    match head.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: head.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 拉取尚未处理的好友申请、加群申请与群邀请，可疑的加群申请单独拉取后合并
+pub async fn fetch_system_messages<S: SsoSender>(sender: &S) -> NetworkResult<SystemMessages> {
+    let group = send_uni_request(
+        sender,
+        CMD_SYSTEM_MSG_NEW_GROUP,
+        &build_system_msg_new_group(false)?,
+    )
+    .await?;
+    let suspicious = send_uni_request(
+        sender,
+        CMD_SYSTEM_MSG_NEW_GROUP,
+        &build_system_msg_new_group(true)?,
+    )
+    .await?;
+    let friend = send_uni_request(
+        sender,
+        CMD_SYSTEM_MSG_NEW_FRIEND,
+        &build_system_msg_new_friend()?,
+    )
+    .await?;
+
+    let mut res = decode_system_msg_new(&group)?;
+    let suspicious = decode_system_msg_new(&suspicious)?;
+    // 可疑列表中的申请处理时需要以 `msg_type` 2 回复
+    res.join_requests
+        .extend(suspicious.join_requests.into_iter().map(|mut req| {
+            req.suspicious = true;
+            req
+        }));
+    res.invitations.extend(suspicious.invitations);
+    res.friend_requests = decode_system_msg_new(&friend)?.friend_requests;
+    Ok(res)
+}
+
+pub async fn solve_group_request<S: SsoSender>(
+    sender: &S,
+    req: &GroupSystemMsgAction<'_>,
+) -> NetworkResult<()> {
+    let payload = send_uni_request(
+        sender,
+        CMD_SYSTEM_MSG_ACTION_GROUP,
+        &build_group_system_msg_action(req)?,
+    )
+    .await?;
+    check_action_response(CMD_SYSTEM_MSG_ACTION_GROUP, &payload)
+}
+
+pub async fn solve_friend_request<S: SsoSender>(
+    sender: &S,
+    request_id: u64,
+    requester_uin: u64,
+    action: RequestAction,
+    blacklist: bool,
+    reason: &str,
+) -> NetworkResult<()> {
+    let req = build_friend_system_msg_action(request_id, requester_uin, action, blacklist, reason)?;
+    let payload = send_uni_request(sender, CMD_SYSTEM_MSG_ACTION_FRIEND, &req).await?;
+    check_action_response(CMD_SYSTEM_MSG_ACTION_FRIEND, &payload)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::test::MockSender;
+
+    fn struct_msg(seq: u64, req_uin: u64, msg: DynamicProtoMessage) -> DynamicProtoMessage {
+        DynamicProtoMessage::new()
+            .with(3, seq)
+            .with(5, req_uin)
+            .with(50, msg)
+    }
+
+    #[test]
+    fn test_decode_group_messages() {
+        let join = DynamicProtoMessage::new()
+            .with(1, 1u32)
+            .with(4, "let me in".to_string())
+            .with(10, 114514u64)
+            .with(12, GROUP_MSG_JOIN_REQUEST)
+            .with(51, "Alice".to_string())
+            .with(52, "Rust".to_string());
+        let invite = DynamicProtoMessage::new()
+            .with(1, 1u32)
+            .with(10, 1919810u64)
+            .with(11, 10086u64)
+            .with(12, GROUP_MSG_INVITED)
+            .with(53, "Bob".to_string());
+        let handled = DynamicProtoMessage::new()
+            .with(1, 2u32)
+            .with(12, GROUP_MSG_JOIN_REQUEST);
+        let payload = DynamicProtoMessage::new()
+            .with(
+                10,
+                vec![
+                    struct_msg(1, 12345, join),
+                    struct_msg(2, 0, invite),
+                    struct_msg(3, 0, handled),
+                ],
+            )
+            .encode()
+            .unwrap();
+
+        let res = decode_system_msg_new(&payload).unwrap();
+        assert!(res.friend_requests.is_empty());
+        assert_eq!(res.join_requests.len(), 1);
+        let join = &res.join_requests[0];
+        assert_eq!(join.request_id, 1);
+        assert_eq!(join.requester_uin, 12345);
+        assert_eq!(join.message, "let me in");
+        assert_eq!(join.group_code, 114514);
+        assert_eq!(join.invitor_uin, None);
+        assert_eq!(res.invitations.len(), 1);
+        assert_eq!(res.invitations[0].invitor_uin, 10086);
+        assert_eq!(res.invitations[0].invitor_nick, "Bob");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_suspicious_requests() {
+        let join = |seq: u64| {
+            let msg = DynamicProtoMessage::new()
+                .with(1, 1u32)
+                .with(10, 114514u64)
+                .with(12, GROUP_MSG_JOIN_REQUEST);
+            DynamicProtoMessage::new()
+                .with(10, vec![struct_msg(seq, 12345, msg)])
+                .encode()
+                .unwrap()
+        };
+        let sender = MockSender::new(vec![join(1), join(2), Vec::new()]);
+        let res = fetch_system_messages(&sender).await.unwrap();
+        let flags: Vec<_> = res
+            .join_requests
+            .iter()
+            .map(|r| (r.request_id, r.suspicious))
+            .collect();
+        assert_eq!(flags, vec![(1, false), (2, true)]);
+
+        let req_msg_type = |idx| {
+            let (command, body) = sender.sent_body(idx);
+            assert_eq!(command, CMD_SYSTEM_MSG_NEW_GROUP);
+            ProtoReader::decode(&body).unwrap().get_u64(11)
+        };
+        assert_eq!((req_msg_type(0), req_msg_type(1)), (Some(1), Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_reject_with_reason() {
+        let ok = DynamicProtoMessage::new()
+            .with(1, DynamicProtoMessage::new().with(1, 0u32))
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![ok]);
+        let req = GroupSystemMsgAction {
+            request_id: 1,
+            requester_uin: 12345,
+            group_code: 114514,
+            is_invite: false,
+            suspicious: false,
+            action: RequestAction::Reject,
+            blacklist: true,
+            reason: "no",
+        };
+        solve_group_request(&sender, &req).await.unwrap();
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SYSTEM_MSG_ACTION_GROUP);
+        let body = ProtoReader::decode(&body).unwrap();
+        assert_eq!(body.get_u64(7), Some(GROUP_MSG_JOIN_REQUEST));
+        let info = body.get_message(8).unwrap().unwrap();
+        assert_eq!(info.get_u64(1), Some(12));
+        assert_eq!(info.get_string(50), Some("no".to_string()));
+        assert_eq!(info.get_u64(53), Some(1));
+    }
+
+    #[test]
+    fn test_friend_action_reason() {
+        let req =
+            build_friend_system_msg_action(1, 12345, RequestAction::Reject, false, "no").unwrap();
+        let info = ProtoReader::decode(&req)
+            .unwrap()
+            .get_message(8)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.get_u64(1), Some(3));
+        assert_eq!(info.get_string(50), Some("no".to_string()));
+
+        let req =
+            build_friend_system_msg_action(1, 12345, RequestAction::Ignore, true, "no").unwrap();
+        let info = ProtoReader::decode(&req)
+            .unwrap()
+            .get_message(8)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.get_string(50), Some(String::new()));
+        assert_eq!(info.get_u64(53), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_action_failure() {
+        let fail = DynamicProtoMessage::new()
+            .with(
+                1,
+                DynamicProtoMessage::new()
+                    .with(1, 1u32)
+                    .with(2, "expired".to_string()),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![fail]);
+        let res = solve_friend_request(&sender, 1, 12345, RequestAction::Accept, false, "").await;
+        assert!(matches!(res, Err(NetworkError::Server { code: 1, .. })));
+    }
+}
@@ -0,0 +1,325 @@
+//! 短视频上传与下载
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/ptt.go)
+
+use std::io;
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    message::{image::ImageInfo, video::ShortVideo, MessageTarget},
+    utils::crypto::md5_digest,
+};
+
+use super::{
+    download,
+    highway::{HighwaySession, Transaction},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_SHORT_VIDEO_UP: &str = "PttCenterSvc.ShortVideoUpReq";
+pub const CMD_SHORT_VIDEO_DOWN: &str = "PttCenterSvc.ShortVideoDownReq";
+
+const HIGHWAY_SHORT_VIDEO: u32 = 25;
+const SUB_CMD_UPLOAD: u32 = 300;
+const SUB_CMD_DOWNLOAD: u32 = 400;
+
+fn chat_type(target: MessageTarget) -> (u64, u32, u64) {
+    match target {
+        MessageTarget::Group(code) => (code, 1, code),
+        MessageTarget::Friend(uin) => (uin, 0, 0),
+    }
+}
+
+/// `ShortVideoReqBody`，同时作为 highway 上传时的 ext
+pub fn build_short_video_up(
+    seq: u32,
+    uin: u64,
+    target: MessageTarget,
+    video: &ShortVideo,
+) -> io::Result<Vec<u8>> {
+    let (to_uin, chat_type, group_code) = chat_type(target);
+    DynamicProtoMessage::new()
+        .with(1, SUB_CMD_UPLOAD)
+        .with(2, seq)
+        .with(
+            3,
+            DynamicProtoMessage::new()
+                .with(1, uin)
+                .with(2, to_uin)
+                .with(3, chat_type)
+                .with(4, 2u32)
+                .with(
+                    5,
+                    DynamicProtoMessage::new()
+                        .with(1, video.name.clone())
+                        .with(2, video.md5.to_vec())
+                        .with(3, video.thumb_md5.to_vec())
+                        .with(4, video.size as u64)
+                        .with(5, 1280u32)
+                        .with(6, 720u32)
+                        .with(7, 3u32)
+                        .with(8, 120u32)
+                        .with(9, video.thumb_size as u64),
+                )
+                .with(6, group_code)
+                .with(9, 1u32),
+        )
+        .with(
+            4,
+            vec![DynamicProtoMessage::new()
+                .with(1, SUB_CMD_UPLOAD)
+                .with(3, 0u32)
+                .with(4, 1u32)],
+        )
+        .encode()
+}
+
+/// 解析 `ShortVideoRspBody.ptt_short_video_upload_rsp`，返回服务器已有的 file id
+pub fn decode_short_video_up(payload: &[u8]) -> NetworkResult<Option<Vec<u8>>> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(3)?
+        .unwrap_or_default();
+    check_ret(CMD_SHORT_VIDEO_UP, &rsp)?;
+    Ok(match rsp.get_u64(7).unwrap_or_default() {
+        1 => rsp.get_bytes(5).map(|id| id.to_vec()),
+        _ => None,
+    })
+}
+
+fn check_ret(command: &str, rsp: &ProtoReader) -> NetworkResult<()> {
+    match rsp.get_i64(1).unwrap_or_default() as i32 {
+        0 => Ok(()),
+        code => Err(NetworkError::Server {
+            command: command.to_string(),
+            code,
+            message: rsp.get_string(2).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 上传短视频与缩略图，缩略图需为可识别的图片格式
+///
+/// 服务器已有相同视频时跳过上传，否则缩略图与视频拼接后一同经 highway 上传
+pub async fn upload_short_video<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: MessageTarget,
+    video: Vec<u8>,
+    thumb: Vec<u8>,
+) -> NetworkResult<ShortVideo> {
+    let info = ImageInfo::detect(&thumb).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "unsupported thumbnail format")
+    })?;
+    let mut elem = ShortVideo::new(
+        md5_digest(&video),
+        video.len() as u32,
+        md5_digest(&thumb),
+        thumb.len() as u32,
+    );
+    (elem.thumb_width, elem.thumb_height) = (info.width, info.height);
+
+    let seq = rand::random::<u16>() as u32;
+    let req = build_short_video_up(seq, highway.uin, target, &elem)?;
+    let rsp = send_uni_request(sender, CMD_SHORT_VIDEO_UP, &req).await?;
+    if let Some(uuid) = decode_short_video_up(&rsp)? {
+        elem.uuid = uuid;
+        return Ok(elem);
+    }
+
+    let mut body = thumb;
+    body.extend_from_slice(&video);
+    let trans =
+        Transaction::new(HIGHWAY_SHORT_VIDEO, highway.sig_session.clone(), body).with_ext(req);
+    let rsp = highway.upload(&highway.servers, trans, 0).await?;
+    let rsp = ProtoReader::decode(&rsp)?
+        .get_message(3)?
+        .unwrap_or_default();
+    check_ret(CMD_SHORT_VIDEO_UP, &rsp)?;
+    elem.uuid = rsp
+        .get_bytes(5)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty short video file id"))?
+        .to_vec();
+    Ok(elem)
+}
+
+pub fn build_short_video_down(
+    seq: u32,
+    uin: u64,
+    target: MessageTarget,
+    video: &ShortVideo,
+) -> io::Result<Vec<u8>> {
+    let (to_uin, _, group_code) = chat_type(target);
+    DynamicProtoMessage::new()
+        .with(1, SUB_CMD_DOWNLOAD)
+        .with(2, seq)
+        .with(
+            4,
+            DynamicProtoMessage::new()
+                .with(1, uin)
+                .with(2, to_uin)
+                .with(3, 1u32)
+                .with(4, 7u32)
+                .with(5, video.file_id())
+                .with(6, group_code)
+                .with(8, video.md5.to_vec())
+                .with(9, 1u32)
+                .with(10, 2u32)
+                .with(11, 2u32)
+                .with(12, 2u32),
+        )
+        .encode()
+}
+
+/// 解析 `ShortVideoRspBody.ptt_short_video_download_rsp`，返回下载地址
+pub fn decode_short_video_down(payload: &[u8]) -> NetworkResult<String> {
+    let rsp = ProtoReader::decode(payload)?
+        .get_message(4)?
+        .unwrap_or_default();
+    check_ret(CMD_SHORT_VIDEO_DOWN, &rsp)?;
+    let addr = rsp.get_message(9)?.unwrap_or_default();
+    let host = addr
+        .get_repeated_bytes(10)
+        .first()
+        .map(|h| String::from_utf8_lossy(h).to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no download host"))?;
+    let args = addr.get_string(20).unwrap_or_default();
+    if host.starts_with("http") {
+        Ok(format!("{}{}", host, args))
+    } else {
+        Ok(format!("http://{}{}", host, args))
+    }
+}
+
+/// 获取收到的短视频的下载地址
+pub async fn query_short_video_url<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    target: MessageTarget,
+    video: &ShortVideo,
+) -> NetworkResult<String> {
+    let req = build_short_video_down(rand::random::<u16>() as u32, uin, target, video)?;
+    let rsp = send_uni_request(sender, CMD_SHORT_VIDEO_DOWN, &req).await?;
+    decode_short_video_down(&rsp)
+}
+
+pub async fn download_short_video<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    target: MessageTarget,
+    video: &ShortVideo,
+    max_size: usize,
+) -> NetworkResult<Vec<u8>> {
+    let url = query_short_video_url(sender, uin, target, video).await?;
+    download(&url, max_size, Some(&video.md5)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::{
+        highway::test::stand_in_with,
+        test::{http_stand_in, MockSender},
+    };
+
+    fn thumb() -> Vec<u8> {
+        let mut data = b"GIF89a\x40\x01\xf0\x00".to_vec();
+        data.extend([0u8; 64]);
+        data
+    }
+
+    fn upload_rsp(exist: bool) -> Vec<u8> {
+        DynamicProtoMessage::new()
+            .with(
+                3,
+                DynamicProtoMessage::new()
+                    .with(1, 0u32)
+                    .with(5, b"video-uuid".to_vec())
+                    .with(7, exist as u32),
+            )
+            .encode()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_short_video() {
+        let (addr, received) = stand_in_with(None, upload_rsp(false)).await;
+        let sender = MockSender::new(vec![DynamicProtoMessage::new()
+            .with(3, DynamicProtoMessage::new().with(1, 0u32))
+            .encode()
+            .unwrap()]);
+        let mut highway = HighwaySession::new(12345, 537066738, b"sig".to_vec());
+        highway.servers = vec![addr];
+
+        let video = vec![7u8; 3000];
+        let elem = upload_short_video(
+            &sender,
+            &highway,
+            MessageTarget::Group(114514),
+            video.clone(),
+            thumb(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(elem.file_id(), "video-uuid");
+
+        let mut expected = thumb();
+        expected.extend_from_slice(&video);
+        assert_eq!(*received.lock().unwrap(), expected);
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_SHORT_VIDEO_UP);
+        let req = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.get_u64(6), Some(114514));
+        let info = req.get_message(5).unwrap().unwrap();
+        assert_eq!(info.get_bytes(2), Some(&md5_digest(&video)[..]));
+        assert_eq!(info.get_u64(9), Some(thumb().len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_skip_existing_video() {
+        let sender = MockSender::new(vec![upload_rsp(true)]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+        let elem = upload_short_video(
+            &sender,
+            &highway,
+            MessageTarget::Friend(10086),
+            vec![1u8; 100],
+            thumb(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(elem.file_id(), "video-uuid");
+        assert_eq!((elem.thumb_width, elem.thumb_height), (320, 240));
+    }
+
+    #[tokio::test]
+    async fn test_download_short_video() {
+        let data = vec![9u8; 1000];
+        let url = http_stand_in(data.clone()).await;
+        let (host, args) = url.trim_start_matches("http://").split_once('/').unwrap();
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                4,
+                DynamicProtoMessage::new().with(1, 0u32).with(
+                    9,
+                    DynamicProtoMessage::new()
+                        .with(10, host.to_string())
+                        .with(20, format!("/{}", args)),
+                ),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let mut video = ShortVideo::new(md5_digest(&data), 1000, [0u8; 16], 0);
+        video.uuid = b"video-uuid".to_vec();
+
+        let res = download_short_video(&sender, 12345, MessageTarget::Group(1), &video, 4096)
+            .await
+            .unwrap();
+        assert_eq!(res, data);
+    }
+}
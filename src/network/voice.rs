@@ -0,0 +1,353 @@
+//! 语音上传与下载
+//! [参考](https://github.com/Mrs4s/MiraiGo/blob/master/client/ptt.go)
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::{
+    binary::protobuf::{DynamicProtoMessage, ProtoReader},
+    message::{
+        voice::{voice_duration, Voice, VoiceFormat},
+        MessageTarget,
+    },
+    utils::crypto::md5_digest,
+};
+
+use super::{
+    cmd0x346::{
+        build_ptt_apply_download, build_ptt_apply_upload, decode_apply_download_rsp,
+        decode_apply_upload_rsp, ApplyUploadReq,
+    },
+    download,
+    highway::{HighwaySession, Transaction, UploadTicket},
+    send_uni_request, NetworkError, NetworkResult, SsoSender,
+};
+
+pub const CMD_GROUP_PTT_UP: &str = "PttStore.GroupPttUp";
+pub const CMD_GROUP_PTT_DOWN: &str = "PttStore.GroupPttDown";
+pub const CMD_C2C_PTT_UP: &str = "PttCenterSvc.pb_pttCenter_CMD_REQ_APPLY_UPLOAD-500";
+pub const CMD_C2C_PTT_DOWN: &str = "PttCenterSvc.pb_pttCenter_CMD_REQ_APPLY_DOWNLOAD-1200";
+
+const HIGHWAY_C2C_PTT: u32 = 26;
+const HIGHWAY_GROUP_PTT: u32 = 29;
+const GROUP_PTT_DOMAIN: &str = "http://grouptalk.c2c.qq.com";
+
+/// `ApplyUploadReq.file_type` 中的语音类型
+const FILE_TYPE_PTT: u32 = 2;
+
+/// 申请上传群语音的结果
+#[derive(Debug, Clone, Default)]
+pub struct PttUploadApply {
+    pub exists: bool,
+    pub ticket: UploadTicket,
+    pub file_id: u64,
+    pub file_key: String,
+}
+
+pub fn build_group_ptt_up(uin: u64, group_code: u64, voice: &Voice) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 3u32)
+        .with(2, 3u32)
+        .with(
+            5,
+            vec![DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, uin)
+                .with(4, voice.md5.to_vec())
+                .with(5, voice.size)
+                .with(6, voice.name.clone())
+                .with(7, 5u32)
+                .with(8, 9u32)
+                .with(9, 4u32)
+                .with(10, "6.5.5.663".to_string())
+                .with(12, voice.duration)
+                .with(13, true)
+                .with(14, voice.format.code())
+                .with(15, 1u32)],
+        )
+        .encode()
+}
+
+/// 解析 `D388RspBody.tryup_ptt_rsp`
+pub fn decode_group_ptt_up(payload: &[u8]) -> NetworkResult<PttUploadApply> {
+    let rsp = ProtoReader::decode(payload)?;
+    let try_up = rsp.get_repeated_message(5)?.pop().unwrap_or_default();
+    match try_up.get_i64(2).unwrap_or_default() as i32 {
+        0 => Ok(PttUploadApply {
+            exists: try_up.get_u64(4).unwrap_or_default() != 0,
+            ticket: UploadTicket::from_proto(&try_up, (5, 6, 7, 9)),
+            file_id: try_up.get_u64(8).unwrap_or_default(),
+            file_key: try_up.get_string(11).unwrap_or_default(),
+        }),
+        code => Err(NetworkError::Server {
+            command: CMD_GROUP_PTT_UP.to_string(),
+            code,
+            message: try_up.get_string(3).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 上传 SILK 或 AMR 语音，PCM 与 WAV 需要先通过
+/// [`encode_voice`](crate::message::voice::encode_voice) 转码
+pub async fn upload_voice<S: SsoSender>(
+    sender: &S,
+    highway: &HighwaySession,
+    target: MessageTarget,
+    data: Vec<u8>,
+) -> NetworkResult<Voice> {
+    let format = VoiceFormat::detect(&data)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "voice must be silk or amr"))?;
+    let mut voice = Voice::new(
+        md5_digest(&data),
+        data.len() as u32,
+        format,
+        voice_duration(&data, format),
+    );
+
+    match target {
+        MessageTarget::Group(code) => {
+            let req = build_group_ptt_up(highway.uin, code, &voice)?;
+            let rsp = send_uni_request(sender, CMD_GROUP_PTT_UP, &req).await?;
+            let apply = decode_group_ptt_up(&rsp)?;
+            if !apply.exists {
+                let trans = Transaction::new(HIGHWAY_GROUP_PTT, apply.ticket.ukey.clone(), data);
+                highway
+                    .upload(&apply.ticket.servers, trans, apply.ticket.offset)
+                    .await?;
+            }
+            voice.file_id = apply.file_id;
+            voice.file_key = apply.file_key;
+        }
+        MessageTarget::Friend(uin) => {
+            let req = ApplyUploadReq {
+                sender_uin: highway.uin,
+                recver_uin: uin,
+                file_type: FILE_TYPE_PTT,
+                file_size: voice.size as u64,
+                file_name: &voice.name,
+                md5: &voice.md5,
+            };
+            // 先申请上传以获得上传服务器，同样的请求体再作为 highway 的 ext
+            let ext = build_ptt_apply_upload(rand::random::<u16>() as u32, &req, voice.duration)?;
+            let rsp = send_uni_request(sender, CMD_C2C_PTT_UP, &ext).await?;
+            let apply = decode_apply_upload_rsp(CMD_C2C_PTT_UP, &rsp)?;
+            if !apply.exists {
+                let servers = match apply.upload_ip.parse::<IpAddr>() {
+                    Ok(ip) => vec![SocketAddr::new(ip, apply.upload_port)],
+                    Err(_) => highway.servers.clone(),
+                };
+                let trans = Transaction::new(HIGHWAY_C2C_PTT, highway.sig_session.clone(), data)
+                    .with_ext(ext);
+                highway.upload(&servers, trans, 0).await?;
+            }
+            voice.file_uuid = apply.uuid;
+        }
+    }
+    Ok(voice)
+}
+
+pub fn build_group_ptt_down(uin: u64, group_code: u64, voice: &Voice) -> io::Result<Vec<u8>> {
+    DynamicProtoMessage::new()
+        .with(1, 3u32)
+        .with(2, 4u32)
+        .with(
+            6,
+            vec![DynamicProtoMessage::new()
+                .with(1, group_code)
+                .with(2, uin)
+                .with(3, voice.file_id)
+                .with(4, voice.md5.to_vec())
+                .with(5, 5u32)
+                .with(6, 9u32)
+                .with(8, 4u32)
+                .with(11, voice.file_key.clone())
+                .with(15, 1u32)],
+        )
+        .encode()
+}
+
+/// 解析 `D388RspBody.getptt_url_rsp`
+pub fn decode_group_ptt_down(payload: &[u8]) -> NetworkResult<String> {
+    let rsp = ProtoReader::decode(payload)?;
+    let url_rsp = rsp.get_repeated_message(6)?.pop().unwrap_or_default();
+    match url_rsp.get_i64(3).unwrap_or_default() as i32 {
+        0 => Ok(format!(
+            "http://{}{}",
+            url_rsp.get_string(8).unwrap_or_default(),
+            url_rsp.get_string(9).unwrap_or_default()
+        )),
+        code => Err(NetworkError::Server {
+            command: CMD_GROUP_PTT_DOWN.to_string(),
+            code,
+            message: url_rsp.get_string(4).unwrap_or_default(),
+        }),
+    }
+}
+
+/// 获取收到的语音的下载地址
+///
+/// 群语音若消息中携带了下载参数则无需请求服务器
+pub async fn query_voice_url<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    target: MessageTarget,
+    voice: &Voice,
+) -> NetworkResult<String> {
+    match target {
+        MessageTarget::Group(_) if !voice.url.is_empty() => {
+            Ok(format!("{}{}", GROUP_PTT_DOMAIN, voice.url))
+        }
+        MessageTarget::Group(code) => {
+            let req = build_group_ptt_down(uin, code, voice)?;
+            let rsp = send_uni_request(sender, CMD_GROUP_PTT_DOWN, &req).await?;
+            decode_group_ptt_down(&rsp)
+        }
+        MessageTarget::Friend(_) => {
+            let req =
+                build_ptt_apply_download(rand::random::<u16>() as u32, uin, &voice.file_uuid)?;
+            let rsp = send_uni_request(sender, CMD_C2C_PTT_DOWN, &req).await?;
+            decode_apply_download_rsp(CMD_C2C_PTT_DOWN, &rsp)
+        }
+    }
+}
+
+/// 下载语音的原始数据，可以再通过
+/// [`decode_voice`](crate::message::voice::decode_voice) 转为 PCM
+pub async fn download_voice<S: SsoSender>(
+    sender: &S,
+    uin: u64,
+    target: MessageTarget,
+    voice: &Voice,
+    max_size: usize,
+) -> NetworkResult<Vec<u8>> {
+    let url = query_voice_url(sender, uin, target, voice).await?;
+    download(&url, max_size, Some(&voice.md5)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::{
+        highway::test::stand_in,
+        test::{http_stand_in, MockSender},
+    };
+
+    fn silk() -> Vec<u8> {
+        let mut data = b"\x02#!SILK_V3".to_vec();
+        for _ in 0..100 {
+            data.extend_from_slice(&[3, 0, 1, 2, 3]);
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_upload_existing_group_voice() {
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                5,
+                vec![DynamicProtoMessage::new()
+                    .with(2, 0u32)
+                    .with(4, true)
+                    .with(8, 77u64)
+                    .with(11, "file-key".to_string())],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+
+        let voice = upload_voice(&sender, &highway, MessageTarget::Group(114514), silk())
+            .await
+            .unwrap();
+        assert_eq!(voice.format, VoiceFormat::Silk);
+        assert_eq!(voice.duration, 2);
+        assert_eq!(voice.file_id, 77);
+        assert_eq!(voice.file_key, "file-key");
+
+        let (_, body) = sender.sent_body(0);
+        let req = ProtoReader::decode(&body).unwrap();
+        let try_up = req.get_message(5).unwrap().unwrap();
+        assert_eq!(try_up.get_u64(14), Some(1));
+        assert_eq!(try_up.get_u64(12), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_upload_friend_voice() {
+        let (addr, received) = stand_in(None).await;
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                7,
+                DynamicProtoMessage::new()
+                    .with(10, 0u32)
+                    .with(60, addr.ip().to_string())
+                    .with(80, addr.port() as u32)
+                    .with(90, b"ptt-uuid".to_vec()),
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        // 未设置默认服务器，只能使用申请结果中的服务器
+        let highway = HighwaySession::new(12345, 537066738, b"sig".to_vec());
+
+        let data = silk();
+        let voice = upload_voice(
+            &sender,
+            &highway,
+            MessageTarget::Friend(10086),
+            data.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(voice.file_uuid, b"ptt-uuid");
+        assert_eq!(*received.lock().unwrap(), data);
+
+        let (command, body) = sender.sent_body(0);
+        assert_eq!(command, CMD_C2C_PTT_UP);
+        let req = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.get_u64(20), Some(10086));
+        assert_eq!(req.get_bytes(60), Some(&voice.md5[..]));
+    }
+
+    #[tokio::test]
+    async fn test_download_group_voice() {
+        let data = silk();
+        let url = http_stand_in(data.clone()).await;
+        let rsp = DynamicProtoMessage::new()
+            .with(
+                6,
+                vec![DynamicProtoMessage::new()
+                    .with(3, 0u32)
+                    .with(8, url.trim_start_matches("http://").to_string())
+                    .with(9, String::new())],
+            )
+            .encode()
+            .unwrap();
+        let sender = MockSender::new(vec![rsp]);
+        let voice = Voice::new(md5_digest(&data), 0, VoiceFormat::Silk, 1);
+
+        let res = download_voice(&sender, 12345, MessageTarget::Group(1), &voice, 4096)
+            .await
+            .unwrap();
+        assert_eq!(res, data);
+    }
+
+    #[tokio::test]
+    async fn test_reject_unknown_format() {
+        let sender = MockSender::new(Vec::new());
+        let highway = HighwaySession::new(12345, 537066738, Vec::new());
+        let res = upload_voice(
+            &sender,
+            &highway,
+            MessageTarget::Friend(1),
+            b"RIFF".to_vec(),
+        )
+        .await;
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,584 @@
+//! OneBot API 的请求与响应，以及各 action 到客户端接口的映射
+//! [参考](https://github.com/botuniverse/onebot-11/blob/master/api/public.md)
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    adapter::{load_file, StoredReceipt},
+    events::Event,
+    message::{
+        rich::{LightApp, ServiceMessage},
+        MessageChain, MessageTarget,
+    },
+    network::{
+        account::get_self_profile,
+        group_admin::{get_group_member_list, mute_all, mute_member},
+        image::upload_image,
+        message::{recall_message, send_message},
+        multi_msg::pack_long_message,
+        profile::{
+            get_group_info, get_group_member_info, get_user_profile, Gender, GroupMemberInfo,
+            MemberPermission,
+        },
+        NetworkError, SsoSender,
+    },
+};
+
+use super::{cqcode, OneBot};
+
+/// `set_group_ban` 未指定时长时的默认值
+const DEFAULT_BAN_DURATION: u64 = 30 * 60;
+/// xml 消息默认的 service id
+const DEFAULT_XML_SERVICE_ID: u32 = 60;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActionRequest {
+    pub action: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub echo: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionResponse {
+    pub status: &'static str,
+    pub retcode: i32,
+    pub data: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<Value>,
+}
+
+#[derive(Debug)]
+pub enum ActionError {
+    /// 缺少参数或参数类型错误
+    BadParams(String),
+    Unsupported(String),
+    Failed(String),
+}
+
+impl ActionError {
+    pub fn retcode(&self) -> i32 {
+        match self {
+            ActionError::BadParams(_) => 100,
+            ActionError::Failed(_) => 102,
+            ActionError::Unsupported(_) => 1404,
+        }
+    }
+}
+
+impl Display for ActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionError::BadParams(msg) => write!(f, "bad params: {}", msg),
+            ActionError::Unsupported(action) => write!(f, "unsupported action: {}", action),
+            ActionError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<NetworkError> for ActionError {
+    fn from(err: NetworkError) -> Self {
+        ActionError::Failed(err.to_string())
+    }
+}
+
+impl ActionResponse {
+    pub fn ok(data: Value) -> Self {
+        Self {
+            status: "ok",
+            retcode: 0,
+            data,
+            msg: None,
+            echo: None,
+        }
+    }
+
+    pub fn failed(err: ActionError) -> Self {
+        Self {
+            status: "failed",
+            retcode: err.retcode(),
+            data: Value::Null,
+            msg: Some(err.to_string()),
+            echo: None,
+        }
+    }
+}
+
+type ActionResult = Result<Value, ActionError>;
+
+/// 数字参数也可能以字符串形式传递
+fn u64_param(params: &Value, key: &str) -> Result<u64, ActionError> {
+    match params.get(key) {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| ActionError::BadParams(key.to_string()))
+}
+
+fn opt_u64_param(params: &Value, key: &str) -> Result<Option<u64>, ActionError> {
+    match params.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => u64_param(params, key).map(Some),
+    }
+}
+
+fn bool_param(params: &Value, key: &str, default: bool) -> bool {
+    match params.get(key) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => s == "true" || s == "1",
+        Some(Value::Number(n)) => n.as_u64() != Some(0),
+        _ => default,
+    }
+}
+
+fn str_param<'a>(params: &'a Value, key: &str) -> Result<&'a str, ActionError> {
+    params
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| ActionError::BadParams(key.to_string()))
+}
+
+fn gender_str(gender: Gender) -> &'static str {
+    match gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Unknown => "unknown",
+    }
+}
+
+fn member_json(member: &GroupMemberInfo) -> Value {
+    json!({
+        "group_id": member.group_code,
+        "user_id": member.uin,
+        "nickname": member.nick,
+        "card": member.card,
+        "sex": gender_str(member.gender),
+        "age": 0,
+        "join_time": member.join_time,
+        "last_sent_time": member.last_speak_time,
+        "level": member.level.to_string(),
+        "role": match member.permission {
+            MemberPermission::Owner => "owner",
+            MemberPermission::Administrator => "admin",
+            MemberPermission::Member => "member",
+        },
+        "title": member.special_title,
+    })
+}
+
+impl<S: SsoSender + Sync> OneBot<S> {
+    /// 处理一次 API 调用，`echo` 原样带回
+    pub async fn handle_action(&self, req: ActionRequest) -> ActionResponse {
+        let mut rsp = match self.dispatch(&req.action, &req.params).await {
+            Ok(data) => ActionResponse::ok(data),
+            Err(err) => ActionResponse::failed(err),
+        };
+        rsp.echo = req.echo;
+        rsp
+    }
+
+    async fn dispatch(&self, action: &str, params: &Value) -> ActionResult {
+        match action {
+            "send_private_msg" => {
+                let target = MessageTarget::Friend(u64_param(params, "user_id")?);
+                self.send_msg(target, params).await
+            }
+            "send_group_msg" => {
+                let target = MessageTarget::Group(u64_param(params, "group_id")?);
+                self.send_msg(target, params).await
+            }
+            "send_msg" => {
+                let group = opt_u64_param(params, "group_id")?;
+                let target = match params.get("message_type").and_then(Value::as_str) {
+                    Some("private") => MessageTarget::Friend(u64_param(params, "user_id")?),
+                    Some("group") => MessageTarget::Group(u64_param(params, "group_id")?),
+                    _ => match group {
+                        Some(code) => MessageTarget::Group(code),
+                        None => MessageTarget::Friend(u64_param(params, "user_id")?),
+                    },
+                };
+                self.send_msg(target, params).await
+            }
+            "delete_msg" => {
+                let id = u64_param(params, "message_id")? as i32;
+                let receipt = match self.receipt(id) {
+                    Some(StoredReceipt::Ready(receipt)) => receipt,
+                    Some(StoredReceipt::Pending(_)) => {
+                        return Err(ActionError::Failed(format!(
+                            "message {} is not confirmed by the server yet",
+                            id
+                        )))
+                    }
+                    None => return Err(ActionError::Failed(format!("message {} not found", id))),
+                };
+                recall_message(&self.sender, self.uin, &receipt).await?;
+                Ok(Value::Null)
+            }
+            "get_login_info" => {
+                let profile = get_self_profile(&self.sender, self.uin).await?;
+                Ok(json!({ "user_id": self.uin, "nickname": profile.nick }))
+            }
+            "get_stranger_info" => {
+                let profile = get_user_profile(&self.sender, u64_param(params, "user_id")?).await?;
+                Ok(json!({
+                    "user_id": profile.uin,
+                    "nickname": profile.nick,
+                    "sex": gender_str(profile.gender),
+                    "age": profile.age,
+                }))
+            }
+            "get_group_info" => {
+                let code = u64_param(params, "group_id")?;
+                let info = get_group_info(&self.sender, self.app_id, code).await?;
+                Ok(json!({
+                    "group_id": info.group_code,
+                    "group_name": info.name,
+                    "member_count": info.member_count,
+                    "max_member_count": info.max_member_count,
+                }))
+            }
+            "get_group_member_info" => {
+                let code = u64_param(params, "group_id")?;
+                let uin = u64_param(params, "user_id")?;
+                let member = get_group_member_info(&self.sender, code, uin)
+                    .await?
+                    .ok_or_else(|| ActionError::Failed(format!("member {} not found", uin)))?;
+                Ok(member_json(&member))
+            }
+            "get_group_member_list" => {
+                let code = u64_param(params, "group_id")?;
+                let info = get_group_info(&self.sender, self.app_id, code).await?;
+                let members =
+                    get_group_member_list(&self.sender, self.uin, code, info.owner_uin).await?;
+                Ok(members.iter().map(member_json).collect())
+            }
+            "set_group_ban" => {
+                let code = u64_param(params, "group_id")?;
+                let uin = u64_param(params, "user_id")?;
+                let duration = opt_u64_param(params, "duration")?.unwrap_or(DEFAULT_BAN_DURATION);
+                mute_member(&self.sender, code, uin, duration as u32).await?;
+                Ok(Value::Null)
+            }
+            "set_group_whole_ban" => {
+                let code = u64_param(params, "group_id")?;
+                mute_all(&self.sender, code, bool_param(params, "enable", true)).await?;
+                Ok(Value::Null)
+            }
+            "set_friend_add_request" => {
+                let (flag, Event::NewFriendRequest(req)) = self.request(params)? else {
+                    return Err(ActionError::BadParams("flag".to_string()));
+                };
+                if bool_param(params, "approve", true) {
+                    req.accept(&self.sender).await?;
+                } else {
+                    req.reject(&self.sender, false, "").await?;
+                }
+                self.remove_request(flag);
+                Ok(Value::Null)
+            }
+            "set_group_add_request" => {
+                let approve = bool_param(params, "approve", true);
+                let (flag, request) = self.request(params)?;
+                let reason = params
+                    .get("reason")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                match request {
+                    Event::MemberJoinRequest(req) if approve => req.accept(&self.sender).await?,
+                    Event::MemberJoinRequest(req) => {
+                        req.reject(&self.sender, false, reason).await?
+                    }
+                    Event::BotInvitedJoinGroupRequest(req) if approve => {
+                        req.accept(&self.sender).await?
+                    }
+                    Event::BotInvitedJoinGroupRequest(req) => {
+                        req.reject(&self.sender, false, reason).await?
+                    }
+                    _ => return Err(ActionError::BadParams("flag".to_string())),
+                }
+                self.remove_request(flag);
+                Ok(Value::Null)
+            }
+            "can_send_image" => Ok(json!({ "yes": self.highway.is_some() })),
+            "can_send_record" => Ok(json!({ "yes": false })),
+            "get_status" => Ok(json!({ "online": true, "good": true })),
+            "get_version_info" => Ok(json!({
+                "app_name": env!("CARGO_PKG_NAME"),
+                "app_version": env!("CARGO_PKG_VERSION"),
+                "protocol_version": "v11",
+            })),
+            _ => Err(ActionError::Unsupported(action.to_string())),
+        }
+    }
+
+    /// 查找 `flag` 对应的申请，处理成功后才需要通过 `remove_request` 移除
+    fn request<'a>(&self, params: &'a Value) -> Result<(&'a str, Event), ActionError> {
+        let flag = str_param(params, "flag")?;
+        let request = self
+            .find_request(flag)
+            .ok_or_else(|| ActionError::Failed(format!("request {} not found", flag)))?;
+        Ok((flag, request))
+    }
+
+    async fn send_msg(&self, target: MessageTarget, params: &Value) -> ActionResult {
+        let message = params
+            .get("message")
+            .ok_or_else(|| ActionError::BadParams("message".to_string()))?;
+        let segments = cqcode::parse_message(message, bool_param(params, "auto_escape", false))
+            .ok_or_else(|| ActionError::BadParams("message".to_string()))?;
+        let mut chain = self.segments_to_chain(target, &segments).await?;
+        if chain.is_empty() {
+            return Err(ActionError::BadParams("message is empty".to_string()));
+        }
+        if let Some(highway) = &self.highway {
+            chain = pack_long_message(&self.sender, highway, target, chain).await?;
+        }
+        let receipt = send_message(&self.sender, target, self.next_seq(), &chain).await?;
+        Ok(json!({ "message_id": self.store_sent(receipt) }))
+    }
+
+    /// 暂不支持的消息段 (`at`、`face`、`reply`、`record`、`video` 等) 返回错误，
+    /// 以免发出残缺的消息
+    async fn segments_to_chain(
+        &self,
+        target: MessageTarget,
+        segments: &[cqcode::Segment],
+    ) -> Result<MessageChain, ActionError> {
+        let mut chain = MessageChain::new();
+        for segment in segments {
+            let data = |key| {
+                segment
+                    .get(key)
+                    .ok_or_else(|| ActionError::BadParams(format!("{}.{}", segment.kind, key)))
+            };
+            match segment.kind.as_str() {
+                "text" => chain.push(data("text")?),
+                "image" => {
+                    let highway = self.highway.as_ref().ok_or_else(|| {
+                        ActionError::Failed("image upload is not available".to_string())
+                    })?;
+                    let file = segment.get("url").map_or_else(|| data("file"), Ok)?;
+                    let image = load_file(file).await?;
+                    chain.push(upload_image(&self.sender, highway, target, image).await?);
+                }
+                "json" => chain.push(LightApp {
+                    content: data("data")?.to_string(),
+                }),
+                "xml" => chain.push(ServiceMessage {
+                    service_id: segment
+                        .get("resid")
+                        .and_then(|id| id.parse().ok())
+                        .unwrap_or(DEFAULT_XML_SERVICE_ID),
+                    content: data("data")?.to_string(),
+                }),
+                kind => {
+                    return Err(ActionError::BadParams(format!(
+                        "unsupported segment: {}",
+                        kind
+                    )))
+                }
+            }
+        }
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binary::protobuf::{DynamicProtoMessage, ProtoReader},
+        events::{
+            message::{GroupMessageEvent, SelfMessageEvent},
+            request::NewFriendRequestEvent,
+        },
+        network::{message::CMD_MSG_WITHDRAW, test::MockSender},
+        onebot::OneBotConfig,
+    };
+
+    fn ok_rsp() -> Vec<u8> {
+        DynamicProtoMessage::new().with(1, 0u32).encode().unwrap()
+    }
+
+    fn request(action: &str, params: Value) -> ActionRequest {
+        ActionRequest {
+            action: action.to_string(),
+            params,
+            echo: Some(json!("e1")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_and_delete_group_msg() {
+        let withdraw = DynamicProtoMessage::new()
+            .with(2, vec![DynamicProtoMessage::new().with(1, 0u32)])
+            .encode()
+            .unwrap();
+        let bot = OneBot::new(
+            MockSender::new(vec![ok_rsp(), withdraw]),
+            10086,
+            16,
+            OneBotConfig::default(),
+        );
+        let params = json!({ "group_id": "10001", "message": "hi [CQ:json,data={}]" });
+        let rsp = bot.handle_action(request("send_group_msg", params)).await;
+        assert_eq!(rsp.retcode, 0);
+        assert_eq!(rsp.echo, Some(json!("e1")));
+
+        let (_, body) = bot.sender.sent_body(0);
+        let rich = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(3)
+            .unwrap()
+            .unwrap()
+            .get_message(1)
+            .unwrap()
+            .unwrap();
+        let chain = MessageChain::from_rich_text(&rich).unwrap();
+        assert_eq!(chain.summary(), "hi [卡片]");
+
+        // 服务器分配的 seq 推送回来之前无法撤回
+        let id = rsp.data["message_id"].clone();
+        let rsp = bot
+            .handle_action(request("delete_msg", json!({ "message_id": id })))
+            .await;
+        assert_ne!(rsp.retcode, 0);
+
+        let random = ProtoReader::decode(&body).unwrap().get_u64(5).unwrap() as u32;
+        bot.push_event(&Event::SelfMessage(SelfMessageEvent {
+            target: MessageTarget::Group(10001),
+            seq: 233,
+            random,
+            time: 0,
+            chain: MessageChain::default(),
+        }));
+        let rsp = bot
+            .handle_action(request("delete_msg", json!({ "message_id": id })))
+            .await;
+        assert_eq!(rsp.retcode, 0);
+        let (command, body) = bot.sender.sent_body(1);
+        assert_eq!(command, CMD_MSG_WITHDRAW);
+        let info = ProtoReader::decode(&body)
+            .unwrap()
+            .get_message(2)
+            .unwrap()
+            .unwrap()
+            .get_message(4)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.get_u64(1), Some(233));
+    }
+
+    #[tokio::test]
+    async fn test_request_kept_on_failure() {
+        let failed = DynamicProtoMessage::new()
+            .with(1, vec![DynamicProtoMessage::new().with(1, 1u32)])
+            .encode()
+            .unwrap();
+        let bot = OneBot::new(
+            MockSender::new(vec![failed]),
+            10086,
+            16,
+            OneBotConfig::default(),
+        );
+        bot.push_event(&Event::NewFriendRequest(NewFriendRequestEvent {
+            request_id: 42,
+            message: String::new(),
+            requester_uin: 10010,
+            requester_nick: String::new(),
+        }));
+        let params = json!({ "flag": "42", "approve": true });
+        let rsp = bot
+            .handle_action(request("set_group_add_request", params.clone()))
+            .await;
+        assert_eq!(rsp.retcode, 100);
+        let rsp = bot
+            .handle_action(request("set_friend_add_request", params.clone()))
+            .await;
+        assert_ne!(rsp.retcode, 0);
+        let rsp = bot
+            .handle_action(request("set_friend_add_request", params.clone()))
+            .await;
+        assert_eq!(rsp.retcode, 0);
+        let rsp = bot
+            .handle_action(request("set_friend_add_request", params))
+            .await;
+        assert_ne!(rsp.retcode, 0);
+    }
+
+    #[tokio::test]
+    async fn test_action_errors() {
+        let bot = OneBot::new(MockSender::new(vec![]), 10086, 16, OneBotConfig::default());
+        let rsp = bot
+            .handle_action(request("send_group_msg", json!({ "message": "hi" })))
+            .await;
+        assert_eq!(rsp.retcode, 100);
+        let rsp = bot
+            .handle_action(request(
+                "send_group_msg",
+                json!({ "group_id": 10001, "message": "hi [CQ:at,qq=10010]" }),
+            ))
+            .await;
+        assert_eq!(rsp.retcode, 100);
+        assert!(bot.sender.sent.lock().unwrap().is_empty());
+        let rsp = bot
+            .handle_action(request("no_such_action", json!({})))
+            .await;
+        assert_eq!(rsp.retcode, 1404);
+        let rsp = bot
+            .handle_action(request(
+                "send_private_msg",
+                json!({ "user_id": 10010, "message": [{"type": "image", "data": {"file": "a.png"}}] }),
+            ))
+            .await;
+        assert_eq!(rsp.retcode, 102);
+    }
+
+    #[tokio::test]
+    async fn test_received_message_id() {
+        let withdraw = DynamicProtoMessage::new()
+            .with(2, vec![DynamicProtoMessage::new().with(1, 0u32)])
+            .encode()
+            .unwrap();
+        let bot = OneBot::new(
+            MockSender::new(vec![withdraw]),
+            10086,
+            16,
+            OneBotConfig::default(),
+        );
+        let mut events = bot.subscribe();
+        bot.push_event(&Event::GroupMessage(GroupMessageEvent {
+            group_code: 10001,
+            sender_uin: 10010,
+            sender_name: String::new(),
+            seq: 42,
+            random: 7,
+            time: 0,
+            chain: MessageChain::new().with("hi"),
+        }));
+        let event = events.recv().await.unwrap();
+        let rsp = bot
+            .handle_action(request(
+                "delete_msg",
+                json!({ "message_id": event["message_id"] }),
+            ))
+            .await;
+        assert_eq!(rsp.retcode, 0);
+        let (_, body) = bot.sender.sent_body(0);
+        let info = ProtoReader::decode(&body)
+            .unwrap()
+            .get_repeated_message(2)
+            .unwrap()[0]
+            .get_repeated_message(4)
+            .unwrap()
+            .remove(0);
+        assert_eq!(info.get_u64(1), Some(42));
+    }
+}
@@ -0,0 +1,213 @@
+//! OneBot 消息段与 CQ 码
+//! [参考](https://github.com/botuniverse/onebot-11/blob/master/message/string.md)
+
+use serde_json::{json, Map, Value};
+
+use crate::message::{MessageChain, MessageElement};
+
+/// 消息段，`data` 保持参数原本的顺序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: String,
+    pub data: Vec<(String, String)>,
+}
+
+impl Segment {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn text(text: &str) -> Self {
+        Self::new("text").with("text", text)
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.data.push((key.to_string(), value.into()));
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.data
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 数组格式中的一项，`{"type": .., "data": {..}}`
+    pub fn to_json(&self) -> Value {
+        let data = self
+            .data
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect::<Map<_, _>>();
+        json!({ "type": self.kind, "data": data })
+    }
+
+    /// 数组格式中的参数可能不是字符串，统一转换为字符串
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let kind = value.get("type")?.as_str()?;
+        let data = match value.get("data") {
+            Some(Value::Object(data)) => data
+                .iter()
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Some(Self {
+            kind: kind.to_string(),
+            data,
+        })
+    }
+}
+
+/// 转义文本，`in_param` 为真时同时转义逗号
+pub fn escape(text: &str, in_param: bool) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '[' => res.push_str("&#91;"),
+            ']' => res.push_str("&#93;"),
+            ',' if in_param => res.push_str("&#44;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+pub fn unescape(text: &str) -> String {
+    text.replace("&#44;", ",")
+        .replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&amp;", "&")
+}
+
+/// 解析字符串格式的消息，不完整的 CQ 码按纯文本处理
+pub fn parse(message: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = message;
+    while !rest.is_empty() {
+        let Some(start) = rest.find("[CQ:") else {
+            segments.push(Segment::text(&unescape(rest)));
+            break;
+        };
+        let Some(len) = rest[start..].find(']') else {
+            segments.push(Segment::text(&unescape(rest)));
+            break;
+        };
+        if start > 0 {
+            segments.push(Segment::text(&unescape(&rest[..start])));
+        }
+        let mut parts = rest[start + 4..start + len].split(',');
+        let mut segment = Segment::new(parts.next().unwrap_or_default());
+        for param in parts {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            segment.data.push((key.to_string(), unescape(value)));
+        }
+        segments.push(segment);
+        rest = &rest[start + len + 1..];
+    }
+    segments
+}
+
+/// 编码为字符串格式的消息
+pub fn to_string(segments: &[Segment]) -> String {
+    let mut res = String::new();
+    for segment in segments {
+        if segment.kind == "text" {
+            res.push_str(&escape(segment.get("text").unwrap_or_default(), false));
+            continue;
+        }
+        res.push_str("[CQ:");
+        res.push_str(&segment.kind);
+        for (k, v) in &segment.data {
+            res.push(',');
+            res.push_str(k);
+            res.push('=');
+            res.push_str(&escape(v, true));
+        }
+        res.push(']');
+    }
+    res
+}
+
+/// 解析 action 中的 `message` 参数，可以是字符串、单个消息段或消息段数组
+pub fn parse_message(message: &Value, auto_escape: bool) -> Option<Vec<Segment>> {
+    match message {
+        Value::String(s) if auto_escape => Some(vec![Segment::text(s)]),
+        Value::String(s) => Some(parse(s)),
+        Value::Array(arr) => arr.iter().map(Segment::from_json).collect(),
+        Value::Object(_) => Segment::from_json(message).map(|s| vec![s]),
+        _ => None,
+    }
+}
+
+/// 将收到的消息链转为消息段，无法表示的元素以摘要文本代替
+pub fn chain_to_segments(chain: &MessageChain) -> Vec<Segment> {
+    chain
+        .iter()
+        .map(|e| match e {
+            MessageElement::Text(text) => Segment::text(text),
+            MessageElement::Image(image) => Segment::new("image")
+                .with("file", image.image_id.clone())
+                .with("url", image.url.clone()),
+            MessageElement::Voice(voice) => Segment::new("record")
+                .with("file", voice.name.clone())
+                .with("url", voice.url.clone()),
+            MessageElement::ShortVideo(video) => {
+                Segment::new("video").with("file", video.name.clone())
+            }
+            MessageElement::Forward(forward) => {
+                Segment::new("forward").with("id", forward.res_id.clone())
+            }
+            MessageElement::LongMessage(long) => Segment::text(&long.brief),
+            MessageElement::LightApp(app) => Segment::new("json").with("data", app.content.clone()),
+            MessageElement::Service(service) => {
+                Segment::new("xml").with("data", service.content.clone())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cq_code() {
+        let segments = parse("hi&#91;1&#93; [CQ:image,file=a&#44;b.png,url=http://x]end[CQ:bad");
+        assert_eq!(
+            segments,
+            [
+                Segment::text("hi[1] "),
+                Segment::new("image")
+                    .with("file", "a,b.png")
+                    .with("url", "http://x"),
+                Segment::text("end[CQ:bad"),
+            ]
+        );
+        assert_eq!(
+            to_string(&segments[..2]),
+            "hi&#91;1&#93; [CQ:image,file=a&#44;b.png,url=http://x]"
+        );
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let array =
+            json!([{"type": "text", "data": {"text": "a"}}, {"type": "at", "data": {"qq": 10086}}]);
+        let segments = parse_message(&array, false).unwrap();
+        assert_eq!(segments[1].get("qq"), Some("10086"));
+        let escaped = parse_message(&json!("[CQ:face,id=1]"), true).unwrap();
+        assert_eq!(escaped, [Segment::text("[CQ:face,id=1]")]);
+    }
+}
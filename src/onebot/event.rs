@@ -0,0 +1,248 @@
+//! 将 [`Event`] 转换为 OneBot 事件
+//! [参考](https://github.com/botuniverse/onebot-11/tree/master/event)
+
+use serde_json::{json, Value};
+
+use crate::{
+    adapter::unix_now,
+    events::Event,
+    message::{MessageChain, MessageTarget},
+    network::message::MessageReceipt,
+};
+
+use super::{cqcode, MessageFormat};
+
+fn message_fields(chain: &MessageChain, format: MessageFormat) -> (Value, String) {
+    let segments = cqcode::chain_to_segments(chain);
+    let raw = cqcode::to_string(&segments);
+    let message = match format {
+        MessageFormat::String => Value::String(raw.clone()),
+        MessageFormat::Array => segments.iter().map(cqcode::Segment::to_json).collect(),
+    };
+    (message, raw)
+}
+
+/// 连接建立后发送的生命周期事件
+pub fn lifecycle_connect(self_id: u64) -> Value {
+    json!({
+        "time": unix_now(),
+        "self_id": self_id,
+        "post_type": "meta_event",
+        "meta_event_type": "lifecycle",
+        "sub_type": "connect",
+    })
+}
+
+/// 可以撤回的消息，分配 `message_id` 用
+pub(crate) fn receipt_of(event: &Event) -> Option<MessageReceipt> {
+    match event {
+        Event::GroupMessage(e) => Some(MessageReceipt {
+            target: MessageTarget::Group(e.group_code),
+            seq: e.seq,
+            random: e.random,
+            time: e.time,
+        }),
+        _ => None,
+    }
+}
+
+/// 申请事件的 `flag`，处理申请时用于找回原事件
+pub(crate) fn request_flag(event: &Event) -> Option<String> {
+    match event {
+        Event::NewFriendRequest(e) => Some(e.request_id.to_string()),
+        Event::MemberJoinRequest(e) => Some(e.request_id.to_string()),
+        Event::BotInvitedJoinGroupRequest(e) => Some(e.request_id.to_string()),
+        _ => None,
+    }
+}
+
+/// 转换为 OneBot 事件，没有对应事件类型时返回 `None`
+pub fn to_json(
+    self_id: u64,
+    event: &Event,
+    message_id: Option<i32>,
+    format: MessageFormat,
+) -> Option<Value> {
+    let mut json = match event {
+        Event::GroupMessage(e) => {
+            let (message, raw) = message_fields(&e.chain, format);
+            json!({
+                "time": e.time,
+                "post_type": "message",
+                "message_type": "group",
+                "sub_type": "normal",
+                "message_id": message_id,
+                "group_id": e.group_code,
+                "user_id": e.sender_uin,
+                "anonymous": null,
+                "message": message,
+                "raw_message": raw,
+                "font": 0,
+                "sender": {
+                    "user_id": e.sender_uin,
+                    "nickname": e.sender_name,
+                    "card": e.sender_name,
+                },
+            })
+        }
+        // go-cqhttp 的扩展事件，bot 在其他设备上发送的消息
+        Event::SelfMessage(e) => {
+            let (message, raw) = message_fields(&e.chain, format);
+            let mut json = json!({
+                "time": e.time,
+                "post_type": "message_sent",
+                "user_id": self_id,
+                "message": message,
+                "raw_message": raw,
+                "font": 0,
+            });
+            match e.target {
+                MessageTarget::Group(code) => {
+                    json["message_type"] = "group".into();
+                    json["group_id"] = code.into();
+                }
+                MessageTarget::Friend(uin) => {
+                    json["message_type"] = "private".into();
+                    json["target_id"] = uin.into();
+                }
+            }
+            json
+        }
+        Event::NewFriendRequest(e) => json!({
+            "post_type": "request",
+            "request_type": "friend",
+            "user_id": e.requester_uin,
+            "comment": e.message,
+            "flag": e.request_id.to_string(),
+        }),
+        Event::MemberJoinRequest(e) => json!({
+            "post_type": "request",
+            "request_type": "group",
+            "sub_type": "add",
+            "group_id": e.group_code,
+            "user_id": e.requester_uin,
+            "comment": e.message,
+            "flag": e.request_id.to_string(),
+        }),
+        Event::BotInvitedJoinGroupRequest(e) => json!({
+            "post_type": "request",
+            "request_type": "group",
+            "sub_type": "invite",
+            "group_id": e.group_code,
+            "user_id": e.invitor_uin,
+            "comment": "",
+            "flag": e.request_id.to_string(),
+        }),
+        Event::MemberJoin(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_increase",
+            "sub_type": "approve",
+            "group_id": e.group_code,
+            "operator_id": 0,
+            "user_id": e.member_uin,
+        }),
+        Event::MemberLeave(e) => {
+            let sub_type = match e.operator_uin {
+                None => "leave",
+                Some(_) if e.member_uin == self_id => "kick_me",
+                Some(_) => "kick",
+            };
+            json!({
+                "post_type": "notice",
+                "notice_type": "group_decrease",
+                "sub_type": sub_type,
+                "group_id": e.group_code,
+                "operator_id": e.operator_uin.unwrap_or(e.member_uin),
+                "user_id": e.member_uin,
+            })
+        }
+        Event::MemberPermissionChange(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_admin",
+            "sub_type": if e.is_admin { "set" } else { "unset" },
+            "group_id": e.group_code,
+            "user_id": e.member_uin,
+        }),
+        Event::MemberMute(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_ban",
+            "sub_type": if e.duration > 0 { "ban" } else { "lift_ban" },
+            "group_id": e.group_code,
+            "operator_id": e.operator_uin,
+            "user_id": e.member_uin,
+            "duration": e.duration,
+        }),
+        // 全员禁言以 user_id 为 0 表示
+        Event::GroupMuteAll(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_ban",
+            "sub_type": if e.enabled { "ban" } else { "lift_ban" },
+            "group_id": e.group_code,
+            "operator_id": e.operator_uin,
+            "user_id": 0,
+            "duration": 0,
+        }),
+        Event::FriendAdded(e) => json!({
+            "post_type": "notice",
+            "notice_type": "friend_add",
+            "user_id": e.uin,
+        }),
+        Event::Nudge(e) => {
+            let mut json = json!({
+                "post_type": "notice",
+                "notice_type": "notify",
+                "sub_type": "poke",
+                "user_id": e.sender_uin,
+                "target_id": e.receiver_uin,
+            });
+            if let Some(code) = e.group_code {
+                json["group_id"] = code.into();
+            }
+            json
+        }
+        _ => return None,
+    };
+    json["self_id"] = self_id.into();
+    if json.get("time").is_none() {
+        json["time"] = unix_now().into();
+    }
+    Some(json)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{group::MemberLeaveEvent, message::GroupMessageEvent};
+
+    #[test]
+    fn test_group_message_to_json() {
+        let event = Event::GroupMessage(GroupMessageEvent {
+            group_code: 10001,
+            sender_uin: 10010,
+            sender_name: "card".to_string(),
+            seq: 1,
+            random: 2,
+            time: 1700000000,
+            chain: MessageChain::new().with("a[b]"),
+        });
+        let json = to_json(10086, &event, Some(5), MessageFormat::String).unwrap();
+        assert_eq!(json["message"], "a&#91;b&#93;");
+        assert_eq!(json["message_id"], 5);
+        assert_eq!(json["self_id"], 10086);
+
+        let json = to_json(10086, &event, Some(5), MessageFormat::Array).unwrap();
+        assert_eq!(json["message"][0]["data"]["text"], "a[b]");
+    }
+
+    #[test]
+    fn test_kick_me() {
+        let event = Event::MemberLeave(MemberLeaveEvent {
+            group_code: 10001,
+            member_uin: 10086,
+            operator_uin: Some(10010),
+        });
+        let json = to_json(10086, &event, None, MessageFormat::String).unwrap();
+        assert_eq!(json["sub_type"], "kick_me");
+        assert!(json["time"].as_u64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,142 @@
+//! OneBot v11 适配器，通过 HTTP、正向 WebSocket 与反向 WebSocket 提供 API 与事件上报
+//! [参考](https://github.com/botuniverse/onebot-11)
+//!
+//! 接收数据包的循环不在此模块中，收到的 [`Event`] 需要通过 [`OneBot::push_event`] 交给适配器
+//!
+//! 目前只能解析群消息，不会上报 `message_type` 为 `private` 的消息事件，
+//! `send_private_msg` 等发送接口不受影响
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::{
+    adapter::{MessageStore, StoredReceipt},
+    events::Event,
+    network::{highway::HighwaySession, message::MessageReceipt, SsoSender},
+};
+
+pub mod action;
+pub mod cqcode;
+pub mod event;
+pub mod server;
+
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// 上报消息时 `message` 字段的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// CQ 码字符串
+    #[default]
+    String,
+    /// 消息段数组
+    Array,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OneBotConfig {
+    /// 设置后所有连接都需要携带 `Authorization: Bearer <token>` 或 `access_token` 参数
+    pub access_token: Option<String>,
+    /// HTTP POST 上报时用于计算 `X-Signature` 的密钥
+    pub secret: Option<String>,
+    pub message_format: MessageFormat,
+}
+
+pub struct OneBot<S> {
+    sender: S,
+    uin: u64,
+    app_id: u32,
+    /// 未设置时无法发送图片
+    highway: Option<HighwaySession>,
+    config: OneBotConfig,
+    seq: AtomicU32,
+    messages: Mutex<MessageStore>,
+    /// 尚未处理的好友申请与加群申请，以 `flag` 为键
+    requests: Mutex<HashMap<String, Event>>,
+    events: broadcast::Sender<Value>,
+}
+
+impl<S: SsoSender> OneBot<S> {
+    pub fn new(sender: S, uin: u64, app_id: u32, config: OneBotConfig) -> Self {
+        Self {
+            sender,
+            uin,
+            app_id,
+            highway: None,
+            config,
+            seq: AtomicU32::new(rand::random::<u16>() as u32),
+            messages: Mutex::new(MessageStore::default()),
+            requests: Mutex::new(HashMap::new()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn with_highway(mut self, highway: HighwaySession) -> Self {
+        self.highway = Some(highway);
+        self
+    }
+
+    pub fn self_id(&self) -> u64 {
+        self.uin
+    }
+
+    pub fn config(&self) -> &OneBotConfig {
+        &self.config
+    }
+
+    /// 订阅转换后的事件，每个连接各自订阅
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    /// 转换并广播事件，无法用 OneBot 表示的事件将被忽略
+    pub fn push_event(&self, event: &Event) {
+        let message_id = event::receipt_of(event).map(|r| self.store_receipt(r));
+        if let Event::SelfMessage(e) = event {
+            let seq = e.seq as u32;
+            self.messages
+                .lock()
+                .unwrap()
+                .confirm(e.target, e.random, seq);
+        }
+        if let Some(flag) = event::request_flag(event) {
+            self.requests.lock().unwrap().insert(flag, event.clone());
+        }
+        if let Some(json) = event::to_json(self.uin, event, message_id, self.config.message_format)
+        {
+            // 没有任何连接时发送会失败，直接丢弃即可
+            let _ = self.events.send(json);
+        }
+    }
+
+    fn next_seq(&self) -> u32 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn store_receipt(&self, receipt: MessageReceipt) -> i32 {
+        self.messages.lock().unwrap().insert(receipt)
+    }
+
+    fn store_sent(&self, receipt: MessageReceipt) -> i32 {
+        self.messages.lock().unwrap().insert_sent(receipt)
+    }
+
+    fn receipt(&self, message_id: i32) -> Option<StoredReceipt> {
+        self.messages.lock().unwrap().get(message_id)
+    }
+
+    fn find_request(&self, flag: &str) -> Option<Event> {
+        self.requests.lock().unwrap().get(flag).cloned()
+    }
+
+    fn remove_request(&self, flag: &str) {
+        self.requests.lock().unwrap().remove(flag);
+    }
+}
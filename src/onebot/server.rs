@@ -0,0 +1,470 @@
+//! OneBot 的通信方式：HTTP API、HTTP POST 上报、正向 WebSocket 与反向 WebSocket
+//! [参考](https://github.com/botuniverse/onebot-11/tree/master/communication)
+
+use std::{collections::HashMap, io, net::TcpListener, sync::Arc, time::Duration};
+
+use axum::{
+    body::Bytes,
+    extract::{ws, Path, Query, State, WebSocketUpgrade},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, http::HeaderValue};
+
+use crate::{network::SsoSender, utils::crypto::sha1_digest};
+
+use super::{
+    action::{ActionError, ActionRequest, ActionResponse},
+    event::lifecycle_connect,
+    OneBot,
+};
+
+/// 连接的用途，与 go-cqhttp 的路径约定一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// 同时处理 API 与事件
+    Universal,
+    Api,
+    Event,
+}
+
+/// 两种 WebSocket 实现的消息类型
+trait WsMessage: Sized {
+    fn text(text: String) -> Self;
+    /// 文本帧返回内容，关闭帧返回 `Err`，其余忽略
+    fn into_text(self) -> Result<Option<String>, ()>;
+}
+
+impl WsMessage for ws::Message {
+    fn text(text: String) -> Self {
+        ws::Message::Text(text)
+    }
+
+    fn into_text(self) -> Result<Option<String>, ()> {
+        match self {
+            ws::Message::Text(text) => Ok(Some(text)),
+            ws::Message::Close(_) => Err(()),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl WsMessage for tungstenite::Message {
+    fn text(text: String) -> Self {
+        tungstenite::Message::Text(text)
+    }
+
+    fn into_text(self) -> Result<Option<String>, ()> {
+        match self {
+            tungstenite::Message::Text(text) => Ok(Some(text)),
+            tungstenite::Message::Close(_) => Err(()),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// 检查 `Authorization` 头或 `access_token` 参数，缺少时为 401，不匹配时为 403
+fn authorize(
+    token: Option<&str>,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<(), StatusCode> {
+    let Some(token) = token else {
+        return Ok(());
+    };
+    let provided = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.strip_prefix("Bearer ")
+                .or_else(|| v.strip_prefix("Token "))
+                .unwrap_or(v)
+        })
+        .or_else(|| query.get("access_token").map(String::as_str));
+    match provided {
+        None => Err(StatusCode::UNAUTHORIZED),
+        Some(provided) if provided != token => Err(StatusCode::FORBIDDEN),
+        Some(_) => Ok(()),
+    }
+}
+
+async fn handle_text<S: SsoSender + Sync>(bot: &OneBot<S>, text: &str) -> String {
+    let rsp = match serde_json::from_str::<ActionRequest>(text) {
+        Ok(req) => bot.handle_action(req).await,
+        Err(e) => ActionResponse::failed(ActionError::BadParams(e.to_string())),
+    };
+    serde_json::to_string(&rsp).unwrap_or_default()
+}
+
+/// 在一个 WebSocket 连接上处理 API 调用并推送事件，直到连接断开
+async fn run_connection<S, W, M, E>(bot: Arc<OneBot<S>>, role: Role, socket: W)
+where
+    S: SsoSender + Sync,
+    W: Stream<Item = Result<M, E>> + Sink<M> + Unpin,
+    M: WsMessage,
+{
+    let (mut tx, mut rx) = socket.split();
+    let mut events = bot.subscribe();
+    if role != Role::Api {
+        let connect = lifecycle_connect(bot.self_id()).to_string();
+        if tx.send(M::text(connect)).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let out = tokio::select! {
+            msg = rx.next() => match msg.map(|m| m.map(WsMessage::into_text)) {
+                Some(Ok(Ok(Some(text)))) if role != Role::Event => handle_text(&bot, &text).await,
+                Some(Ok(Ok(_))) => continue,
+                _ => return,
+            },
+            event = events.recv(), if role != Role::Api => match event {
+                Ok(event) => event.to_string(),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+        };
+        if tx.send(M::text(out)).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn ws_handler<S: SsoSender + Send + Sync + 'static>(
+    bot: Arc<OneBot<S>>,
+    role: Role,
+    headers: HeaderMap,
+    query: HashMap<String, String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if let Err(status) = authorize(bot.config().access_token.as_deref(), &headers, &query) {
+        return status.into_response();
+    }
+    upgrade.on_upgrade(move |socket| run_connection(bot, role, socket))
+}
+
+async fn ws_universal<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<OneBot<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Role::Universal, headers, query, upgrade).await
+}
+
+async fn ws_api<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<OneBot<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Role::Api, headers, query, upgrade).await
+}
+
+async fn ws_event<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<OneBot<S>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    ws_handler(bot, Role::Event, headers, query, upgrade).await
+}
+
+/// `/:action`，参数来自 query string 与 JSON body，两者都有时以 body 为准
+async fn http_action<S: SsoSender + Send + Sync + 'static>(
+    State(bot): State<Arc<OneBot<S>>>,
+    Path(action): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(status) = authorize(bot.config().access_token.as_deref(), &headers, &query) {
+        return status.into_response();
+    }
+    let mut params = query
+        .into_iter()
+        .filter(|(k, _)| k != "access_token")
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect::<serde_json::Map<_, _>>();
+    if !body.is_empty() {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Object(body)) => params.extend(body),
+            _ => return StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+    let req = ActionRequest {
+        action,
+        params: Value::Object(params),
+        echo: None,
+    };
+    let rsp = bot.handle_action(req).await;
+    let status = match rsp.retcode {
+        1404 => StatusCode::NOT_FOUND,
+        _ => StatusCode::OK,
+    };
+    (status, Json(rsp)).into_response()
+}
+
+/// 在同一端口上提供 HTTP API 与正向 WebSocket
+///
+/// WebSocket 路径为 `/`（API 与事件）、`/api` 与 `/event`，其余路径作为 HTTP API 的 action
+pub async fn serve<S: SsoSender + Send + Sync + 'static>(
+    bot: Arc<OneBot<S>>,
+    listener: TcpListener,
+) -> io::Result<()> {
+    let app = Router::new()
+        .route("/", get(ws_universal::<S>))
+        .route("/api", get(ws_api::<S>))
+        .route("/event", get(ws_event::<S>))
+        .route("/:action", get(http_action::<S>).post(http_action::<S>))
+        .with_state(bot);
+    listener.set_nonblocking(true)?;
+    axum::Server::from_tcp(listener)
+        .map_err(io::Error::other)?
+        .serve(app.into_make_service())
+        .await
+        .map_err(io::Error::other)
+}
+
+/// 连接反向 WebSocket，断开后每隔 `reconnect_interval` 重试，不会返回
+pub async fn connect_reverse_ws<S: SsoSender + Send + Sync + 'static>(
+    bot: Arc<OneBot<S>>,
+    url: &str,
+    reconnect_interval: Duration,
+) {
+    loop {
+        if let Ok(mut req) = url.into_client_request() {
+            let headers = req.headers_mut();
+            headers.insert("X-Self-ID", bot.self_id().into());
+            headers.insert("X-Client-Role", HeaderValue::from_static("Universal"));
+            if let Some(token) = &bot.config().access_token {
+                if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+                    headers.insert("Authorization", value);
+                }
+            }
+            if let Ok((socket, _)) = tokio_tungstenite::connect_async(req).await {
+                run_connection(bot.clone(), Role::Universal, socket).await;
+            }
+        }
+        tokio::time::sleep(reconnect_interval).await;
+    }
+}
+
+fn hmac_sha1(key: &[u8], data: &[u8]) -> [u8; 20] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..20].copy_from_slice(&sha1_digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = block.iter().map(|b| b ^ 0x36).collect::<Vec<_>>();
+    inner.extend_from_slice(data);
+    let mut outer = block.iter().map(|b| b ^ 0x5c).collect::<Vec<_>>();
+    outer.extend_from_slice(&sha1_digest(&inner));
+    sha1_digest(&outer)
+}
+
+/// 通过 HTTP POST 将事件上报到 `url`，不处理快速操作，事件通道关闭后返回
+///
+/// 配置了 `secret` 时附带 `X-Signature: sha1=<HMAC-SHA1>`
+pub async fn post_events<S: SsoSender>(bot: Arc<OneBot<S>>, url: &str) {
+    let client = reqwest::Client::new();
+    let mut events = bot.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event.to_string(),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+        let mut req = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Self-ID", bot.self_id());
+        if let Some(secret) = &bot.config().secret {
+            let sig = hmac_sha1(secret.as_bytes(), event.as_bytes());
+            req = req.header("X-Signature", format!("sha1={}", hex::encode(sig)));
+        }
+        // 上报失败时丢弃该事件
+        let _ = req.body(event).send().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        events::{request::NewFriendRequestEvent, Event},
+        network::test::MockSender,
+        onebot::OneBotConfig,
+    };
+    use axum::routing::post;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    fn bot(responses: Vec<Vec<u8>>) -> Arc<OneBot<MockSender>> {
+        let config = OneBotConfig {
+            access_token: Some("token".to_string()),
+            secret: Some("key".to_string()),
+            ..Default::default()
+        };
+        Arc::new(OneBot::new(MockSender::new(responses), 10086, 16, config))
+    }
+
+    fn friend_request() -> Event {
+        Event::NewFriendRequest(NewFriendRequestEvent {
+            request_id: 1,
+            message: "hi".to_string(),
+            requester_uin: 10010,
+            requester_nick: String::new(),
+        })
+    }
+
+    async fn start(bot: Arc<OneBot<MockSender>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(bot, listener));
+        addr.to_string()
+    }
+
+    #[test]
+    fn test_hmac_sha1() {
+        let sig = hmac_sha1(b"key", b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(hex::encode(sig), "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9");
+    }
+
+    #[tokio::test]
+    async fn test_http_api() {
+        let addr = start(bot(Vec::new())).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/get_status", addr);
+
+        let rsp = client.post(&url).send().await.unwrap();
+        assert_eq!(rsp.status(), StatusCode::UNAUTHORIZED);
+        let rsp = client.post(&url).bearer_auth("bad").send().await.unwrap();
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+        let rsp: Value = client
+            .post(&url)
+            .bearer_auth("token")
+            .json(&json!({}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(rsp["data"]["online"], true);
+
+        let rsp = client
+            .get(format!("http://{}/no_such_action?access_token=token", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(rsp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_forward_ws() {
+        let bot = bot(Vec::new());
+        let addr = start(bot.clone()).await;
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/?access_token=token", addr))
+                .await
+                .unwrap();
+        let recv = |msg: Option<Result<tungstenite::Message, _>>| {
+            serde_json::from_str::<Value>(&msg.unwrap().unwrap().into_text().unwrap()).unwrap()
+        };
+        assert_eq!(recv(socket.next().await)["meta_event_type"], "lifecycle");
+
+        bot.push_event(&friend_request());
+        assert_eq!(recv(socket.next().await)["request_type"], "friend");
+
+        let req = json!({ "action": "get_version_info", "echo": 7 }).to_string();
+        socket.send(tungstenite::Message::Text(req)).await.unwrap();
+        let rsp = recv(socket.next().await);
+        assert_eq!(rsp["echo"], 7);
+        assert_eq!(rsp["data"]["protocol_version"], "v11");
+
+        assert!(tokio_tungstenite::connect_async(format!("ws://{}/", addr))
+            .await
+            .is_err());
+    }
+
+    // 握手回调的签名由 tungstenite 决定
+    #[allow(clippy::result_large_err)]
+    #[tokio::test]
+    async fn test_reverse_ws() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        let bot = bot(Vec::new());
+        let task = tokio::spawn({
+            let bot = bot.clone();
+            async move { connect_reverse_ws(bot, &url, Duration::from_millis(100)).await }
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut headers = None;
+        let mut socket = tokio_tungstenite::accept_hdr_async(
+            stream,
+            |req: &tungstenite::handshake::server::Request, rsp| {
+                headers = Some(req.headers().clone());
+                Ok(rsp)
+            },
+        )
+        .await
+        .unwrap();
+        let headers = headers.unwrap();
+        assert_eq!(headers["X-Self-ID"], "10086");
+        assert_eq!(headers["Authorization"], "Bearer token");
+
+        let connect = socket.next().await.unwrap().unwrap();
+        assert!(connect.into_text().unwrap().contains("lifecycle"));
+        let req = json!({ "action": "can_send_image", "echo": "x" }).to_string();
+        socket.send(tungstenite::Message::Text(req)).await.unwrap();
+        let rsp = socket.next().await.unwrap().unwrap().into_text().unwrap();
+        let rsp = serde_json::from_str::<Value>(&rsp).unwrap();
+        assert_eq!(
+            (rsp["echo"].clone(), rsp["data"]["yes"].clone()),
+            (json!("x"), json!(false))
+        );
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_post_events() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/",
+            post(move |headers: HeaderMap, body: String| async move {
+                tx.send((headers, body)).unwrap();
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let bot = bot(Vec::new());
+        let task = tokio::spawn({
+            let bot = bot.clone();
+            async move { post_events(bot, &url).await }
+        });
+        // 等待上报任务订阅事件
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        bot.push_event(&friend_request());
+
+        let (headers, body) = rx.recv().await.unwrap();
+        let sig = format!("sha1={}", hex::encode(hmac_sha1(b"key", body.as_bytes())));
+        assert_eq!(headers["X-Signature"], sig.as_str());
+        assert_eq!(headers["X-Self-ID"], "10086");
+        assert!(body.contains("\"flag\":\"1\""));
+        task.abort();
+    }
+}
@@ -1,10 +1,21 @@
 use std::fmt::Debug;
 use std::{fmt::Display, io};
 
+use md5::{Digest, Md5};
+use sha1::Sha1;
+
 pub mod tea;
 
 pub mod ecdh;
 
+pub fn md5_digest(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+pub fn sha1_digest(data: &[u8]) -> [u8; 20] {
+    Sha1::digest(data).into()
+}
+
 pub enum CryptoError {
     Io(io::Error),
     Size(